@@ -1,14 +1,15 @@
 use anyhow::{anyhow, Result};
-use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyType};
 use regex::RegexSet;
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::fsutil::to_lexical_normal;
+use crate::fsutil::{to_lexical_absolute, to_lexical_normal};
+use crate::list::DEFAULT_REMOVAL_PREFIX;
+use crate::refs::{RenderOpts, RESOLVE_MAX_DEPTH};
+use crate::types::{Mapping, Value};
 
 /// Flags to change reclass-rs behavior to be compaible with Python reclass
 #[pyclass(eq, eq_int)]
@@ -44,8 +45,76 @@ impl TryFrom<&str> for CompatFlag {
     }
 }
 
+/// Policies for handling user-defined values for the automatic `_reclass_` parameter
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum ReclassMetaPolicy {
+    /// User-defined values for `_reclass_` are merged with the automatic parameter. This matches
+    /// Python reclass's behavior.
+    #[default]
+    Merge,
+    /// User-defined values for `_reclass_` are merged with the automatic parameter, but rendering
+    /// a node generates a warning if the node or any of its classes define the parameter.
+    Warn,
+    /// User-defined values for `_reclass_` replace the automatic parameter.
+    Override,
+    /// Parameter `_reclass_` is reserved, and rendering a node returns an error if the node or
+    /// any of its classes define the parameter.
+    Reserve,
+}
+
+impl TryFrom<&str> for ReclassMetaPolicy {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "merge" | "Merge" => Ok(Self::Merge),
+            "warn" | "Warn" => Ok(Self::Warn),
+            "override" | "Override" => Ok(Self::Override),
+            "reserve" | "Reserve" => Ok(Self::Reserve),
+            _ => Err(anyhow!("Unknown policy for parameter _reclass_ '{value}'")),
+        }
+    }
+}
+
+/// Strategies for handling nodes or classes which are defined in both a `.yml` and a `.yaml` file
+#[pyclass(eq, eq_int)]
+#[derive(Clone, Copy, Debug, Default, Hash, Eq, PartialEq)]
+pub enum DuplicateEntityStrategy {
+    /// Discovering the inventory returns an error. This matches Python reclass's behavior.
+    #[default]
+    Error,
+    /// The definition in the `.yml` file is used.
+    PreferYml,
+    /// The definition in the `.yaml` file is used.
+    PreferYaml,
+}
+
+impl DuplicateEntityStrategy {
+    /// Returns the file extension which is preferred by the strategy, if any.
+    pub(crate) fn preferred_extension(self) -> Option<&'static str> {
+        match self {
+            Self::Error => None,
+            Self::PreferYml => Some("yml"),
+            Self::PreferYaml => Some("yaml"),
+        }
+    }
+}
+
+impl TryFrom<&str> for DuplicateEntityStrategy {
+    type Error = anyhow::Error;
+    fn try_from(value: &str) -> Result<Self> {
+        match value {
+            "error" | "Error" => Ok(Self::Error),
+            "prefer_yml" | "PreferYml" => Ok(Self::PreferYml),
+            "prefer_yaml" | "PreferYaml" => Ok(Self::PreferYaml),
+            _ => Err(anyhow!("Unknown duplicate entity strategy '{value}'")),
+        }
+    }
+}
+
 #[pyclass]
 #[derive(Clone, Debug, Default)]
+#[allow(clippy::struct_excessive_bools)]
 pub struct Config {
     /// Base path of the inventory
     #[pyo3(get)]
@@ -64,14 +133,231 @@ pub struct Config {
     /// Whether to treat nested files in `nodes_path` as node definitions
     #[pyo3(get)]
     pub compose_node_name: bool,
-    /// Python Reclass compatibility flags. See `CompatFlag` for available flags.
+    /// Whether to render the `uri` field of nodes and classes relative to `inventory_path`
+    /// instead of as an absolute path
+    #[pyo3(get)]
+    pub relative_uri: bool,
+    /// Whether to return an error when files with unsupported extensions are found in
+    /// `nodes_path` or `classes_path` during discovery
+    #[pyo3(get)]
+    pub strict_discovery: bool,
+    /// Patterns for files which are ignored when `strict_discovery` is enabled. Patterns are
+    /// matched against the file's path relative to `nodes_path` or `classes_path`.
+    #[pyo3(get)]
+    strict_discovery_ignore_regexp: Vec<String>,
+    strict_discovery_ignore_regexset: RegexSet,
+    /// Whether discovered node and class paths are normalized in parallel. This can speed up
+    /// discovery for large inventories, especially on networked filesystems.
+    #[pyo3(get)]
+    pub parallel_discovery: bool,
+    /// Whether to return an error with a suggestion for absolute class references (without leading
+    /// dot) which don't exist, if a class with the same name exists relative to the including
+    /// class.
+    #[pyo3(get)]
+    pub strict_relative_classes: bool,
+    /// Whether class names are matched case-insensitively. Useful for inventories which are
+    /// maintained on case-insensitive filesystems. Discovered class names are lowercased, and
+    /// classes whose names only differ in case are treated as duplicates.
+    #[pyo3(get)]
+    pub case_insensitive_classes: bool,
+    /// Maximum number of layers which can be merged for a single parameter key before rendering
+    /// a node returns an error. `None` means unlimited.
+    #[pyo3(get)]
+    pub max_value_list_length: Option<usize>,
+    /// Whether merging two sequences only appends elements which aren't already present in the
+    /// sequence that's merged into. By default, sequences are concatenated, which matches Python
+    /// reclass's behavior.
+    #[pyo3(get)]
+    pub unique_sequence_merge: bool,
+    /// Whether merging a value over a value of an incompatible type (e.g. a string over a mapping,
+    /// or a mapping over a sequence) replaces the existing value. By default, such merges return
+    /// an error, which matches Python reclass's behavior.
+    #[pyo3(get)]
+    pub type_changing_merge: bool,
+    /// Maximum recursion depth for resolving references. Rendering a node whose references are
+    /// nested deeper than this limit returns an error. Defaults to 64.
+    #[pyo3(get)]
+    pub resolve_max_depth: usize,
+    /// Whether references of the form `${foo:-default}` evaluate to `default` if `foo` doesn't
+    /// exist. An empty default value (`${foo:-}`) evaluates to null.
+    #[pyo3(get)]
+    pub shell_style_defaults: bool,
+    /// Whether reference path segment `*` collects the values of a Mapping (or the elements of a
+    /// Sequence) into a Sequence, e.g. `${foo:*}` evaluates to the list of values of mapping `foo`.
+    #[pyo3(get)]
+    pub wildcard_references: bool,
+    /// Whether classes whose name contains references are loaded after all other classes which
+    /// are included by the same node or class. This allows references in class names to use
+    /// parameters of classes which are listed after the class in question. Note that this changes
+    /// the merge order of the affected classes.
+    #[pyo3(get)]
+    pub defer_class_references: bool,
+    /// Whether parameter keys of the form `path:N`, e.g. `some_list:1`, override element `N` of
+    /// the sequence at `path` which was defined by previously merged classes.
+    #[pyo3(get)]
+    pub sequence_index_keys: bool,
+    /// Prefix which marks entries in a node's or class's `applications` list as removals.
+    /// Defaults to `~`, which matches Python reclass.
+    #[pyo3(get)]
+    pub application_removal_prefix: String,
+    /// How to handle user-defined values for the automatic `_reclass_` parameter. See
+    /// `ReclassMetaPolicy` for available policies.
+    #[pyo3(get)]
+    pub reclass_meta_policy: ReclassMetaPolicy,
+    /// How to handle nodes or classes which are defined in both a `.yml` and a `.yaml` file. See
+    /// `DuplicateEntityStrategy` for available strategies.
+    #[pyo3(get)]
+    pub duplicate_entity_strategy: DuplicateEntityStrategy,
+    /// Environment which is used for nodes which don't specify an environment
+    #[pyo3(get)]
+    pub default_environment: String,
+    /// Maximum directory depth for discovering nodes and classes. Entities which are located
+    /// directly in `nodes_path` or `classes_path` have depth 1. `None` means unlimited.
+    #[pyo3(get)]
+    pub max_discovery_depth: Option<usize>,
+    /// Number of threads used for rendering the full inventory. If set, the inventory is rendered
+    /// in a thread pool which is local to the render call instead of in the global thread pool.
+    /// `Some(0)` uses one thread per logical core of the system. `None` uses the global thread
+    /// pool.
+    #[pyo3(get)]
+    pub thread_count: Option<usize>,
+    /// File extensions (without leading dot) of node and class files. Defaults to `yml` and
+    /// `yaml`. Extensions may contain dots, e.g. `yaml.j2`.
+    #[pyo3(get)]
+    pub yaml_extensions: Vec<String>,
+    /// Whether to record a warning in the rendered node for parameters which are marked as
+    /// override (`~`), but don't have an inherited value to override.
+    #[pyo3(get)]
+    pub warn_dangling_overrides: bool,
+    /// Whether rendering a node returns an error if a class includes itself, either directly or
+    /// through other classes. By default, classes which are included again are skipped.
+    #[pyo3(get)]
+    pub class_include_cycle_error: bool,
+    /// Whether comments directly preceding top-level parameter keys are extracted from node and
+    /// class files. The extracted comments are available in `NodeInfo.parameter_docs`.
+    #[pyo3(get)]
+    pub extract_parameter_docs: bool,
+    /// Whether to record which class or node last set each leaf parameter while rendering a node.
+    /// The recorded sources are available in `NodeInfo.parameter_sources`.
+    #[pyo3(get)]
+    pub track_parameter_sources: bool,
+    /// Regex patterns for missing classes which are ignored when `ignore_class_notfound` is
+    /// enabled. See `ignore_class_notfound_glob` for glob patterns.
     #[pyo3(get)]
     ignore_class_notfound_regexp: Vec<String>,
+    /// Whether entries of `ignore_class_notfound_regexp` are glob patterns which must match the
+    /// full class name, e.g. `components.*`. If enabled, entries wrapped in slashes (`/.../`) are
+    /// still treated as regex patterns.
+    #[pyo3(get)]
+    ignore_class_notfound_glob: bool,
     ignore_class_notfound_regexset: RegexSet,
+    /// Python Reclass compatibility flags. See `CompatFlag` for available flags.
     #[pyo3(get)]
     pub compatflags: HashSet<CompatFlag>,
 }
 
+/// Deprecated config keys and the current key names which they're aliases for. Add an entry here
+/// when renaming a config key.
+const CONFIG_KEY_ALIASES: &[(&str, &str)] = &[];
+
+/// Returns the current name for config key `k` according to alias table `aliases`. If `k` is a
+/// deprecated alias, a deprecation warning is returned together with the current name.
+fn canonical_config_key<'a>(aliases: &[(&str, &'a str)], k: &'a str) -> (&'a str, Option<String>) {
+    match aliases.iter().find(|(alias, _)| *alias == k) {
+        Some((alias, key)) => (
+            key,
+            Some(format!(
+                "reclass-config.yml entry '{alias}' is deprecated, please use '{key}' instead"
+            )),
+        ),
+        None => (k, None),
+    }
+}
+
+/// Parses the provided config value as a boolean. Parameter `k` is the config key, and is used
+/// in error messages.
+fn bool_option(k: &str, v: &serde_yaml::Value) -> Result<bool> {
+    v.as_bool().ok_or(anyhow!(
+        "Expected value of config key '{k}' to be a boolean"
+    ))
+}
+
+/// Parses the provided config value as a string. Parameter `k` is the config key, and is used in
+/// error messages.
+fn string_option<'a>(k: &str, v: &'a serde_yaml::Value) -> Result<&'a str> {
+    v.as_str()
+        .ok_or(anyhow!("Expected value of config key '{k}' to be a string"))
+}
+
+/// Parses the provided config value as a non-negative integer which fits into a `usize`.
+/// Parameter `k` is the config key, and is used in error messages.
+fn usize_option(k: &str, v: &serde_yaml::Value) -> Result<usize> {
+    let n = v.as_u64().ok_or(anyhow!(
+        "Expected value of config key '{k}' to be a positive integer"
+    ))?;
+    Ok(usize::try_from(n)?)
+}
+
+/// Parses the provided config value as a list of strings. Parameter `k` is the config key, and
+/// is used in error messages.
+fn string_list(k: &str, v: &serde_yaml::Value) -> Result<Vec<String>> {
+    let list = v
+        .as_sequence()
+        .ok_or(anyhow!("Expected value of config key '{k}' to be a list"))?;
+    list.iter()
+        .map(|val| {
+            val.as_str()
+                .map(ToString::to_string)
+                .ok_or(anyhow!("Expected entry of '{k}' to be a string"))
+        })
+        .collect()
+}
+
+/// Converts an `ignore_class_notfound_regexp` entry into a regex pattern if
+/// `ignore_class_notfound_glob` is enabled. Entries which are wrapped in slashes (`/.../`) are
+/// regex patterns, all other entries are glob patterns which must match the full class name.
+fn class_pattern_regex(pattern: &str) -> String {
+    if pattern.len() >= 2 && pattern.starts_with('/') && pattern.ends_with('/') {
+        return pattern[1..pattern.len() - 1].to_string();
+    }
+    let mut re = String::from("^");
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            '[' if chars.clone().any(|c| c == ']') => {
+                re.push('[');
+                if chars.next_if_eq(&'!').is_some() {
+                    re.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        re.push('\\');
+                    }
+                    re.push(c);
+                }
+                re.push(']');
+            }
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+/// Returns an error if nodes path `npath` and classes path `cpath` are identical or if one of them
+/// is located in the other.
+fn check_non_overlapping(npath: &Path, cpath: &Path) -> Result<()> {
+    if npath == cpath || npath.starts_with(cpath) || cpath.starts_with(npath) {
+        return Err(anyhow!("Nodes and classes path must be non-overlapping."));
+    }
+    Ok(())
+}
+
 impl Config {
     /// Creates a new `Config` from the provided parameters.
     ///
@@ -104,77 +390,170 @@ impl Config {
             npath.push(p);
         } else {
             npath.push("nodes");
-        };
+        }
         let mut cpath = PathBuf::from(inventory_path);
         if let Some(p) = classes_path {
             cpath.push(p);
         } else {
             cpath.push("classes");
-        };
-        if npath == cpath || npath.starts_with(&cpath) || cpath.starts_with(&npath) {
-            return Err(anyhow!("Nodes and classes path must be non-overlapping."));
         }
+        check_non_overlapping(&npath, &cpath)?;
         Ok(Self {
             inventory_path: inventory_path.into(),
             nodes_path: to_lexical_normal(&npath, true).display().to_string(),
             classes_path: to_lexical_normal(&cpath, true).display().to_string(),
             ignore_class_notfound: ignore_class_notfound.unwrap_or(false),
             compose_node_name: false,
+            relative_uri: false,
+            strict_discovery: false,
+            strict_discovery_ignore_regexp: vec![],
+            strict_discovery_ignore_regexset: RegexSet::empty(),
+            parallel_discovery: false,
+            strict_relative_classes: false,
+            case_insensitive_classes: false,
+            max_value_list_length: None,
+            unique_sequence_merge: false,
+            type_changing_merge: false,
+            resolve_max_depth: RESOLVE_MAX_DEPTH,
+            shell_style_defaults: false,
+            wildcard_references: false,
+            defer_class_references: false,
+            sequence_index_keys: false,
+            application_removal_prefix: DEFAULT_REMOVAL_PREFIX.to_string(),
+            reclass_meta_policy: ReclassMetaPolicy::Merge,
+            duplicate_entity_strategy: DuplicateEntityStrategy::Error,
+            default_environment: "base".to_string(),
+            max_discovery_depth: None,
+            thread_count: None,
+            yaml_extensions: vec!["yml".to_string(), "yaml".to_string()],
+            warn_dangling_overrides: false,
+            class_include_cycle_error: false,
+            extract_parameter_docs: false,
+            track_parameter_sources: false,
             ignore_class_notfound_regexp: vec![".*".to_string()],
+            ignore_class_notfound_glob: false,
             ignore_class_notfound_regexset: RegexSet::new([".*"])?,
             compatflags: HashSet::new(),
         })
     }
 
-    fn set_option(
+    /// Returns the boolean config keys together with mutable references to their fields.
+    fn bool_fields(&mut self) -> [(&'static str, &mut bool); 18] {
+        [
+            ("ignore_class_notfound", &mut self.ignore_class_notfound),
+            ("compose_node_name", &mut self.compose_node_name),
+            ("relative_uri", &mut self.relative_uri),
+            ("strict_discovery", &mut self.strict_discovery),
+            ("parallel_discovery", &mut self.parallel_discovery),
+            ("strict_relative_classes", &mut self.strict_relative_classes),
+            (
+                "case_insensitive_classes",
+                &mut self.case_insensitive_classes,
+            ),
+            ("unique_sequence_merge", &mut self.unique_sequence_merge),
+            ("type_changing_merge", &mut self.type_changing_merge),
+            ("sequence_index_keys", &mut self.sequence_index_keys),
+            ("defer_class_references", &mut self.defer_class_references),
+            ("wildcard_references", &mut self.wildcard_references),
+            ("shell_style_defaults", &mut self.shell_style_defaults),
+            ("warn_dangling_overrides", &mut self.warn_dangling_overrides),
+            (
+                "class_include_cycle_error",
+                &mut self.class_include_cycle_error,
+            ),
+            ("extract_parameter_docs", &mut self.extract_parameter_docs),
+            ("track_parameter_sources", &mut self.track_parameter_sources),
+            (
+                "ignore_class_notfound_glob",
+                &mut self.ignore_class_notfound_glob,
+            ),
+        ]
+    }
+
+    /// Returns a mutable reference to the field for boolean config key `k`, or `None` if `k` isn't
+    /// a boolean config key.
+    fn bool_field(&mut self, k: &str) -> Option<&mut bool> {
+        self.bool_fields()
+            .into_iter()
+            .find_map(|(name, field)| (name == k).then_some(field))
+    }
+
+    /// Sets config option `k` to value `v`. Relative `nodes_uri` and `classes_uri` values are
+    /// resolved relative to `base_dir`.
+    pub(crate) fn set_option(
         &mut self,
-        cfg_path: &std::path::Path,
+        base_dir: &Path,
         k: &str,
         v: &serde_yaml::Value,
         verbose: bool,
     ) -> Result<()> {
+        let (k, warning) = canonical_config_key(CONFIG_KEY_ALIASES, k);
+        if let Some(warning) = warning {
+            if verbose {
+                eprintln!("{warning}");
+            }
+        }
+        if let Some(field) = self.bool_field(k) {
+            *field = bool_option(k, v)?;
+            return Ok(());
+        }
         let vstr = serde_yaml::to_string(v)?;
         let vstr = vstr.trim();
         match k {
             "nodes_uri" => {
-                cfg_path
-                    .with_file_name(vstr)
+                base_dir
+                    .join(vstr)
                     .to_str()
                     .ok_or(anyhow!("Can't create nodes path from config file"))?
                     .clone_into(&mut self.nodes_path);
             }
             "classes_uri" => {
-                cfg_path
-                    .with_file_name(vstr)
+                base_dir
+                    .join(vstr)
                     .to_str()
                     .ok_or(anyhow!("Can't create nodes path from config file"))?
                     .clone_into(&mut self.classes_path);
             }
-            "ignore_class_notfound" => {
-                self.ignore_class_notfound = v.as_bool().ok_or(anyhow!(
-                    "Expected value of config key 'ignore_class_notfound' to be a boolean"
-                ))?;
-            }
             "ignore_class_notfound_regexp" => {
-                let list = v.as_sequence().ok_or(anyhow!(
-                    "Expected value of config key 'ignore_class_notfound_regexp' to be a list"
-                ))?;
-                self.ignore_class_notfound_regexp.clear();
-                for val in list {
-                    self.ignore_class_notfound_regexp.push(
-                        val.as_str()
-                            .ok_or(anyhow!(
-                                "Expected entry of 'ignore_class_notfound_regexp' to be a string"
-                            ))?
-                            .to_string(),
-                    );
+                self.ignore_class_notfound_regexp = string_list(k, v)?;
+            }
+            "strict_discovery_ignore_regexp" => {
+                self.strict_discovery_ignore_regexp = string_list(k, v)?;
+                self.compile_strict_discovery_ignore_patterns()?;
+            }
+            "max_value_list_length" => {
+                self.max_value_list_length = Some(usize_option(k, v)?);
+            }
+            "reclass_meta_policy" => {
+                self.reclass_meta_policy = ReclassMetaPolicy::try_from(string_option(k, v)?)?;
+            }
+            "duplicate_entity_strategy" => {
+                self.duplicate_entity_strategy =
+                    DuplicateEntityStrategy::try_from(string_option(k, v)?)?;
+            }
+            "application_removal_prefix" => {
+                let prefix = string_option(k, v)?;
+                if prefix.is_empty() {
+                    return Err(anyhow!(
+                        "Expected value of config key '{k}' to be a non-empty string"
+                    ));
                 }
-                self.ignore_class_notfound_regexp.shrink_to_fit();
+                self.application_removal_prefix = prefix.to_string();
             }
-            "compose_node_name" => {
-                self.compose_node_name = v.as_bool().ok_or(anyhow!(
-                    "Expected value of config key 'compose_node_name' to be a boolean"
-                ))?;
+            "default_environment" => {
+                self.default_environment = string_option(k, v)?.to_string();
+            }
+            "resolve_max_depth" => {
+                self.resolve_max_depth = usize_option(k, v)?;
+            }
+            "max_discovery_depth" => {
+                self.max_discovery_depth = Some(usize_option(k, v)?);
+            }
+            "thread_count" => {
+                self.thread_count = Some(usize_option(k, v)?);
+            }
+            "yaml_extensions" => {
+                self.yaml_extensions = string_list(k, v)?;
             }
             "reclass_rs_compat_flags" => {
                 let flags = v.as_sequence().ok_or(anyhow!(
@@ -198,7 +577,7 @@ impl Config {
                     );
                 }
             }
-        };
+        }
 
         Ok(())
     }
@@ -215,18 +594,171 @@ impl Config {
 
         let cfg_file = std::fs::read_to_string(&cfg_path)?;
         let cfg: serde_yaml::Value = serde_yaml::from_str(&cfg_file)?;
+        let base_dir = cfg_path.parent().unwrap_or(Path::new(""));
+        self.load_from_value(base_dir, &cfg, verbose)
+    }
+
+    /// Creates a new `Config` for the inventory at `inventory_path` from an arbitrary
+    /// serializable value, e.g. a struct which derives `serde::Serialize` or a
+    /// `serde_json::Value`.
+    ///
+    /// The value must serialize to a mapping which uses the same keys as a reclass config file
+    /// (`nodes_uri`, `classes_uri`, `ignore_class_notfound`, etc.). Relative `nodes_uri` and
+    /// `classes_uri` values are resolved relative to `inventory_path`.
+    ///
+    /// If `verbose` is true, the method will print diagnostic messages for config options which
+    /// aren't implemented yet.
+    pub fn from_serializable<T: serde::Serialize>(
+        inventory_path: &str,
+        config: &T,
+        verbose: bool,
+    ) -> Result<Self> {
+        let cfg = serde_yaml::to_value(config)?;
+        let mut c = Config::new(Some(inventory_path), None, None, None)?;
+        c.load_from_value(Path::new(inventory_path), &cfg, verbose)?;
+        Ok(c)
+    }
+
+    /// Creates a new `Config` for the inventory at `inventory_path` from a YAML or JSON string
+    /// which uses the same keys as a reclass config file.
+    ///
+    /// See `Config::from_serializable()` for details.
+    pub fn from_yaml_str(inventory_path: &str, config: &str, verbose: bool) -> Result<Self> {
+        let cfg: serde_yaml::Value = serde_yaml::from_str(config)?;
+        Self::from_serializable(inventory_path, &cfg, verbose)
+    }
+
+    /// Loads config options from mapping `cfg`. Relative `nodes_uri` and `classes_uri` values are
+    /// resolved relative to `base_dir`.
+    fn load_from_value(
+        &mut self,
+        base_dir: &Path,
+        cfg: &serde_yaml::Value,
+        verbose: bool,
+    ) -> Result<()> {
         for (k, v) in cfg
             .as_mapping()
             .ok_or(anyhow!("Expected reclass config to be a Mapping"))?
         {
             let kstr = serde_yaml::to_string(k)?;
             let kstr = kstr.trim();
-            self.set_option(&cfg_path, kstr, v, verbose)?;
+            self.set_option(base_dir, kstr, v, verbose)?;
         }
         self.compile_ignore_class_notfound_patterns()?;
         Ok(())
     }
 
+    /// Checks that the configured nodes and classes paths are non-overlapping and that both
+    /// paths exist and are directories.
+    ///
+    /// `Config::new()` only checks the paths it was called with. Call this method after modifying
+    /// the paths, e.g. through `load_from_file()`, to catch misconfigurations before discovering
+    /// the inventory.
+    pub fn validate(&self) -> Result<()> {
+        let npath = to_lexical_normal(Path::new(&self.nodes_path), false);
+        let cpath = to_lexical_normal(Path::new(&self.classes_path), false);
+        check_non_overlapping(&npath, &cpath)?;
+        for (kind, path) in [("Nodes", &self.nodes_path), ("Classes", &self.classes_path)] {
+            if !Path::new(path).is_dir() {
+                return Err(anyhow!(
+                    "{kind} path '{path}' doesn't exist or isn't a directory"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns a summary of the effective configuration, e.g. for printing the configuration of
+    /// an inventory.
+    ///
+    /// In contrast to the `Config` fields, the summary holds the absolute nodes and classes paths,
+    /// the sorted list of active compatibility flags, and the regex patterns which were compiled
+    /// from `ignore_class_notfound_regexp` and `strict_discovery_ignore_regexp`.
+    pub fn effective(&self) -> Result<Mapping> {
+        let path = |p: &str| -> Result<Value> {
+            Ok(to_lexical_absolute(Path::new(p))?
+                .display()
+                .to_string()
+                .into())
+        };
+        let b = Value::Bool;
+        let opt = |v: Option<usize>| v.map_or(Value::Null, Value::from);
+        let mut compatflags = self
+            .compatflags
+            .iter()
+            .map(|f| format!("{f:?}"))
+            .collect::<Vec<_>>();
+        compatflags.sort();
+
+        let mut m = Mapping::new();
+        for (k, v) in [
+            ("inventory_path", path(&self.inventory_path)?),
+            ("nodes_path", path(&self.nodes_path)?),
+            ("classes_path", path(&self.classes_path)?),
+            ("ignore_class_notfound", b(self.ignore_class_notfound)),
+            (
+                "ignore_class_notfound_regexp",
+                self.ignore_class_notfound_regexp.clone().into(),
+            ),
+            (
+                "ignore_class_notfound_glob",
+                b(self.ignore_class_notfound_glob),
+            ),
+            (
+                "ignore_class_notfound_patterns",
+                self.ignore_class_notfound_regexset.patterns().into(),
+            ),
+            ("compose_node_name", b(self.compose_node_name)),
+            ("relative_uri", b(self.relative_uri)),
+            ("strict_discovery", b(self.strict_discovery)),
+            (
+                "strict_discovery_ignore_patterns",
+                self.strict_discovery_ignore_regexset.patterns().into(),
+            ),
+            ("parallel_discovery", b(self.parallel_discovery)),
+            ("strict_relative_classes", b(self.strict_relative_classes)),
+            ("case_insensitive_classes", b(self.case_insensitive_classes)),
+            ("max_value_list_length", opt(self.max_value_list_length)),
+            ("unique_sequence_merge", b(self.unique_sequence_merge)),
+            ("type_changing_merge", b(self.type_changing_merge)),
+            ("resolve_max_depth", self.resolve_max_depth.into()),
+            ("shell_style_defaults", b(self.shell_style_defaults)),
+            ("wildcard_references", b(self.wildcard_references)),
+            ("defer_class_references", b(self.defer_class_references)),
+            ("sequence_index_keys", b(self.sequence_index_keys)),
+            (
+                "application_removal_prefix",
+                self.application_removal_prefix.clone().into(),
+            ),
+            (
+                "reclass_meta_policy",
+                format!("{:?}", self.reclass_meta_policy).into(),
+            ),
+            (
+                "duplicate_entity_strategy",
+                format!("{:?}", self.duplicate_entity_strategy).into(),
+            ),
+            (
+                "default_environment",
+                self.default_environment.clone().into(),
+            ),
+            ("max_discovery_depth", opt(self.max_discovery_depth)),
+            ("thread_count", opt(self.thread_count)),
+            ("yaml_extensions", self.yaml_extensions.clone().into()),
+            ("warn_dangling_overrides", b(self.warn_dangling_overrides)),
+            (
+                "class_include_cycle_error",
+                b(self.class_include_cycle_error),
+            ),
+            ("extract_parameter_docs", b(self.extract_parameter_docs)),
+            ("track_parameter_sources", b(self.track_parameter_sources)),
+            ("compatflags", compatflags.into()),
+        ] {
+            m.insert(k.into(), v)?;
+        }
+        Ok(m)
+    }
+
     /// Returns the currently configured `ignore_class_notfound_regexp` pattern list.
     pub fn get_ignore_class_notfound_regexp(&self) -> &Vec<String> {
         &self.ignore_class_notfound_regexp
@@ -239,16 +771,87 @@ impl Config {
         self.compile_ignore_class_notfound_patterns()
     }
 
+    /// Updates whether `ignore_class_notfound_regexp` entries are treated as glob patterns and
+    /// ensures that the precompiled RegexSet is updated accordingly.
+    pub fn set_ignore_class_notfound_glob(&mut self, glob: bool) -> Result<()> {
+        self.ignore_class_notfound_glob = glob;
+        self.compile_ignore_class_notfound_patterns()
+    }
+
+    /// Returns the currently configured `strict_discovery_ignore_regexp` pattern list.
+    pub fn get_strict_discovery_ignore_regexp(&self) -> &Vec<String> {
+        &self.strict_discovery_ignore_regexp
+    }
+
+    /// Updates the saved strict_discovery_ignore_regexp pattern list with the provided list and
+    /// ensures that the precompiled RegexSet is updated to match the new pattern list.
+    pub fn set_strict_discovery_ignore_regexp(&mut self, patterns: Vec<String>) -> Result<()> {
+        self.strict_discovery_ignore_regexp = patterns;
+        self.compile_strict_discovery_ignore_patterns()
+    }
+
+    /// Returns the precompiled `strict_discovery_ignore_regexp` patterns if strict discovery is
+    /// enabled, and `None` otherwise.
+    pub(crate) fn strict_discovery_ignores(&self) -> Option<&RegexSet> {
+        if self.strict_discovery {
+            Some(&self.strict_discovery_ignore_regexset)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the directory depth to use for entity discovery, limiting `depth` to the configured
+    /// `max_discovery_depth`.
+    pub(crate) fn discovery_depth(&self, depth: usize) -> usize {
+        self.max_discovery_depth
+            .map_or(depth, |limit| depth.min(limit))
+    }
+
+    /// Returns the file extensions (without leading dot) of node and class files. If feature
+    /// `gzip` is enabled, the configured `yaml_extensions` are additionally recognized with a
+    /// `.gz` suffix, e.g. `yml.gz`.
+    pub(crate) fn entity_extensions(&self) -> Vec<String> {
+        let mut exts = self.yaml_extensions.clone();
+        if cfg!(feature = "gzip") {
+            exts.extend(self.yaml_extensions.iter().map(|ext| format!("{ext}.gz")));
+        }
+        exts
+    }
+
+    /// Returns the render options for rendering parameters with this config.
+    pub(crate) fn render_opts(&self) -> RenderOpts {
+        RenderOpts {
+            max_depth: self.resolve_max_depth,
+            unique_sequences: self.unique_sequence_merge,
+            shell_style_defaults: self.shell_style_defaults,
+            wildcard_refs: self.wildcard_references,
+            type_changing_merge: self.type_changing_merge,
+        }
+    }
+
     pub(crate) fn is_class_ignored(&self, cls: &str) -> bool {
         self.ignore_class_notfound && self.ignore_class_notfound_regexset.is_match(cls)
     }
 
-    fn compile_ignore_class_notfound_patterns(&mut self) -> Result<()> {
-        self.ignore_class_notfound_regexset = RegexSet::new(&self.ignore_class_notfound_regexp)
+    pub(crate) fn compile_ignore_class_notfound_patterns(&mut self) -> Result<()> {
+        let patterns = self.ignore_class_notfound_regexp.iter().map(|p| {
+            if self.ignore_class_notfound_glob {
+                class_pattern_regex(p)
+            } else {
+                p.clone()
+            }
+        });
+        self.ignore_class_notfound_regexset = RegexSet::new(patterns)
             .map_err(|e| anyhow!("while compiling ignore_class_notfound regex patterns: {e}"))?;
         Ok(())
     }
 
+    fn compile_strict_discovery_ignore_patterns(&mut self) -> Result<()> {
+        self.strict_discovery_ignore_regexset = RegexSet::new(&self.strict_discovery_ignore_regexp)
+            .map_err(|e| anyhow!("while compiling strict_discovery_ignore regex patterns: {e}"))?;
+        Ok(())
+    }
+
     /// Construct path to node from `self.inventory_path`, `self.nodes_path` and the provided path
     /// to the node relative to the inventory nodes directory.
     pub(crate) fn node_path(&self, npath: &PathBuf) -> PathBuf {
@@ -257,6 +860,22 @@ impl Config {
         invpath
     }
 
+    /// Renders the `yaml_fs://` URI for the entity at `path` relative to `self.inventory_path`.
+    ///
+    /// Returns an error if `path` isn't located in `self.inventory_path`.
+    pub(crate) fn relative_entity_uri(&self, path: &Path) -> Result<String> {
+        let invpath = to_lexical_absolute(Path::new(&self.inventory_path))?;
+        let abspath = to_lexical_absolute(path)?;
+        let relpath = abspath.strip_prefix(&invpath).map_err(|_| {
+            anyhow!(
+                "Can't render uri for {} relative to inventory path {}",
+                path.display(),
+                self.inventory_path
+            )
+        })?;
+        Ok(format!("yaml_fs://{}", relpath.display()))
+    }
+
     /// Construct path to class from `self.inventory_path`, `self.classes_path` and the provided
     /// path to the class relative to the inventory classes directory.
     pub(crate) fn class_path(&self, cpath: &PathBuf) -> PathBuf {
@@ -266,50 +885,6 @@ impl Config {
     }
 }
 
-#[pymethods]
-impl Config {
-    fn __repr__(&self) -> String {
-        format!("{self:#?}")
-    }
-
-    /// Creates a Config object based on the provided `inventory_path` and the config options
-    /// passed in the `config` Python dict. If `verbose` is set to `true`, reclass-rs will print
-    /// diagnostic messages for unknown config options.
-    ///
-    /// Returns a `Config` object or raises a `ValueError`.
-    #[classmethod]
-    #[pyo3(signature = (inventory_path, config, verbose=false))]
-    fn from_dict(
-        _cls: &Bound<'_, PyType>,
-        inventory_path: &str,
-        config: &Bound<'_, PyDict>,
-        verbose: bool,
-    ) -> PyResult<Self> {
-        let mut cfg = Config::new(Some(inventory_path), None, None, None).map_err(|e| {
-            PyValueError::new_err(format!(
-                "Failed to initialize reclass-rs config object: {e}"
-            ))
-        })?;
-
-        // `set_option()` expects `cfg_path` to be the path to the reclass config file. Since we're
-        // not actually reading from the file here, we need to push an arbitrary path segment so
-        // that `set_option()` will configure the `nodes_path` and `classes_path` fields correctly.
-        let mut cfg_path = PathBuf::from(inventory_path);
-        cfg_path.push("dummy");
-
-        for (k, v) in config {
-            let kstr = k.extract::<&str>()?;
-            let val: crate::types::Value = TryInto::try_into(v)?;
-            cfg.set_option(&cfg_path, kstr, &val.into(), verbose)
-                .map_err(|e| {
-                    PyValueError::new_err(format!("Error while setting option {kstr}: {e}"))
-                })?;
-        }
-
-        Ok(cfg)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -359,7 +934,7 @@ mod tests {
         let cfg = Config::new(Some("./inventory"), None, None, None).unwrap();
         assert_eq!(cfg.nodes_path, "./inventory/nodes");
         assert_eq!(cfg.classes_path, "./inventory/classes");
-        assert_eq!(cfg.ignore_class_notfound, false);
+        assert!(!cfg.ignore_class_notfound);
     }
 
     #[test]
@@ -368,7 +943,7 @@ mod tests {
             Config::new(Some("./inventory"), Some("targets"), Some("settings"), None).unwrap();
         assert_eq!(cfg.nodes_path, "./inventory/targets");
         assert_eq!(cfg.classes_path, "./inventory/settings");
-        assert_eq!(cfg.ignore_class_notfound, false);
+        assert!(!cfg.ignore_class_notfound);
     }
 
     #[test]
@@ -382,7 +957,100 @@ mod tests {
         .unwrap();
         assert_eq!(cfg.nodes_path, "./inventory/targets");
         assert_eq!(cfg.classes_path, "./inventory/classes");
-        assert_eq!(cfg.ignore_class_notfound, false);
+        assert!(!cfg.ignore_class_notfound);
+    }
+
+    #[test]
+    fn test_config_key_alias() {
+        let aliases = [("legacy_nodes_uri", "nodes_uri")];
+        assert_eq!(
+            canonical_config_key(&aliases, "nodes_uri"),
+            ("nodes_uri", None)
+        );
+        assert_eq!(
+            canonical_config_key(&aliases, "legacy_nodes_uri"),
+            (
+                "nodes_uri",
+                Some(
+                    "reclass-config.yml entry 'legacy_nodes_uri' is deprecated, please use \
+                    'nodes_uri' instead"
+                        .to_string()
+                )
+            )
+        );
+        assert_eq!(
+            canonical_config_key(CONFIG_KEY_ALIASES, "nodes_path"),
+            ("nodes_path", None)
+        );
+    }
+
+    #[test]
+    fn test_config_reclass_meta_policy() {
+        let mut cfg = Config::new(Some("./inventory"), None, None, None).unwrap();
+        assert_eq!(cfg.reclass_meta_policy, ReclassMetaPolicy::Merge);
+
+        let base_dir = Path::new("./inventory");
+        cfg.set_option(base_dir, "reclass_meta_policy", &"reserve".into(), false)
+            .unwrap();
+        assert_eq!(cfg.reclass_meta_policy, ReclassMetaPolicy::Reserve);
+
+        cfg.set_option(base_dir, "reclass_meta_policy", &"warn".into(), false)
+            .unwrap();
+        assert_eq!(cfg.reclass_meta_policy, ReclassMetaPolicy::Warn);
+
+        let err = cfg
+            .set_option(base_dir, "reclass_meta_policy", &"foo".into(), false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown policy for parameter _reclass_ 'foo'"
+        );
+    }
+
+    #[test]
+    fn test_config_duplicate_entity_strategy() {
+        let mut cfg = Config::new(Some("./inventory"), None, None, None).unwrap();
+        assert_eq!(
+            cfg.duplicate_entity_strategy,
+            DuplicateEntityStrategy::Error
+        );
+
+        let base_dir = Path::new("./inventory");
+        cfg.set_option(
+            base_dir,
+            "duplicate_entity_strategy",
+            &"prefer_yaml".into(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            cfg.duplicate_entity_strategy,
+            DuplicateEntityStrategy::PreferYaml
+        );
+
+        let err = cfg
+            .set_option(base_dir, "duplicate_entity_strategy", &"foo".into(), false)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Unknown duplicate entity strategy 'foo'");
+    }
+
+    #[test]
+    fn test_config_application_removal_prefix() {
+        let mut cfg = Config::new(Some("./inventory"), None, None, None).unwrap();
+        assert_eq!(cfg.application_removal_prefix, "~");
+
+        let base_dir = Path::new("./inventory");
+        cfg.set_option(base_dir, "application_removal_prefix", &"-".into(), false)
+            .unwrap();
+        assert_eq!(cfg.application_removal_prefix, "-");
+
+        let err = cfg
+            .set_option(base_dir, "application_removal_prefix", &"".into(), false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Expected value of config key 'application_removal_prefix' to be a non-empty string"
+        );
     }
 
     #[test]
@@ -397,4 +1065,155 @@ mod tests {
         assert!(cfg.ignore_class_notfound_regexset.is_match("baring"));
         assert!(!cfg.ignore_class_notfound_regexset.is_match("bazzer"));
     }
+
+    #[test]
+    fn test_config_ignore_class_notfound_glob_and_regex() {
+        let mut cfg = Config::new(Some("./inventory"), None, None, None).unwrap();
+        cfg.ignore_class_notfound = true;
+        assert!(cfg.is_class_ignored("foo.bar"));
+
+        cfg.set_ignore_class_notfound_regexp(vec!["foo.*".into(), "/^ba[rz]\\./".into()])
+            .unwrap();
+        // entries are regex patterns unless `ignore_class_notfound_glob` is enabled
+        assert!(cfg.is_class_ignored("fooxbar"));
+        assert!(!cfg.is_class_ignored("bar.qux"));
+
+        cfg.set_ignore_class_notfound_glob(true).unwrap();
+        assert!(cfg.is_class_ignored("foo.bar"));
+        assert!(cfg.is_class_ignored("foo.bar.baz"));
+        assert!(!cfg.is_class_ignored("fooxbar"));
+        assert!(!cfg.is_class_ignored("qux.foo.bar"));
+        assert!(cfg.is_class_ignored("bar.qux"));
+        assert!(cfg.is_class_ignored("baz.qux"));
+        assert!(!cfg.is_class_ignored("bax.qux"));
+    }
+
+    #[test]
+    fn test_class_pattern_regex() {
+        assert_eq!(class_pattern_regex("*"), "^.*$");
+        assert_eq!(class_pattern_regex("foo.?"), "^foo\\..$");
+        assert_eq!(class_pattern_regex("[!ab]x[c"), "^[^ab]x\\[c$");
+        assert_eq!(class_pattern_regex("/.*missing.*/"), ".*missing.*");
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let invpath = "./tests/inventory-validate";
+        let cfg = Config::new(Some(invpath), None, None, None).unwrap();
+        let valid = cfg.validate();
+
+        let mut overlap = cfg.clone();
+        overlap.load_from_file("overlap.yml", false).unwrap();
+        let overlap_err = overlap.validate().unwrap_err();
+        let reclass_err = crate::Reclass::new_from_config(overlap).unwrap_err();
+
+        let mut missing = cfg.clone();
+        missing.load_from_file("missing.yml", false).unwrap();
+        let missing_err = missing.validate().unwrap_err();
+
+        assert!(valid.is_ok());
+        assert_eq!(
+            overlap_err.to_string(),
+            "Nodes and classes path must be non-overlapping."
+        );
+        assert_eq!(
+            reclass_err.to_string(),
+            "Nodes and classes path must be non-overlapping."
+        );
+        assert_eq!(
+            missing_err.to_string(),
+            format!("Classes path '{invpath}/missing' doesn't exist or isn't a directory")
+        );
+    }
+
+    #[test]
+    fn test_config_effective() {
+        let mut c = Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let eff = c.effective().unwrap();
+
+        let cwd = std::env::current_dir().unwrap();
+        let abs = |p: &str| Value::from(cwd.join(p).display().to_string());
+        assert_eq!(
+            eff.get(&"inventory_path".into()),
+            Some(&abs("tests/inventory"))
+        );
+        assert_eq!(
+            eff.get(&"nodes_path".into()),
+            Some(&abs("tests/inventory/nodes"))
+        );
+        assert_eq!(
+            eff.get(&"classes_path".into()),
+            Some(&abs("tests/inventory/classes"))
+        );
+        assert_eq!(
+            eff.get(&"ignore_class_notfound".into()),
+            Some(&Value::Bool(true))
+        );
+        assert_eq!(
+            eff.get(&"compose_node_name".into()),
+            Some(&Value::Bool(false))
+        );
+        assert_eq!(eff.get(&"thread_count".into()), Some(&Value::Null));
+        assert_eq!(
+            eff.get(&"ignore_class_notfound_patterns".into()),
+            Some(&vec![".*"].into())
+        );
+        assert_eq!(
+            eff.get(&"compatflags".into()),
+            Some(&Value::Sequence(vec![]))
+        );
+    }
+
+    #[test]
+    fn test_config_effective_bool_options() {
+        let mut c = Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        let eff = c.effective().unwrap();
+        for (k, v) in c.bool_fields() {
+            assert_eq!(eff.get(&k.into()), Some(&Value::Bool(*v)), "{k}");
+        }
+    }
+
+    #[test]
+    fn test_config_from_yaml_str_json() {
+        let json = r#"{
+            "nodes_uri": "targets",
+            "classes_uri": "classes",
+            "ignore_class_notfound": true,
+            "ignore_class_notfound_regexp": ["foo", "/bar.*/"],
+            "ignore_class_notfound_glob": true,
+            "compose_node_name": true
+        }"#;
+        let c = Config::from_yaml_str("./tests/inventory", json, false).unwrap();
+        assert_eq!(c.nodes_path, "./tests/inventory/targets");
+        assert_eq!(c.classes_path, "./tests/inventory/classes");
+        assert!(c.ignore_class_notfound);
+        assert!(c.compose_node_name);
+        assert_eq!(
+            c.ignore_class_notfound_regexset.patterns(),
+            &["^foo$", "bar.*"]
+        );
+    }
+
+    #[test]
+    fn test_config_from_serializable() {
+        #[derive(serde::Serialize)]
+        struct Settings {
+            nodes_uri: &'static str,
+            classes_uri: &'static str,
+            compose_node_name: bool,
+        }
+        let settings = Settings {
+            nodes_uri: "targets",
+            classes_uri: "classes",
+            compose_node_name: true,
+        };
+        let c = Config::from_serializable("./tests/inventory", &settings, false).unwrap();
+        assert_eq!(c.nodes_path, "./tests/inventory/targets");
+        assert_eq!(c.classes_path, "./tests/inventory/classes");
+        assert!(c.compose_node_name);
+
+        let err = Config::from_yaml_str("./tests/inventory", "[1, 2]", false).unwrap_err();
+        assert_eq!(err.to_string(), "Expected reclass config to be a Mapping");
+    }
 }
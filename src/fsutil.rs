@@ -41,6 +41,56 @@ pub(crate) fn to_lexical_absolute(p: &Path) -> Result<PathBuf> {
     Ok(absolute)
 }
 
+/// Strips the longest matching extension in `exts` from the file name of `p`. Extensions are
+/// given without leading dot and may contain dots themselves, e.g. `yaml.j2`.
+///
+/// Returns `None` if the file name of `p` doesn't end in any of the extensions, or if the file
+/// name consists only of the extension, e.g. `.yml`.
+pub(crate) fn strip_extension(p: &Path, exts: &[String]) -> Option<PathBuf> {
+    let name = p.file_name()?.to_str()?;
+    let stem = exts
+        .iter()
+        .filter_map(|ext| name.strip_suffix(ext.as_str())?.strip_suffix('.'))
+        .filter(|stem| !stem.is_empty())
+        .min_by_key(|stem| stem.len())?;
+    Some(p.with_file_name(stem))
+}
+
+/// Scratch directory for tests which need to create or modify inventory files. The directory is
+/// removed when the value is dropped, so that it's also cleaned up when a test fails.
+#[cfg(test)]
+pub(crate) struct TempDir(PathBuf);
+
+#[cfg(test)]
+impl TempDir {
+    /// Creates an empty scratch directory whose name contains `name` and the current process id.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("reclass-rs-{name}-{}", std::process::id()));
+        if path.exists() {
+            std::fs::remove_dir_all(&path).unwrap();
+        }
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+#[cfg(test)]
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        // Errors are ignored, since panicking in `drop()` would abort a failing test.
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -84,4 +134,16 @@ mod tests {
         let abs = to_lexical_normal(&PathBuf::from(orig), true);
         assert_eq!(abs, PathBuf::from("./foo/bar/baz"));
     }
+
+    #[test]
+    fn test_strip_extension() {
+        let exts = vec!["yml".to_string(), "yaml".to_string(), "yaml.j2".to_string()];
+        let strip = |p: &str| strip_extension(Path::new(p), &exts);
+        assert_eq!(strip("foo/bar.yml"), Some(PathBuf::from("foo/bar")));
+        assert_eq!(strip("foo/bar.yaml.j2"), Some(PathBuf::from("foo/bar")));
+        assert_eq!(strip("foo.bar.yaml"), Some(PathBuf::from("foo.bar")));
+        assert_eq!(strip("foo/bar.j2"), None);
+        assert_eq!(strip("foo/.yml"), None);
+        assert_eq!(strip("foo/yml"), None);
+    }
 }
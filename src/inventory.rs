@@ -1,42 +1,93 @@
 use anyhow::{anyhow, Result};
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
 use rayon::prelude::*;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
 
 use super::{NodeInfo, Reclass};
+use crate::types::{Mapping, MappingDiff, Value};
 
+/// Serialization formats for [`Inventory::write()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+}
+
+/// Options for [`Inventory::write()`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputOpts {
+    /// Whether JSON output is pretty-printed. YAML output is always emitted in block style.
+    pub pretty: bool,
+    /// Whether all timestamps are replaced with a fixed value (the Unix epoch), so that writing
+    /// the inventory for identical inputs produces identical output.
+    pub deterministic_timestamps: bool,
+    /// Whether mapping keys in node parameters and exports are sorted. Note that JSON objects are
+    /// always emitted with sorted keys.
+    pub sort_keys: bool,
+}
+
+/// Rendered Reclass inventory.
+///
+/// All maps are `BTreeMap`s so that iterating over (and serializing) the inventory produces
+/// entries sorted by key.
 #[pyclass]
 #[derive(Debug, Default)]
 pub struct Inventory {
     /// Maps each application which is included by at least one node to the list of nodes which
     /// include it.
     #[pyo3(get)]
-    applications: HashMap<String, Vec<String>>,
+    pub(crate) applications: BTreeMap<String, Vec<String>>,
     /// Maps each class which is included by at least one node to the list of nodes which include
     /// it.
     #[pyo3(get)]
-    classes: HashMap<String, Vec<String>>,
+    pub(crate) classes: BTreeMap<String, Vec<String>>,
     /// Maps each node name discovered by `Reclass::discover_nodes()` to its `NodeInfo`.
     #[pyo3(get)]
-    nodes: HashMap<String, NodeInfo>,
+    pub(crate) nodes: BTreeMap<String, NodeInfo>,
 }
 
 impl Inventory {
     /// Renders the full inventory for the given Reclass config.
     pub fn render(r: &Reclass) -> Result<Self> {
+        Self::render_with_transform(r, |_, _| Ok(()))
+    }
+
+    /// Renders the full inventory for the given Reclass config and applies the provided transform
+    /// to each rendered node.
+    ///
+    /// The transform is called with the node's name and a mutable reference to the node's
+    /// `NodeInfo` before the node is inserted into the inventory. Nodes are rendered in parallel,
+    /// but the transform is called sequentially for each node once all nodes have been rendered.
+    /// If the transform returns an error, rendering the inventory is aborted and the error is
+    /// returned.
+    ///
+    /// The rendered nodes are processed in lexical order of their names. If multiple nodes fail
+    /// to render, the error of the first failed node in that order is returned.
+    ///
+    /// If config option `thread_count` is set, the nodes are rendered in a thread pool which is
+    /// local to this call instead of in rayon's global thread pool.
+    pub fn render_with_transform<F>(r: &Reclass, mut f: F) -> Result<Self>
+    where
+        F: FnMut(&str, &mut NodeInfo) -> Result<()>,
+    {
         // Render all nodes
-        let infos: Vec<_> = r
-            .nodes
-            .par_iter()
-            .map(|(name, _)| (name, { r.render_node(name) }))
-            .collect();
+        let mut infos: Vec<_> = r.in_thread_pool(|| {
+            r.nodes
+                .par_iter()
+                .map(|(name, _)| (name, { r.render_node(name) }))
+                .collect()
+        })?;
+        // `r.nodes` is a `HashMap` whose iteration order differs between `Reclass` instances.
+        // Sort the results so that errors and transform calls are deterministic.
+        infos.sort_unstable_by_key(|(name, _)| *name);
 
         // Generate `Inventory` from the rendered nodes
         let mut inv = Self::default();
         for (name, info) in infos {
-            let info = info.map_err(|e| anyhow!("Error rendering node {name}: {e}"))?;
+            let mut info = info.map_err(|e| anyhow!("Error rendering node {name}: {e}"))?;
+            f(name, &mut info).map_err(|e| anyhow!("Error transforming node {name}: {e}"))?;
             for cls in &info.classes {
                 inv.classes
                     .entry(cls.clone())
@@ -68,29 +119,129 @@ impl Inventory {
         }
         Ok(inv)
     }
+
+    /// Compares the parameters of each node in this inventory with the parameters of the same node
+    /// in inventory `other`.
+    ///
+    /// Returns a map from node name to the node's parameter differences, cf. [`Mapping::diff()`].
+    /// Nodes whose parameters are identical in both inventories aren't included in the result.
+    /// Nodes which only exist in one of the inventories are compared against empty parameters.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> HashMap<String, MappingDiff> {
+        let empty = Mapping::new();
+        let names = self
+            .nodes
+            .keys()
+            .chain(other.nodes.keys())
+            .collect::<BTreeSet<_>>();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let ours = self.nodes.get(name).map_or(&empty, |n| &n.parameters);
+                let theirs = other.nodes.get(name).map_or(&empty, |n| &n.parameters);
+                let diff = ours.diff(theirs);
+                (!diff.is_empty()).then(|| (name.clone(), diff))
+            })
+            .collect()
+    }
 }
 
-#[pymethods]
 impl Inventory {
-    /// Returns the Inventory as a Python dict.
+    /// Serializes the inventory in the provided format to `w`.
     ///
-    /// The structure of the returned dict should match Python reclass the structure of the dict
-    /// returned by Python reclass's `inventory()` method.
-    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
-        let dict = PyDict::new(py);
-        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
-        dict.set_item("classes", self.classes.clone().into_pyobject(py)?)?;
-        let nodes_dict = PyDict::new(py);
+    /// The output is structured identically to the dict returned by `as_dict()`.
+    pub fn write<W: Write>(&self, w: W, format: Format, opts: OutputOpts) -> Result<()> {
+        let inv = self.as_output_mapping(opts)?;
+        match format {
+            Format::Json => {
+                let inv = serde_json::Value::from(Value::Mapping(inv));
+                if opts.pretty {
+                    serde_json::to_writer_pretty(w, &inv)?;
+                } else {
+                    serde_json::to_writer(w, &inv)?;
+                }
+            }
+            Format::Yaml => {
+                serde_yaml::to_writer(w, &serde_yaml::Value::from(Value::Mapping(inv)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the inventory as a JSON value which is structured identically to the dict
+    /// returned by `as_dict()`.
+    pub fn to_json(&self) -> Result<serde_json::Value> {
+        let inv = self.as_output_mapping(OutputOpts::default())?;
+        Ok(serde_json::Value::from(Value::Mapping(inv)))
+    }
+
+    /// Returns the inventory as a `Mapping` which is structured identically to the dict returned
+    /// by `as_dict()`.
+    fn as_output_mapping(&self, opts: OutputOpts) -> Result<Mapping> {
+        let epoch = DateTime::<Utc>::UNIX_EPOCH.format("%c").to_string();
+
+        let mut nodes = Mapping::with_capacity(self.nodes.len());
         for (name, info) in &self.nodes {
-            nodes_dict.set_item(name, info.as_dict(py)?)?;
+            let ts = if opts.deterministic_timestamps {
+                epoch.clone()
+            } else {
+                info.render_timestamp()
+            };
+            let mut n = info.as_output_mapping(&ts)?;
+            if opts.sort_keys {
+                for k in ["exports", "parameters"] {
+                    if let Some(Value::Mapping(m)) = n.get_mut(&k.into())? {
+                        *m = sorted_mapping(m);
+                    }
+                }
+            }
+            nodes.insert(name.clone().into(), n.into())?;
         }
-        dict.set_item("nodes", nodes_dict)?;
+        let ts = if opts.deterministic_timestamps {
+            epoch
+        } else {
+            Local::now().format("%c").to_string()
+        };
+        let mut meta = Mapping::new();
+        meta.insert("timestamp".into(), ts.into())?;
 
-        let reclass_dict = PyDict::new(py);
-        let ts = Local::now();
-        reclass_dict.set_item("timestamp", ts.format("%c").to_string())?;
-        dict.set_item("__reclass__", reclass_dict)?;
-        Ok(dict)
+        let mut inv = Mapping::new();
+        inv.insert("__reclass__".into(), meta.into())?;
+        inv.insert(
+            "applications".into(),
+            entity_map(&self.applications)?.into(),
+        )?;
+        inv.insert("classes".into(), entity_map(&self.classes)?.into())?;
+        inv.insert("nodes".into(), nodes.into())?;
+        Ok(inv)
+    }
+}
+
+/// Converts an entity map of the inventory, e.g. `Inventory.applications`, into a `Mapping`.
+fn entity_map(m: &BTreeMap<String, Vec<String>>) -> Result<Mapping> {
+    let mut res = Mapping::with_capacity(m.len());
+    for (k, nodes) in m {
+        res.insert(k.clone().into(), nodes.clone().into())?;
+    }
+    Ok(res)
+}
+
+/// Returns a copy of the provided Mapping whose keys are recursively sorted by their string
+/// representation.
+fn sorted_mapping(m: &Mapping) -> Mapping {
+    let mut entries = m
+        .iter()
+        .map(|(k, v)| (k.clone(), sorted_value(v)))
+        .collect::<Vec<_>>();
+    entries.sort_by_cached_key(|(k, _)| k.raw_string().unwrap_or_else(|_| k.to_string()));
+    entries.into_iter().collect()
+}
+
+fn sorted_value(v: &Value) -> Value {
+    match v {
+        Value::Mapping(m) => Value::Mapping(sorted_mapping(m)),
+        Value::Sequence(s) => Value::Sequence(s.iter().map(sorted_value).collect()),
+        _ => v.clone(),
     }
 }
 
@@ -116,11 +267,11 @@ mod inventory_tests {
         assert_eq!(nodes, expected_nodes);
 
         // applications should contain app[1-2], [a-d]
-        let mut expected_applications = HashMap::<String, Vec<String>>::new();
+        let mut expected_applications = BTreeMap::<String, Vec<String>>::new();
         expected_applications.insert("app1".into(), vec!["n1".into()]);
         expected_applications.insert("app2".into(), vec!["n1".into()]);
         expected_applications.insert("a".into(), vec!["n12".into(), "n13".into()]);
-        expected_applications.insert("b".into(), vec!["n13".into()]);
+        expected_applications.insert("b".into(), vec!["n12".into(), "n13".into()]);
         expected_applications.insert("c".into(), vec!["n12".into()]);
         expected_applications.insert("d".into(), vec!["n13".into()]);
 
@@ -136,7 +287,7 @@ mod inventory_tests {
         // * \${baz} -- rendered as `${baz}` for n17
         // * cluster.${dist} -- rendered as cluster.foo for n19
 
-        let mut expected_classes = HashMap::<String, Vec<String>>::new();
+        let mut expected_classes = BTreeMap::<String, Vec<String>>::new();
         expected_classes.insert("${cls9}".into(), vec!["n15".into()]);
         expected_classes.insert("${qux}".into(), vec!["n4".into()]);
         expected_classes.insert("${tenant}.${cluster}".into(), vec!["n16".into()]);
@@ -194,6 +345,74 @@ mod inventory_tests {
         assert_eq!(inv.classes, expected_classes);
     }
 
+    #[test]
+    fn test_render_sorted() {
+        fn assert_sorted<'a>(keys: impl Iterator<Item = &'a String>) {
+            let keys = keys.collect::<Vec<_>>();
+            let mut sorted = keys.clone();
+            sorted.sort();
+            assert_eq!(keys, sorted);
+        }
+
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let inv = Inventory::render(&r).unwrap();
+
+        assert_sorted(inv.classes.keys());
+        assert_sorted(inv.applications.keys());
+        assert_sorted(inv.nodes.keys());
+        for nodes in inv.classes.values() {
+            assert_sorted(nodes.iter());
+        }
+    }
+
+    #[test]
+    fn test_render_with_transform_redact() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let inv = Inventory::render_with_transform(&r, |name, info| {
+            if name == "n1" {
+                let bar = info
+                    .parameters
+                    .get_mut(&"foo".into())?
+                    .ok_or(anyhow!("Parameter foo missing for n1"))?
+                    .as_mapping_mut()
+                    .ok_or(anyhow!("Parameter foo isn't a mapping for n1"))?
+                    .get_mut(&"bar".into())?
+                    .ok_or(anyhow!("Parameter foo.bar missing for n1"))?;
+                *bar = literal("<redacted>");
+            }
+            Ok(())
+        })
+        .unwrap();
+
+        let foo = inv.nodes["n1"].parameters.get(&"foo".into()).unwrap();
+        assert_eq!(foo.get(&"bar".into()), Some(&literal("<redacted>")));
+        assert_eq!(foo.get(&"baz".into()), Some(&literal("cls1")));
+        // other nodes aren't touched by the transform
+        let foo = inv.nodes["n2"].parameters.get(&"foo".into()).unwrap();
+        assert_ne!(foo.get(&"bar".into()), Some(&literal("<redacted>")));
+    }
+
+    #[test]
+    fn test_render_with_transform_reject() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let res = Inventory::render_with_transform(&r, |name, _| {
+            if name == "n2" {
+                return Err(anyhow!("node {name} rejected"));
+            }
+            Ok(())
+        });
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            "Error transforming node n2: node n2 rejected"
+        );
+    }
+
     use crate::types::Value;
     fn literal(v: &str) -> Value {
         Value::Literal(v.to_string())
@@ -250,7 +469,7 @@ mod inventory_tests {
             assert_eq!(
                 params_reclass_name.get(&"parts".into()),
                 Some(expected_parts)
-            )
+            );
         }
     }
 
@@ -352,4 +571,172 @@ mod inventory_tests {
             Some(&sequence(&["a.1"]))
         );
     }
+
+    #[test]
+    fn test_nodes_with_class_and_application() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let inv = Inventory::render(&r).unwrap();
+
+        assert_eq!(
+            inv.nodes_with_class("cls9"),
+            vec!["n10", "n12", "n13", "n14", "n18", "n5", "n6", "n7", "n9"]
+        );
+        assert_eq!(inv.nodes_with_application("a"), vec!["n12", "n13"]);
+        assert!(inv.nodes_with_class("missing").is_empty());
+        assert!(inv.nodes_with_application("missing").is_empty());
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let inv = Inventory::render(&r).unwrap();
+        let j = inv.to_json().unwrap();
+
+        let keys = j.as_object().unwrap().keys().collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec!["__reclass__", "applications", "classes", "nodes"]
+        );
+        assert!(j["__reclass__"]["timestamp"].is_string());
+        assert_eq!(j["nodes"].as_object().unwrap().len(), 25);
+
+        let n1 = &j["nodes"]["n1"];
+        assert_eq!(n1["applications"], serde_json::json!(["app1", "app2"]));
+        assert_eq!(n1["environment"], serde_json::json!("base"));
+        assert_eq!(n1["parameters"]["foo"]["foo"], serde_json::json!("foo"));
+        assert_eq!(
+            n1["parameters"]["_reclass_"]["name"]["full"],
+            serde_json::json!("n1")
+        );
+    }
+
+    #[test]
+    fn test_render_deterministic() {
+        let render = || {
+            let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+            c.load_from_file("reclass-config.yml", false).unwrap();
+            let r = Reclass::new_from_config(c).unwrap();
+            let inv = Inventory::render(&r).unwrap();
+            inv.nodes
+                .into_iter()
+                .map(|(name, info)| {
+                    let params = serde_yaml::Mapping::from(info.parameters);
+                    (name, serde_yaml::to_string(&params).unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(render(), render());
+    }
+
+    #[test]
+    fn test_render_local_thread_pool() {
+        let render = |count| {
+            let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+            c.load_from_file("reclass-config.yml", false).unwrap();
+            c.thread_count = Some(count);
+            let r = Reclass::new_from_config(c).unwrap();
+            let inv = Inventory::render(&r).unwrap();
+            inv.nodes
+                .into_iter()
+                .map(|(name, info)| {
+                    let params = serde_yaml::Mapping::from(info.parameters);
+                    (name, serde_yaml::to_string(&params).unwrap())
+                })
+                .collect::<Vec<_>>()
+        };
+        let single = render(1);
+        assert_eq!(single.len(), 25);
+        assert_eq!(single, render(4));
+    }
+
+    #[test]
+    fn test_render_deterministic_error() {
+        // Every `Reclass` instance holds a `HashMap` of the discovered nodes with a random
+        // iteration order, so we render the inventory with multiple instances.
+        let errors = (0..5)
+            .map(|_| {
+                let r = Reclass::new("./tests/inventory-render-error", "nodes", "classes", false)
+                    .unwrap();
+                format!("{:#}", Inventory::render(&r).unwrap_err())
+            })
+            .collect::<Vec<_>>();
+
+        assert!(
+            errors[0].starts_with("Error rendering node n1: "),
+            "unexpected error: {}",
+            errors[0]
+        );
+        assert!(errors[0].contains("'${missing}' in parameter 'a'"));
+        assert!(errors.iter().all(|e| *e == errors[0]));
+    }
+
+    #[test]
+    fn test_write_json() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let opts = OutputOpts {
+            deterministic_timestamps: true,
+            ..Default::default()
+        };
+
+        let mut buf = vec![];
+        r.write_inventory(&mut buf, Format::Json, opts).unwrap();
+        let out: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+
+        let inv = r.render_inventory().unwrap();
+        let nodes = out["nodes"].as_object().unwrap();
+        assert_eq!(
+            nodes.keys().collect::<Vec<_>>(),
+            inv.nodes.keys().collect::<Vec<_>>()
+        );
+        for (name, info) in &inv.nodes {
+            let n = &nodes[name];
+            assert_eq!(
+                n["parameters"],
+                serde_json::Value::from(crate::types::Value::Mapping(info.parameters.clone()))
+            );
+            assert_eq!(n["applications"], serde_json::json!(info.applications));
+            assert_eq!(n["classes"], serde_json::json!(info.classes));
+            assert_eq!(n["__reclass__"]["uri"], serde_json::json!(info.reclass.uri));
+        }
+        assert_eq!(out["applications"], serde_json::json!(inv.applications));
+        assert_eq!(out["classes"], serde_json::json!(inv.classes));
+
+        // Output is identical for repeated writes with deterministic timestamps
+        let mut buf2 = vec![];
+        r.write_inventory(&mut buf2, Format::Json, opts).unwrap();
+        assert_eq!(buf, buf2);
+    }
+
+    #[test]
+    fn test_write_yaml_sorted() {
+        let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let opts = OutputOpts {
+            deterministic_timestamps: true,
+            sort_keys: true,
+            ..Default::default()
+        };
+        let mut buf = vec![];
+        r.write_inventory(&mut buf, Format::Yaml, opts).unwrap();
+        let out: serde_yaml::Mapping = serde_yaml::from_slice(&buf).unwrap();
+        let params = out["nodes"]["n1"]["parameters"].as_mapping().unwrap();
+        let keys = params
+            .keys()
+            .map(|k| k.as_str().unwrap())
+            .collect::<Vec<_>>();
+        let mut sorted = keys.clone();
+        sorted.sort_unstable();
+        assert_eq!(keys, sorted);
+        assert_eq!(
+            out["nodes"]["n1"]["__reclass__"]["timestamp"],
+            serde_yaml::Value::from("Thu Jan  1 00:00:00 1970")
+        );
+    }
 }
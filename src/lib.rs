@@ -12,34 +12,38 @@ mod fsutil;
 mod inventory;
 mod list;
 mod node;
+mod python;
 mod refs;
 pub mod types;
 
 use anyhow::{anyhow, Result};
-use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
-use pyo3::types::PyType;
+use rayon::prelude::*;
 use rayon::ThreadPoolBuilder;
+use regex::RegexSet;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf, MAIN_SEPARATOR};
 use walkdir::WalkDir;
 
-use config::{CompatFlag, Config};
-use fsutil::to_lexical_absolute;
+use config::{CompatFlag, Config, DuplicateEntityStrategy, ReclassMetaPolicy};
+use fsutil::{strip_extension, to_lexical_absolute};
 use inventory::Inventory;
+pub use inventory::{Format, OutputOpts};
 use node::{Node, NodeInfo, NodeInfoMeta};
+use types::{MappingDiff, Value};
 
-const SUPPORTED_YAML_EXTS: [&str; 2] = ["yml", "yaml"];
-
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 struct EntityInfo {
     path: PathBuf,
     loc: PathBuf,
 }
 
-#[derive(Eq, PartialEq)]
-enum EntityKind {
+/// Kinds of entities which can be discovered in an inventory.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EntityKind {
+    /// A node in `nodes_path`
     Node,
+    /// A class in `classes_path`
     Class,
 }
 
@@ -118,80 +122,186 @@ fn err_duplicate_entity(
     ))
 }
 
+/// Walks the entity directory `root` and inserts all discovered entities into `entity_map`.
+///
+/// If config option `strict_discovery` is enabled, the function returns an error for files with
+/// unsupported extensions unless their path relative to `root` matches one of the patterns in
+/// `strict_discovery_ignore_regexp`.
+///
+/// If config option `parallel_discovery` is enabled, the discovered paths are normalized in
+/// parallel. Entities are still inserted into `entity_map` in the order in which they were found,
+/// so that the results and errors are identical to a serial discovery.
+///
+/// If config option `case_insensitive_classes` is enabled, class names are lowercased, so that
+/// classes whose names only differ in case collide.
+///
+/// Entities which are defined more than once are an error, unless config option
+/// `duplicate_entity_strategy` selects which of a `.yml` and a `.yaml` file is used.
+///
+/// Files named `init.yml` (or `init.yaml`) are only treated specially for classes, where
+/// `foo/init.yml` holds the contents of class `foo`. Nodes are always named after their file,
+/// which matches Python reclass: `nodes/init.yml` is node `init`, and `nodes/foo/init.yml` is node
+/// `foo.init` if `compose_node_name` is enabled, and isn't discovered otherwise.
 fn walk_entity_dir(
     kind: &EntityKind,
     root: &str,
     entity_map: &mut HashMap<String, EntityInfo>,
     max_depth: usize,
+    config: &Config,
 ) -> Result<()> {
     let entity_root = to_lexical_absolute(&PathBuf::from(root))?;
+    let strict = config.strict_discovery_ignores();
+    let exts = config.entity_extensions();
+    let lowercase = kind == &EntityKind::Class && config.case_insensitive_classes;
 
     // We need to follow symlinks when walking the root directory, so that inventories which
     // contain symlinked directories are loaded correctly.
-    for entry in WalkDir::new(root).max_depth(max_depth).follow_links(true) {
-        let entry = entry?;
-        // We use `entry.path()` here to get the symlink name for symlinked files.
-        let ext = if let Some(ext) = entry.path().extension() {
-            ext.to_str()
-        } else {
-            None
+    let entries = WalkDir::new(root)
+        .max_depth(max_depth)
+        .follow_links(true)
+        .into_iter()
+        .map(|entry| entry.map_err(|e| err_symlink_loop(kind, root, e)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let process = |entry: &walkdir::DirEntry| {
+        process_entity(kind, root, &entity_root, entry, max_depth, strict, &exts)
+    };
+    let entities = if config.parallel_discovery {
+        entries.par_iter().map(process).collect::<Vec<_>>()
+    } else {
+        entries.iter().map(process).collect::<Vec<_>>()
+    };
+
+    for entity in entities {
+        let Some((cls, info)) = entity? else {
+            continue;
         };
-        if ext.is_some() && SUPPORTED_YAML_EXTS.contains(&ext.unwrap()) {
-            // it's an entity (class or node), process it
-            let abspath = to_lexical_absolute(entry.path())?;
-            let relpath = abspath.strip_prefix(&entity_root)?;
-            let cls = relpath.with_extension("");
-            let (cls, loc) = if cls.ends_with("init") {
-                // treat `foo/init.yml` as contents for class `foo`
-                let cls = cls
-                    .parent()
-                    .ok_or(anyhow!(
-                        "Failed to normalize entity {}",
-                        entry.path().display()
-                    ))?
-                    .to_owned();
-                // here, unwrap can't panic since we otherwise would have already returned an error
-                // in the previous statement.
-                let loc = relpath.parent().unwrap();
-                // For `init.ya?ml` classes, the location is parent directory of the directory
-                // holding the class file.
-                (cls, loc.parent().unwrap_or(Path::new("")))
-            } else {
-                // For normal classes, the location is the directory holding the class file.
-                (cls, relpath.parent().unwrap_or(Path::new("")))
-            };
-            let cls = cls.to_str().ok_or(anyhow!(
-                "Failed to normalize entity {}",
-                entry.path().display()
-            ))?;
-            let (cls, loc) = if kind == &EntityKind::Node && max_depth > 1 && cls.starts_with('_') {
-                // special case node paths starting with _ for compose-node-name
-                (
-                    cls.split(MAIN_SEPARATOR).last().ok_or(anyhow!(
-                        "Can't shorten node name for {}",
-                        entry.path().display()
-                    ))?,
-                    Path::new(""),
-                )
-            } else {
-                (cls, loc)
+        let cls = if lowercase { cls.to_lowercase() } else { cls };
+        if let Some(prev) = entity_map.get(&cls) {
+            // Collisions between a `.yml` and a `.yaml` file with the same path are resolved
+            // according to the configured `duplicate_entity_strategy`. All other collisions, e.g.
+            // between `foo/init.yml` and `foo.yaml`, are an error.
+            let same_stem =
+                strip_extension(&prev.path, &exts) == strip_extension(&info.path, &exts);
+            let preferred = config.duplicate_entity_strategy.preferred_extension();
+            let is_preferred = |p: &Path| {
+                same_stem
+                    && preferred.is_some()
+                    && p.extension().and_then(|e| e.to_str()) == preferred
             };
-            let cls = cls.replace(MAIN_SEPARATOR, ".");
-            if let Some(prev) = entity_map.get(&cls) {
-                return err_duplicate_entity(kind, root, relpath, &cls, &prev.path);
+            if is_preferred(&info.path) && !is_preferred(&prev.path) {
+                entity_map.insert(cls, info);
+                continue;
             }
-            entity_map.insert(
-                cls,
-                EntityInfo {
-                    path: relpath.to_path_buf(),
-                    loc: PathBuf::from(loc),
-                },
-            );
+            if is_preferred(&prev.path) && !is_preferred(&info.path) {
+                continue;
+            }
+            return err_duplicate_entity(kind, root, &info.path, &cls, &prev.path);
         }
+        entity_map.insert(cls, info);
     }
     Ok(())
 }
 
+/// Normalizes the directory entry `entry` found in entity directory `root` into the entity's
+/// name and `EntityInfo`. Returns `None` for entries which aren't entities.
+///
+/// See `walk_entity_dir()` for the meaning of `strict` and `exts`.
+fn process_entity(
+    kind: &EntityKind,
+    root: &str,
+    entity_root: &Path,
+    entry: &walkdir::DirEntry,
+    max_depth: usize,
+    strict: Option<&RegexSet>,
+    exts: &[String],
+) -> Result<Option<(String, EntityInfo)>> {
+    // We use `entry.path()` here to get the symlink name for symlinked files.
+    if let Some(stem) = strip_extension(entry.path(), exts) {
+        // it's an entity (class or node), process it
+        let abspath = to_lexical_absolute(entry.path())?;
+        let relpath = abspath.strip_prefix(entity_root)?;
+        let cls = relpath.with_file_name(stem.file_name().unwrap_or_default());
+        let (cls, loc) = if kind == &EntityKind::Class && cls.ends_with("init") {
+            // treat `foo/init.yml` as contents for class `foo`
+            let cls = cls
+                .parent()
+                .ok_or(anyhow!(
+                    "Failed to normalize entity {}",
+                    entry.path().display()
+                ))?
+                .to_owned();
+            // here, unwrap can't panic since we otherwise would have already returned an error
+            // in the previous statement.
+            let loc = relpath.parent().unwrap();
+            // For `init.ya?ml` classes, the location is parent directory of the directory
+            // holding the class file.
+            (cls, loc.parent().unwrap_or(Path::new("")))
+        } else {
+            // For normal classes, the location is the directory holding the class file.
+            (cls, relpath.parent().unwrap_or(Path::new("")))
+        };
+        let cls = cls.to_str().ok_or(anyhow!(
+            "Failed to normalize entity {}",
+            entry.path().display()
+        ))?;
+        let (cls, loc) = if kind == &EntityKind::Node && max_depth > 1 && cls.starts_with('_') {
+            // special case node paths starting with _ for compose-node-name
+            (
+                cls.split(MAIN_SEPARATOR).next_back().ok_or(anyhow!(
+                    "Can't shorten node name for {}",
+                    entry.path().display()
+                ))?,
+                Path::new(""),
+            )
+        } else {
+            (cls, loc)
+        };
+        let cls = cls.replace(MAIN_SEPARATOR, ".");
+        return Ok(Some((
+            cls,
+            EntityInfo {
+                path: relpath.to_path_buf(),
+                loc: PathBuf::from(loc),
+            },
+        )));
+    } else if let Some(ignores) = strict {
+        // In strict mode, files with unsupported extensions are an error, unless their path
+        // matches one of the configured ignore patterns.
+        if entry.file_type().is_file() {
+            let abspath = to_lexical_absolute(entry.path())?;
+            let relpath = abspath.strip_prefix(entity_root)?;
+            let relpath = relpath
+                .to_str()
+                .ok_or(anyhow!("Failed to convert {} to string", relpath.display()))?;
+            if !ignores.is_match(relpath) {
+                return Err(anyhow!(
+                    "Found file '{}' with unsupported extension in {} directory '{root}'. \
+                    Supported extensions are: {}",
+                    entry.path().display(),
+                    kind.plural(false),
+                    exts.join(", ")
+                ));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Wraps walkdir errors which are caused by symlink loops in an error which names the offending
+/// symlink. Other walkdir errors are returned unchanged.
+fn err_symlink_loop(kind: &EntityKind, root: &str, e: walkdir::Error) -> anyhow::Error {
+    match (e.loop_ancestor(), e.path()) {
+        (Some(ancestor), Some(path)) => anyhow!(
+            "Found symlink loop in {} directory '{root}': '{}' points to its ancestor '{}'",
+            kind.plural(false),
+            path.display(),
+            ancestor.display()
+        ),
+        _ => e.into(),
+    }
+}
+
 impl Reclass {
     pub fn new(
         inventory_path: &str,
@@ -208,19 +318,59 @@ impl Reclass {
         Self::new_from_config(config)
     }
 
+    /// Creates a `Reclass` instance for the provided `inventory_path` and loads config options
+    /// from the provided config file. The value of `config_file` is interpreted relative to
+    /// `inventory_path`.
+    ///
+    /// If `verbose` is true, diagnostic messages are printed for config options which aren't
+    /// implemented yet.
+    pub fn from_config_file(
+        inventory_path: &str,
+        config_file: &str,
+        verbose: bool,
+    ) -> Result<Self> {
+        let mut c = Config::new(Some(inventory_path), None, None, None)?;
+        c.load_from_file(config_file, verbose)?;
+        Self::new_from_config(c)
+    }
+
     pub fn new_from_config(config: Config) -> Result<Self> {
+        config.validate()?;
         let mut r = Self {
             config,
             classes: HashMap::new(),
             nodes: HashMap::new(),
         };
-        r.discover_nodes()
-            .map_err(|e| anyhow!("Error while discovering nodes: {e}"))?;
-        r.discover_classes()
-            .map_err(|e| anyhow!("Error while discovering classes: {e}"))?;
+        r.discover()?;
         Ok(r)
     }
 
+    /// Re-discovers all nodes and classes with the instance's current config. This allows
+    /// long-running users to pick up changes to the inventory on disk without creating a new
+    /// `Reclass` instance.
+    ///
+    /// If discovery fails, the method returns the error and the instance doesn't hold any nodes
+    /// or classes.
+    pub fn reload(&mut self) -> Result<()> {
+        self.nodes.clear();
+        self.classes.clear();
+        let res = self.discover();
+        if res.is_err() {
+            // Don't leave a partially discovered inventory behind
+            self.nodes.clear();
+            self.classes.clear();
+        }
+        res
+    }
+
+    /// Discovers all nodes and classes.
+    fn discover(&mut self) -> Result<()> {
+        self.discover_nodes()
+            .map_err(|e| anyhow!("Error while discovering nodes: {e}"))?;
+        self.discover_classes()
+            .map_err(|e| anyhow!("Error while discovering classes: {e}"))
+    }
+
     /// Discover all top-level YAML files in `r.nodes_path`.
     ///
     /// This method will raise an error if multiple nodes which resolve to the same node name
@@ -236,7 +386,8 @@ impl Reclass {
             &EntityKind::Node,
             &self.config.nodes_path,
             &mut self.nodes,
-            depth,
+            self.config.discovery_depth(depth),
+            &self.config,
         )
     }
 
@@ -250,10 +401,44 @@ impl Reclass {
             &EntityKind::Class,
             &self.config.classes_path,
             &mut self.classes,
-            usize::MAX,
+            self.config.discovery_depth(usize::MAX),
+            &self.config,
         )
     }
 
+    /// Returns the `EntityInfo` for absolute class name `cls`, or `None` if no such class was
+    /// discovered. If config option `case_insensitive_classes` is enabled, the class is looked up
+    /// case-insensitively.
+    fn class_info(&self, cls: &str) -> Option<&EntityInfo> {
+        if self.config.case_insensitive_classes {
+            self.classes.get(&cls.to_lowercase())
+        } else {
+            self.classes.get(cls)
+        }
+    }
+
+    /// Returns all discovered nodes and classes with their kind and their path relative to
+    /// `nodes_path` or `classes_path` respectively.
+    ///
+    /// Nodes are listed before classes, and entities of each kind are sorted by name.
+    #[must_use]
+    pub fn entities(&self) -> Vec<(String, EntityKind, PathBuf)> {
+        let mut res = Vec::with_capacity(self.nodes.len() + self.classes.len());
+        for (kind, entities) in [
+            (EntityKind::Node, &self.nodes),
+            (EntityKind::Class, &self.classes),
+        ] {
+            let mut names = entities.keys().collect::<Vec<_>>();
+            names.sort();
+            res.extend(
+                names
+                    .into_iter()
+                    .map(|name| (name.clone(), kind.clone(), entities[name].path.clone())),
+            );
+        }
+        res
+    }
+
     /// Renders a single Node and returns the corresponding `NodeInfo` struct.
     pub fn render_node(&self, nodename: &str) -> Result<NodeInfo> {
         let mut n = Node::parse(self, nodename)?;
@@ -261,143 +446,174 @@ impl Reclass {
         Ok(NodeInfo::from(n))
     }
 
-    pub fn render_inventory(&self) -> Result<Inventory> {
-        Inventory::render(self)
+    /// Renders a single Node and returns the corresponding `NodeInfo` struct together with the raw
+    /// contents of the node file and of all class files which were read while rendering the
+    /// node. The raw contents are keyed by the URI of the node or class.
+    pub fn render_node_with_sources(
+        &self,
+        nodename: &str,
+    ) -> Result<(NodeInfo, HashMap<String, String>)> {
+        let mut sources = HashMap::new();
+        let mut n = Node::parse_with_sources(self, nodename, Some(&mut sources))?;
+        n.render_with_sources(self, Some(&mut sources))?;
+        Ok((NodeInfo::from(n), sources))
     }
-}
 
-#[pymethods]
-impl Reclass {
-    #[new]
-    #[pyo3(signature = (inventory_path=".", nodes_path=None, classes_path=None, ignore_class_notfound=None))]
-    pub fn new_py(
-        inventory_path: Option<&str>,
-        nodes_path: Option<&str>,
-        classes_path: Option<&str>,
-        ignore_class_notfound: Option<bool>,
-    ) -> PyResult<Self> {
-        let c = Config::new(
-            inventory_path,
-            nodes_path,
-            classes_path,
-            ignore_class_notfound,
-        )
-        .map_err(|e| PyValueError::new_err(format!("{e}")))?;
-        let r = Self::new_from_config(c).map_err(|e| PyValueError::new_err(format!("{e}")))?;
-        Ok(r)
+    /// Returns the names of all classes which are included by the Node, either directly or through
+    /// other classes, without rendering the Node.
+    ///
+    /// The classes are returned in the order in which `render_node()` would list them. Class
+    /// names which contain references are returned unresolved, and classes included by such
+    /// classes aren't part of the result.
+    pub fn node_class_closure(&self, name: &str) -> Result<Vec<String>> {
+        Node::parse(self, name)?.class_closure(self)
     }
 
-    /// Creates a `Reclass` instance for the provided `inventory_path` and loads config options
-    /// from the provided config file. The value of `config_file` is interpreted relative to
-    /// `inventory_path`.
+    /// Returns the flattened reference paths of all references which are present in the
+    /// unrendered parameters of the node file, without rendering the Node.
     ///
-    /// Returns a `Reclass` instance or raises a `ValueError`
-    #[classmethod]
-    #[pyo3(signature = (inventory_path, config_file, verbose=false))]
-    fn from_config_file(
-        cls: &Bound<'_, PyType>,
-        inventory_path: &str,
-        config_file: &str,
-        verbose: bool,
-    ) -> PyResult<Self> {
-        let mut c = Config::new(Some(inventory_path), None, None, None)
-            .map_err(|e| PyValueError::new_err(format!("{e}")))?;
-        c.load_from_file(config_file, verbose)
-            .map_err(|e| PyValueError::new_err(format!("{e}")))?;
-        Self::from_config(cls, c)
+    /// See `Node::references()` for details.
+    pub fn node_references(&self, name: &str) -> Result<Vec<String>> {
+        Node::parse(self, name)?.references()
     }
 
-    /// Creates a `Reclass` instance from the provided `Config` instance.
+    /// Returns the direct include relationships of all discovered classes.
     ///
-    /// Returns a `Reclass` instance or raises a `ValueError`
-    #[classmethod]
-    fn from_config(_cls: &Bound<'_, PyType>, config: Config) -> PyResult<Self> {
-        let r = Self::new_from_config(config).map_err(|e| PyValueError::new_err(format!("{e}")))?;
-        Ok(r)
+    /// Each class is parsed without rendering it. The returned map holds the classes which are
+    /// directly included by each class, in the order in which they're listed in the class.
+    /// Relative class names are resolved to absolute class names. Class names which contain
+    /// references are returned unresolved.
+    pub fn class_graph(&self) -> Result<HashMap<String, Vec<String>>> {
+        self.classes
+            .keys()
+            .map(|cls| Ok((cls.clone(), Node::class_includes(self, cls)?)))
+            .collect()
     }
 
-    fn __repr__(&self) -> String {
-        format!("{self:#?}")
+    /// Renders a single Node from a snapshot of the inventory and returns the corresponding
+    /// `NodeInfo` struct.
+    ///
+    /// The node file and the files of all classes in the node's class closure (see
+    /// `node_class_closure()`) are read before rendering starts. No further files are read while
+    /// rendering, so the result is consistent even if inventory files are modified concurrently.
+    /// The only exception are classes whose name contains references, which are read when
+    /// they're included.
+    pub fn render_node_snapshot(&self, nodename: &str) -> Result<NodeInfo> {
+        let snapshot = Node::snapshot_sources(self, nodename)?;
+        self.render_node_from_snapshot(nodename, snapshot)
     }
 
-    /// Returns the rendered data for the node with the provided name if it exists.
-    pub fn nodeinfo(&self, nodename: &str) -> PyResult<NodeInfo> {
-        self.render_node(nodename)
-            .map_err(|e| PyValueError::new_err(format!("Error while rendering {nodename}: {e}")))
+    /// Renders a single Node with the file contents from `snapshot`, as returned by
+    /// `Node::snapshot_sources()`.
+    fn render_node_from_snapshot(
+        &self,
+        nodename: &str,
+        mut snapshot: HashMap<String, String>,
+    ) -> Result<NodeInfo> {
+        let mut n = Node::parse_with_sources(self, nodename, Some(&mut snapshot))?;
+        n.render_with_sources(self, Some(&mut snapshot))?;
+        Ok(NodeInfo::from(n))
     }
 
-    /// Returns the rendered data for the full inventory.
-    pub fn inventory(&self) -> PyResult<Inventory> {
-        self.render_inventory()
-            .map_err(|e| PyValueError::new_err(format!("Error while rendering inventory: {e}")))
+    /// Renders a single Node from the provided YAML string and returns the corresponding
+    /// `NodeInfo` struct.
+    ///
+    /// The node doesn't need to exist in `nodes_path`. Classes included by the node are looked up
+    /// in the classes discovered in `classes_path`. Relative class includes are treated as
+    /// relative to `classes_path`. The `uri` field of the node's metadata is left empty, since
+    /// the node isn't backed by a file.
+    pub fn render_node_from_str(&self, nodename: &str, yaml: &str) -> Result<NodeInfo> {
+        let meta = NodeInfoMeta::new(
+            nodename,
+            nodename,
+            "",
+            PathBuf::from(nodename),
+            &self.config.default_environment,
+        );
+        let mut n = Node::from_source(self, meta, None, yaml)?;
+        n.render(self)?;
+        Ok(NodeInfo::from(n))
     }
 
-    /// Configures the number of threads to use when rendering the full inventory. Calling the
-    /// method with `count=0` will configure the thread pool to have one thread per logical core of
-    /// the system.
+    /// Renders a single Node and returns the value of the parameter at the provided `:`-separated
+    /// path, e.g. `cluster:name`. Path segments which are used to index into sequences are
+    /// interpreted as zero-based indices.
     ///
-    /// Note that this method should only be called once and will print a diagnostic message if
-    /// called again.
-    #[classmethod]
-    pub fn set_thread_count(_cls: &Bound<'_, PyType>, count: usize) {
-        if let Err(e) = ThreadPoolBuilder::new().num_threads(count).build_global() {
-            eprintln!("While initializing global thread pool: {e}");
-        }
+    /// Note that the Node is still fully rendered, since references may cross subtrees. Returns
+    /// `None` if the path doesn't exist in the Node's rendered parameters.
+    pub fn render_subtree(&self, nodename: &str, path: &str) -> Result<Option<Value>> {
+        let info = self.render_node(nodename)?;
+        Ok(info.parameters.get_path(path).cloned())
     }
 
-    /// Sets the provided CompatFlag in the current Reclass instance's config object
-    pub fn set_compat_flag(&mut self, flag: CompatFlag) {
-        self.config.compatflags.insert(flag);
+    pub fn render_inventory(&self) -> Result<Inventory> {
+        Inventory::render(self)
     }
 
-    /// Unsets the provided CompatFlag in the current Reclass instance's config object
-    pub fn unset_compat_flag(&mut self, flag: &CompatFlag) {
-        self.config.compatflags.remove(flag);
+    /// Renders all nodes in parallel and calls `f` with the name and the render result of each
+    /// node.
+    ///
+    /// In contrast to [`Reclass::render_inventory()`], the rendered nodes aren't retained, which
+    /// allows callers to process (e.g. write to disk) and drop each node to bound memory usage for
+    /// large inventories. Since `f` is called from multiple threads, no ordering of the calls is
+    /// guaranteed.
+    ///
+    /// Returns an error if the thread pool for rendering can't be initialized.
+    pub fn render_inventory_each<F>(&self, f: F) -> Result<()>
+    where
+        F: Fn(String, Result<NodeInfo>) + Sync,
+    {
+        self.in_thread_pool(|| {
+            self.nodes
+                .par_iter()
+                .for_each(|(name, _)| f(name.clone(), self.render_node(name)));
+        })
     }
 
-    /// Clears the compatflags set in the current Reclass instance's config object
-    pub fn clear_compat_flags(&mut self) {
-        self.config.compatflags.clear();
+    /// Runs `op` in a thread pool which is local to this call if config option `thread_count` is
+    /// set, and in rayon's global thread pool otherwise.
+    pub(crate) fn in_thread_pool<OP, R>(&self, op: OP) -> Result<R>
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        let Some(count) = self.config.thread_count else {
+            return Ok(op());
+        };
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(count)
+            .build()
+            .map_err(|e| anyhow!("While initializing thread pool: {e}"))?;
+        Ok(pool.install(op))
     }
 
-    /// Returns a dict containing all discovered nodes with their paths relative to `nodes_path`.
+    /// Renders the full inventory and serializes it in the provided format to `w`.
     ///
-    /// NOTE: We don't use the generated getter here, because we don't want to return the
-    /// EntityInfo.
-    #[getter]
-    pub fn nodes(&self) -> PyResult<HashMap<String, PathBuf>> {
-        let res = self
-            .nodes
-            .iter()
-            .map(|(k, v)| (k.clone(), v.path.clone()))
-            .collect::<HashMap<String, PathBuf>>();
-        Ok(res)
+    /// See [`Inventory::write()`] for details.
+    pub fn write_inventory<W: std::io::Write>(
+        &self,
+        w: W,
+        format: Format,
+        opts: OutputOpts,
+    ) -> Result<()> {
+        self.render_inventory()?.write(w, format, opts)
     }
 
-    /// Returns the dict of all discovered classes and their paths relative to `classes_path`.
+    /// Renders the full inventory once with `config_a` and once with `config_b` and returns the
+    /// parameter differences for each node whose parameters differ between the two renders.
     ///
-    /// NOTE: We don't use the generated getter here, because we don't want to return the
-    /// EntityInfo.
-    #[getter]
-    pub fn classes(&self) -> PyResult<HashMap<String, PathBuf>> {
-        let res = self
-            .classes
-            .iter()
-            .map(|(k, v)| (k.clone(), v.path.clone()))
-            .collect::<HashMap<String, PathBuf>>();
-        Ok(res)
-    }
-
-    /// Update the current Reclass instance's config object with the provided
-    /// `ignore_class_notfound_regexp` patterns
-    pub fn set_ignore_class_notfound_regexp(&mut self, patterns: Vec<String>) -> PyResult<()> {
-        self.config
-            .set_ignore_class_notfound_regexp(patterns)
-            .map_err(|e| {
-                PyValueError::new_err(format!(
-                    "Error while setting ignore_class_notfound_regexp: {e}"
-                ))
-            })
+    /// See [`Inventory::diff()`] for details.
+    pub fn diff_configs(
+        config_a: Config,
+        config_b: Config,
+    ) -> Result<HashMap<String, MappingDiff>> {
+        let a = Self::new_from_config(config_a)?
+            .render_inventory()
+            .map_err(|e| anyhow!("While rendering inventory with first config: {e}"))?;
+        let b = Self::new_from_config(config_b)?
+            .render_inventory()
+            .map_err(|e| anyhow!("While rendering inventory with second config: {e}"))?;
+        Ok(a.diff(&b))
     }
 }
 
@@ -411,9 +627,12 @@ impl Default for Reclass {
 fn reclass_rs(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     // Register the top-level `Reclass` Python class which is used to configure the library
     m.add_class::<Reclass>()?;
-    // Register the `Config` class and `CompatFlag` enum
+    // Register the `Config` class and the `CompatFlag`, `ReclassMetaPolicy` and
+    // `DuplicateEntityStrategy` enums
     m.add_class::<Config>()?;
     m.add_class::<CompatFlag>()?;
+    m.add_class::<ReclassMetaPolicy>()?;
+    m.add_class::<DuplicateEntityStrategy>()?;
     // Register the NodeInfoMeta and NodeInfo classes
     m.add_class::<NodeInfoMeta>()?;
     m.add_class::<NodeInfo>()?;
@@ -425,6 +644,7 @@ fn reclass_rs(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fsutil::TempDir;
 
     #[test]
     fn test_reclass_new() {
@@ -432,7 +652,7 @@ mod tests {
         assert_eq!(n.config.inventory_path, "./tests/inventory");
         assert_eq!(n.config.nodes_path, "./tests/inventory/nodes");
         assert_eq!(n.config.classes_path, "./tests/inventory/classes");
-        assert_eq!(n.config.ignore_class_notfound, false);
+        assert!(!n.config.ignore_class_notfound);
     }
 
     #[test]
@@ -444,6 +664,315 @@ mod tests {
         Reclass::new("./tests/broken-inventory", "nodes", "classes", false).unwrap();
     }
 
+    #[test]
+    fn test_reclass_discover_non_strict() {
+        let r = Reclass::new(
+            "./tests/inventory-strict-discovery",
+            "nodes",
+            "classes",
+            false,
+        )
+        .unwrap();
+        assert_eq!(r.classes.len(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Error while discovering classes: \
+        Found file './tests/inventory-strict-discovery/classes/nested/stray.txt' with unsupported \
+        extension in classes directory './tests/inventory-strict-discovery/classes'. \
+        Supported extensions are: yml, yaml")]
+    fn test_reclass_discover_strict() {
+        let mut c =
+            Config::new(Some("./tests/inventory-strict-discovery"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        Reclass::new_from_config(c).unwrap();
+    }
+
+    #[test]
+    fn test_reclass_discover_strict_ignore() {
+        let mut c =
+            Config::new(Some("./tests/inventory-strict-discovery"), None, None, None).unwrap();
+        c.load_from_file("reclass-config-ignore.yml", false)
+            .unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        assert_eq!(r.nodes.len(), 1);
+        assert_eq!(r.classes.len(), 2);
+    }
+
+    #[test]
+    fn test_reclass_render_node_with_sources() {
+        let mut c = Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.relative_uri = true;
+        let r = Reclass::new_from_config(c).unwrap();
+        let (n, sources) = r.render_node_with_sources("n1").unwrap();
+        assert_eq!(n.classes, vec!["cls1", "cls2"]);
+
+        let mut uris = sources.keys().collect::<Vec<_>>();
+        uris.sort();
+        assert_eq!(
+            uris,
+            vec![
+                "yaml_fs://classes/cls1.yml",
+                "yaml_fs://classes/cls2.yml",
+                "yaml_fs://nodes/n1.yml"
+            ]
+        );
+        assert_eq!(
+            sources["yaml_fs://nodes/n1.yml"],
+            std::fs::read_to_string("./tests/inventory/nodes/n1.yml").unwrap()
+        );
+        assert_eq!(
+            sources["yaml_fs://classes/cls1.yml"],
+            std::fs::read_to_string("./tests/inventory/classes/cls1.yml").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_reclass_render_node_snapshot() {
+        // Set up a scratch inventory which we can modify while "rendering"
+        let inv = TempDir::new("snapshot");
+        std::fs::create_dir_all(inv.join("nodes")).unwrap();
+        std::fs::create_dir_all(inv.join("classes")).unwrap();
+        std::fs::write(inv.join("nodes/n1.yml"), "classes: [cls1]").unwrap();
+        std::fs::write(inv.join("classes/cls1.yml"), "parameters: {foo: before}").unwrap();
+
+        let r = Reclass::new(inv.to_str().unwrap(), "nodes", "classes", false).unwrap();
+        let snapshot = Node::snapshot_sources(&r, "n1").unwrap();
+        // Simulate a concurrent modification after the render has started
+        std::fs::write(inv.join("classes/cls1.yml"), "parameters: {foo: after}").unwrap();
+
+        let n = r.render_node_from_snapshot("n1", snapshot).unwrap();
+        let n2 = r.render_node("n1").unwrap();
+
+        assert_eq!(
+            n.parameters.get(&"foo".into()),
+            Some(&Value::Literal("before".into()))
+        );
+        assert_eq!(
+            n2.parameters.get(&"foo".into()),
+            Some(&Value::Literal("after".into()))
+        );
+    }
+
+    #[test]
+    fn test_reclass_render_node_snapshot_inventory() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        let n = r.render_node_snapshot("n1").unwrap();
+        let expected = r.render_node("n1").unwrap();
+        assert_eq!(n.classes, expected.classes);
+        assert_eq!(n.parameters, expected.parameters);
+
+        // The snapshot only holds the node and the classes in its class closure
+        let snapshot = Node::snapshot_sources(&r, "n1").unwrap();
+        assert_eq!(
+            snapshot.len(),
+            1 + r.node_class_closure("n1").unwrap().len()
+        );
+        assert!(snapshot.len() < r.classes.len());
+    }
+
+    #[test]
+    fn test_reclass_node_class_closure() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        for n in ["n1", "n2", "n3", "n10"] {
+            let expected = r.render_node(n).unwrap().classes;
+            assert_eq!(r.node_class_closure(n).unwrap(), expected);
+        }
+        assert_eq!(
+            r.node_class_closure("n3").unwrap(),
+            vec!["cls4", "cls5", "cls6", "cls3"]
+        );
+        // Class names with references are returned unresolved
+        assert_eq!(
+            r.node_class_closure("n4").unwrap(),
+            vec!["cls8", "${qux}", "cls7"]
+        );
+    }
+
+    #[test]
+    fn test_reclass_node_references() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        assert_eq!(
+            r.node_references("n11").unwrap(),
+            vec!["bar:${foo}", "foo", "bar:foo_int"]
+        );
+        assert_eq!(
+            r.node_references("n14").unwrap(),
+            vec!["foodict", "foolist"]
+        );
+        assert!(r.node_references("n1").unwrap().is_empty());
+        assert!(r.node_references("missing").is_err());
+    }
+
+    #[test]
+    fn test_reclass_class_graph() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        let g = r.class_graph().unwrap();
+        assert_eq!(g.len(), r.classes.len());
+        assert_eq!(g["nested.cls1"], vec!["nested.cls2"]);
+        assert_eq!(g["cls1"], Vec::<String>::new());
+        // Class names with references are returned unresolved
+        assert!(g.values().flatten().any(|c| c == "${qux}"));
+    }
+
+    #[test]
+    fn test_reclass_node_exists() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        assert!(r.node_exists("n1"));
+        assert!(!r.node_exists("n1000"));
+        // classes aren't nodes
+        assert!(!r.node_exists("cls1"));
+    }
+
+    #[test]
+    fn test_reclass_entity_location() {
+        let mut c = Config::new(
+            Some("./tests/inventory-compose-node-name"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        assert_eq!(r.node_location("a"), Some(PathBuf::new()));
+        assert_eq!(r.node_location("b.1"), Some(PathBuf::from("b")));
+        assert_eq!(r.node_location("c._c.1"), Some(PathBuf::from("c/_c")));
+        // nodes in directories starting with `_` are treated as top-level nodes
+        assert_eq!(r.node_location("d2"), Some(PathBuf::new()));
+        assert_eq!(r.node_location("missing"), None);
+
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        assert_eq!(r.class_location("cls1"), Some(PathBuf::new()));
+        assert_eq!(
+            r.class_location("nested.cls1"),
+            Some(PathBuf::from("nested"))
+        );
+        assert_eq!(
+            r.class_location("..nested.cls1"),
+            Some(PathBuf::from("nested"))
+        );
+        assert_eq!(r.class_location("missing"), None);
+    }
+
+    #[test]
+    fn test_reclass_class_exists() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        assert!(r.class_exists("cls1"));
+        assert!(r.class_exists("nested.cls1"));
+        // relative class names are interpreted relative to `classes_path`
+        assert!(r.class_exists(".cls1"));
+        assert!(r.class_exists("..nested.cls1"));
+        assert!(!r.class_exists("missing"));
+        assert!(!r.class_exists("n1"));
+    }
+
+    #[test]
+    fn test_reclass_diff_configs() {
+        let mut c = Config::new(
+            Some("./tests/inventory-compose-node-name"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let mut a = c.clone();
+        a.compose_node_name = false;
+        let diff = Reclass::diff_configs(a, c.clone()).unwrap();
+        let mut nodes = diff.keys().collect::<Vec<_>>();
+        nodes.sort();
+        // Nested nodes are only discovered with `compose_node_name`, top-level nodes render
+        // identically.
+        assert_eq!(nodes, vec!["b.1", "c.1", "c._c.1", "d1", "d2"]);
+        let d = &diff["b.1"];
+        assert!(d.removed.is_empty() && d.changed.is_empty());
+        let added = d.added.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            added,
+            vec![
+                "_reclass_",
+                "node_name",
+                "short_name",
+                "path",
+                "parts",
+                "uri_suffix"
+            ]
+        );
+
+        let mut b = c.clone();
+        b.compatflags.insert(CompatFlag::ComposeNodeNameLiteralDots);
+        let diff = Reclass::diff_configs(c, b).unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(
+            diff["a.1"].changed,
+            vec![
+                (
+                    "_reclass_:name:parts".to_string(),
+                    Value::from(vec![Value::Literal("a.1".into())]),
+                    Value::from(vec![Value::Literal("a".into()), Value::Literal("1".into())]),
+                ),
+                (
+                    "_reclass_:name:path".to_string(),
+                    Value::Literal("a.1".into()),
+                    Value::Literal("a/1".into())
+                ),
+                (
+                    "_reclass_:name:short".to_string(),
+                    Value::Literal("a.1".into()),
+                    Value::Literal("1".into())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reclass_compose_node_name_literal_dots_node_override() {
+        let mut c = Config::new(
+            Some("./tests/inventory-compose-node-name"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c.clone()).unwrap();
+        let parts = |r: &Reclass, yaml: &str| {
+            let n = r.render_node_from_str("a.1", yaml).unwrap();
+            n.parameters
+                .get(&"_reclass_".into())
+                .and_then(|m| m.get(&"name".into()))
+                .and_then(|m| m.get(&"parts".into()))
+                .cloned()
+                .unwrap()
+        };
+        let split = Value::from(vec![Value::Literal("a".into()), Value::Literal("1".into())]);
+        let literal = Value::from(vec![Value::Literal("a.1".into())]);
+
+        // global compat flag is off, node opts into splitting on literal dots
+        assert_eq!(parts(&r, "classes: []"), literal);
+        assert_eq!(parts(&r, "compose_node_name_literal_dots: true"), split);
+
+        // global compat flag is on, node opts out of splitting on literal dots
+        c.compatflags.insert(CompatFlag::ComposeNodeNameLiteralDots);
+        let r = Reclass::new_from_config(c).unwrap();
+        assert_eq!(parts(&r, "classes: []"), split);
+        assert_eq!(parts(&r, "compose_node_name_literal_dots: false"), literal);
+    }
+
+    #[test]
+    fn test_reclass_render_subtree() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        let v = r.render_subtree("n3", "cluster:name").unwrap();
+        assert_eq!(v, Some(Value::Literal("c-test-cluster-1234".into())));
+
+        let v = r.render_subtree("n3", "cluster:missing").unwrap();
+        assert_eq!(v, None);
+
+        let v = r.render_subtree("n1", "_reclass_:name:parts:0").unwrap();
+        assert_eq!(v, Some(Value::Literal("n1".into())));
+    }
+
     #[test]
     fn test_reclass_discover_nodes_compose_node_name() {
         let mut c = Config::new(
@@ -471,4 +1000,444 @@ mod tests {
         assert_eq!(r.nodes["d1"].path, PathBuf::from("_d/d1.yml"));
         assert_eq!(r.nodes["d2"].path, PathBuf::from("_d/d/d2.yml"));
     }
+
+    #[test]
+    fn test_reclass_discover_nodes_init() {
+        let c = Config::new(Some("./tests/inventory-init"), None, None, None).unwrap();
+        let r = Reclass::new_from_config(c.clone()).unwrap();
+        let mut nodes = r.nodes.keys().cloned().collect::<Vec<_>>();
+        nodes.sort();
+        let classes = r.classes.keys().cloned().collect::<Vec<_>>();
+        let n = r.render_node("init").unwrap();
+
+        let mut c = c;
+        c.compose_node_name = true;
+        let r = Reclass::new_from_config(c).unwrap();
+        let mut composed_nodes = r.nodes.keys().cloned().collect::<Vec<_>>();
+        composed_nodes.sort();
+        let composed = r.render_node("foo.init").unwrap();
+
+        // `init.yml` is only special for classes
+        assert_eq!(classes, vec!["foo"]);
+        assert_eq!(nodes, vec!["bar", "init"]);
+        assert_eq!(
+            n.parameters.get(&"foo".into()),
+            Some(&Value::Literal("bar".into()))
+        );
+        assert_eq!(composed_nodes, vec!["bar", "foo.init", "init"]);
+        assert_eq!(
+            r.nodes["foo.init"].path,
+            PathBuf::from("foo").join("init.yml")
+        );
+        assert_eq!(composed.reclass.name, "foo.init");
+        assert_eq!(
+            composed.parameters.get(&"foo".into()),
+            Some(&Value::Literal("bar".into()))
+        );
+    }
+
+    #[test]
+    fn test_reclass_from_config_file() {
+        let r =
+            Reclass::from_config_file("./tests/inventory", "reclass-config.yml", false).unwrap();
+        assert_eq!(r.config.nodes_path, "./tests/inventory/nodes");
+        assert_eq!(r.config.classes_path, "./tests/inventory/classes");
+        assert!(r.config.ignore_class_notfound);
+        assert_eq!(r.nodes.len(), 25);
+    }
+
+    #[test]
+    fn test_reclass_render_node_error_context() {
+        let inv = "./tests/inventory-error-context";
+        let r = Reclass::new(inv, "nodes", "classes", false).unwrap();
+        let n1 = r.render_node("n1").unwrap_err();
+        let n2 = r.render_node("n2").unwrap_err();
+        let uri = |n: &str| {
+            format!(
+                "yaml_fs://{}",
+                to_lexical_absolute(&Path::new(inv).join(format!("nodes/{n}.yml")))
+                    .unwrap()
+                    .display()
+            )
+        };
+
+        assert_eq!(
+            n1.to_string(),
+            format!(
+                "While rendering parameters of node 'n1' ({}): While resolving references: \
+                Error while parsing ref: Error parsing reference '${{baz' in parameter 'foo.bar'",
+                uri("n1")
+            )
+        );
+        assert_eq!(
+            n2.to_string(),
+            format!(
+                "While rendering parameters of node 'n2' ({}): While resolving references: \
+                lookup error for reference '${{baz}}' in parameter 'foo.bar': key 'baz' not found",
+                uri("n2")
+            )
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_reclass_discover_symlink_loop() {
+        let inv = TempDir::new("symlink");
+        std::fs::create_dir_all(inv.join("nodes")).unwrap();
+        std::fs::create_dir_all(inv.join("classes/nested")).unwrap();
+        std::fs::write(inv.join("classes/nested/cls1.yml"), "parameters: {}").unwrap();
+        std::os::unix::fs::symlink("..", inv.join("classes/nested/loop")).unwrap();
+
+        let res = Reclass::new(inv.to_str().unwrap(), "nodes", "classes", false);
+
+        let err = res.unwrap_err().to_string();
+        let classes = inv.join("classes");
+        assert_eq!(
+            err,
+            format!(
+                "Error while discovering classes: Found symlink loop in classes directory '{}': \
+                '{}' points to its ancestor '{}'",
+                classes.display(),
+                classes.join("nested/loop").display(),
+                classes.display(),
+            )
+        );
+    }
+
+    #[test]
+    fn test_reclass_discover_custom_extensions() {
+        let mut c = Config::new(Some("./tests/inventory-custom-ext"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let mut nodes = r.nodes.keys().collect::<Vec<_>>();
+        nodes.sort();
+        assert_eq!(nodes, vec!["n1", "n2"]);
+        assert_eq!(r.nodes["n1"].path, PathBuf::from("n1.yaml.j2"));
+        assert!(r.classes.contains_key("cls1"));
+
+        let n = r.render_node("n1").unwrap();
+        assert_eq!(
+            n.parameters.get_path("_reclass_:name:path"),
+            Some(&Value::Literal("n1".into()))
+        );
+        assert_eq!(n.classes, vec!["cls1"]);
+        assert_eq!(
+            n.parameters.get(&"bar".into()),
+            Some(&Value::Literal("cls1".into()))
+        );
+    }
+
+    #[test]
+    fn test_reclass_discover_custom_extensions_default() {
+        // Files with `.yaml.j2` extension aren't discovered by default
+        let r = Reclass::new("./tests/inventory-custom-ext", "nodes", "classes", false).unwrap();
+        let nodes = r.nodes.keys().collect::<Vec<_>>();
+        assert_eq!(nodes, vec!["n2"]);
+        assert!(r.classes.is_empty());
+    }
+
+    #[test]
+    fn test_reclass_entities() {
+        let r = Reclass::new("./tests/inventory", "nodes", "classes", false).unwrap();
+        let entities = r.entities();
+        assert_eq!(entities.len(), r.nodes.len() + r.classes.len());
+        for (name, kind, path) in &entities {
+            let map = match kind {
+                EntityKind::Node => &r.nodes,
+                EntityKind::Class => &r.classes,
+            };
+            assert_eq!(&map[name].path, path);
+        }
+        let nodes = entities
+            .iter()
+            .take_while(|(_, kind, _)| *kind == EntityKind::Node)
+            .count();
+        assert_eq!(nodes, r.nodes.len());
+        assert_eq!(
+            entities[0],
+            ("n1".to_string(), EntityKind::Node, PathBuf::from("n1.yml"))
+        );
+        assert_eq!(entities[nodes].1.to_string(), "class");
+    }
+
+    #[test]
+    fn test_reclass_case_insensitive_classes() {
+        let mut c =
+            Config::new(Some("./tests/inventory-case-insensitive"), None, None, None).unwrap();
+        let r = Reclass::new_from_config(c.clone()).unwrap();
+        let err = r.render_node("n1").unwrap_err();
+        assert_eq!(err.to_string(), "Class Foo not found");
+
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        let r = Reclass::new_from_config(c).unwrap();
+        let mut classes = r.classes.keys().cloned().collect::<Vec<_>>();
+        classes.sort();
+        assert_eq!(classes, vec!["foo", "nested.bar", "nested.baz"]);
+        assert!(r.class_exists("FOO"));
+
+        let n = r.render_node("n1").unwrap();
+        assert_eq!(n.classes, vec!["nested.baz", "foo", "nested.bar"]);
+        for k in ["foo", "bar", "baz"] {
+            assert_eq!(n.parameters.get(&k.into()), Some(&Value::Literal(k.into())));
+        }
+
+        // Class includes which only differ in case are only loaded once
+        let n = r.render_node_from_str("n2", "classes: [Foo, foo]").unwrap();
+        assert_eq!(n.classes, vec!["foo"]);
+        assert_eq!(
+            n.parameters.get(&"l".into()),
+            Some(&Value::Sequence(vec![Value::Literal("a".into())]))
+        );
+    }
+
+    #[test]
+    fn test_reclass_case_insensitive_classes_duplicate() {
+        let inv = "./tests/inventory-case-duplicate";
+        let mut c = Config::new(Some(inv), None, None, None).unwrap();
+        let r = Reclass::new_from_config(c.clone()).map(|r| r.classes.len());
+        c.case_insensitive_classes = true;
+        let err = Reclass::new_from_config(c).unwrap_err();
+
+        let classes = Path::new(inv).join("classes");
+        assert_eq!(r.unwrap(), 2);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "Error while discovering classes: Definition of class 'foo' in '{}' collides \
+                with definition in '{}'. Classes can only be defined once per inventory.",
+                classes.join("Foo").join("init.yml").display(),
+                classes.join("foo.yml").display()
+            )
+        );
+    }
+
+    #[test]
+    fn test_reclass_render_inventory_each() {
+        let mut c = Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.load_from_file("reclass-config.yml", false).unwrap();
+        c.thread_count = Some(2);
+        let r = Reclass::new_from_config(c).unwrap();
+
+        let rendered = std::sync::Mutex::new(vec![]);
+        r.render_inventory_each(|name, info| {
+            let info = info.unwrap();
+            assert_eq!(
+                info.parameters.get_path("_reclass_:name:full").unwrap(),
+                &Value::Literal(name.clone())
+            );
+            rendered.lock().unwrap().push(name);
+        })
+        .unwrap();
+
+        let mut rendered = rendered.into_inner().unwrap();
+        rendered.sort();
+        let mut expected = r.nodes.keys().cloned().collect::<Vec<_>>();
+        expected.sort();
+        assert_eq!(rendered.len(), 25);
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn test_reclass_reload() {
+        let inv = TempDir::new("reload");
+        std::fs::create_dir_all(inv.join("nodes")).unwrap();
+        std::fs::create_dir_all(inv.join("classes")).unwrap();
+        std::fs::write(inv.join("nodes/n1.yml"), "classes: [cls1]").unwrap();
+        std::fs::write(inv.join("classes/cls1.yml"), "parameters: {foo: foo}").unwrap();
+
+        let mut r = Reclass::new(inv.to_str().unwrap(), "nodes", "classes", false).unwrap();
+        assert!(!r.node_exists("n2"));
+
+        std::fs::write(inv.join("nodes/n2.yml"), "classes: [cls1]").unwrap();
+        r.reload().unwrap();
+        let mut nodes = r.nodes.keys().cloned().collect::<Vec<_>>();
+        nodes.sort();
+        let n2 = r.render_node("n2");
+
+        // Introduce a collision, reloading should fail and leave no entities behind
+        std::fs::write(inv.join("nodes/n2.yaml"), "").unwrap();
+        let err = r.reload();
+
+        assert_eq!(nodes, vec!["n1", "n2"]);
+        assert_eq!(
+            n2.unwrap().parameters.get(&"foo".into()),
+            Some(&Value::Literal("foo".into()))
+        );
+        assert!(err
+            .unwrap_err()
+            .to_string()
+            .starts_with("Error while discovering nodes: Definition of node 'n2'"));
+        assert!(r.nodes.is_empty());
+        assert!(r.classes.is_empty());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_reclass_render_gzip_class() {
+        use std::io::Write;
+
+        let inv = TempDir::new("gzip");
+        std::fs::create_dir_all(inv.join("nodes")).unwrap();
+        std::fs::create_dir_all(inv.join("classes")).unwrap();
+        let class = "parameters:\n  foo:\n    bar: ${baz}\n  baz: [1, 2]\n";
+        std::fs::write(inv.join("classes/plain.yml"), class).unwrap();
+        let mut gz = flate2::write::GzEncoder::new(
+            std::fs::File::create(inv.join("classes/packed.yml.gz")).unwrap(),
+            flate2::Compression::default(),
+        );
+        gz.write_all(class.as_bytes()).unwrap();
+        gz.finish().unwrap();
+        std::fs::write(inv.join("nodes/plain.yml"), "classes: [plain]").unwrap();
+        std::fs::write(inv.join("nodes/packed.yml"), "classes: [packed]").unwrap();
+
+        let r = Reclass::new(inv.to_str().unwrap(), "nodes", "classes", false).unwrap();
+        let plain = r.render_node("plain");
+        let packed = r.render_node("packed");
+
+        assert_eq!(r.classes["packed"].path, PathBuf::from("packed.yml.gz"));
+        let (plain, packed) = (plain.unwrap(), packed.unwrap());
+        for k in ["foo", "baz"] {
+            assert_eq!(
+                plain.parameters.get(&k.into()),
+                packed.parameters.get(&k.into())
+            );
+        }
+    }
+
+    #[test]
+    fn test_reclass_discover_custom_extensions_duplicate() {
+        let inv = "./tests/inventory-custom-ext-duplicate";
+        let mut c = Config::new(Some(inv), None, None, None).unwrap();
+        c.yaml_extensions = vec!["yml".into(), "yaml.j2".into()];
+        let res = Reclass::new_from_config(c);
+
+        let nodes = Path::new(inv).join("nodes");
+        assert_eq!(
+            res.unwrap_err().to_string(),
+            format!(
+                "Error while discovering nodes: Definition of node 'n1' in '{}' collides with \
+                definition in '{}'. Nodes can only be defined once per inventory.",
+                nodes.join("n1.yaml.j2").display(),
+                nodes.join("n1.yml").display(),
+            )
+        );
+    }
+
+    #[test]
+    fn test_reclass_duplicate_entity_strategy() {
+        let mut c = Config::new(Some("./tests/inventory-duplicate-ext"), None, None, None).unwrap();
+        let err = Reclass::new_from_config(c.clone()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error while discovering nodes: Definition of node 'n1' in \
+            './tests/inventory-duplicate-ext/nodes/n1.yaml' collides with definition in \
+            './tests/inventory-duplicate-ext/nodes/n1.yml'. Nodes can only be defined once per \
+            inventory."
+        );
+
+        for (strategy, ext) in [
+            (DuplicateEntityStrategy::PreferYml, "yml"),
+            (DuplicateEntityStrategy::PreferYaml, "yaml"),
+        ] {
+            c.duplicate_entity_strategy = strategy;
+            let r = Reclass::new_from_config(c.clone()).unwrap();
+            assert_eq!(r.nodes["n1"].path, PathBuf::from(format!("n1.{ext}")));
+            assert_eq!(r.classes["cls"].path, PathBuf::from(format!("cls.{ext}")));
+
+            let n = r.render_node("n1").unwrap();
+            for k in ["node", "class"] {
+                assert_eq!(
+                    n.parameters.get(&k.into()),
+                    Some(&Value::Literal(ext.into()))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_reclass_duplicate_entity_strategy_different_paths() {
+        let inv = Some("./tests/inventory-duplicate-paths");
+        let config = |nodes, classes| {
+            let mut c = Config::new(inv, nodes, classes, None).unwrap();
+            c.duplicate_entity_strategy = DuplicateEntityStrategy::PreferYaml;
+            c
+        };
+
+        let mut c = config(None, None);
+        c.compose_node_name = true;
+        let err = Reclass::new_from_config(c).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Error while discovering nodes: Definition of node 'n'"));
+
+        let err = Reclass::new_from_config(config(Some("nodes-flat"), None)).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Error while discovering classes: Definition of class 'foo'"));
+
+        let mut c = config(Some("nodes-flat"), Some("classes-case"));
+        assert!(Reclass::new_from_config(c.clone()).is_ok());
+        c.case_insensitive_classes = true;
+        let err = Reclass::new_from_config(c).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Error while discovering classes: Definition of class 'bar'"));
+    }
+
+    #[test]
+    fn test_reclass_discover_nodes_max_discovery_depth() {
+        let mut c = Config::new(
+            Some("./tests/inventory-compose-node-name"),
+            None,
+            None,
+            None,
+        )
+        .unwrap();
+        c.load_from_file("reclass-config.yml", true).unwrap();
+        c.max_discovery_depth = Some(2);
+        let r = Reclass::new_from_config(c).unwrap();
+        let mut nodes = r.nodes.keys().collect::<Vec<_>>();
+        nodes.sort();
+        // `c/_c/1.yml` and `_d/d/d2.yml` are nested three levels deep
+        assert_eq!(nodes, vec!["a", "a.1", "b.1", "c.1", "d", "d1"]);
+    }
+
+    #[test]
+    fn test_reclass_discover_parallel() {
+        for (inv, cfg) in [
+            ("./tests/inventory", None),
+            (
+                "./tests/inventory-compose-node-name",
+                Some("reclass-config.yml"),
+            ),
+        ] {
+            let mut c = Config::new(Some(inv), None, None, None).unwrap();
+            if let Some(cfg) = cfg {
+                c.load_from_file(cfg, false).unwrap();
+            }
+            let serial = Reclass::new_from_config(c.clone()).unwrap();
+            c.parallel_discovery = true;
+            let parallel = Reclass::new_from_config(c).unwrap();
+            assert_eq!(serial.nodes, parallel.nodes);
+            assert_eq!(serial.classes, parallel.classes);
+        }
+    }
+
+    #[test]
+    fn test_reclass_discover_parallel_duplicate() {
+        let mut c = Config::new(Some("./tests/broken-inventory"), None, None, None).unwrap();
+        let serial = Reclass::new_from_config(c.clone()).unwrap_err();
+        c.parallel_discovery = true;
+        let parallel = Reclass::new_from_config(c).unwrap_err();
+        assert_eq!(serial.to_string(), parallel.to_string());
+    }
+
+    #[test]
+    fn test_reclass_discover_classes_max_discovery_depth() {
+        let mut c = Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+        c.max_discovery_depth = Some(1);
+        let r = Reclass::new_from_config(c).unwrap();
+        assert!(r.classes.contains_key("cls1"));
+        assert!(!r.classes.contains_key("nested.cls1"));
+    }
 }
@@ -10,7 +10,42 @@ pub struct RemovableList {
     negations: Vec<String>,
 }
 
+/// Default prefix which marks an item as removed, cf. Python reclass
+pub(crate) const DEFAULT_REMOVAL_PREFIX: &str = "~";
+
 impl RemovableList {
+    /// Creates a new list from `items`. Items which start with `prefix` remove the item without
+    /// the prefix from the list, see `List::append_if_new()`.
+    pub(crate) fn from_prefixed(items: Vec<String>, prefix: &str) -> Self {
+        let mut res = Self::with_capacity(items.len());
+        for it in items {
+            res.append_or_remove(it, prefix);
+        }
+        res
+    }
+
+    /// Appends `item` to the list, or removes it if it starts with `prefix`.
+    fn append_or_remove(&mut self, item: String, prefix: &str) {
+        match item.strip_prefix(prefix) {
+            // handle negation
+            Some(neg) if !prefix.is_empty() => self.handle_negation(neg.to_string()),
+            _ => self.push_if_new(item),
+        }
+    }
+
+    /// Appends `item` to the list if it's not present yet, without checking for a removal
+    /// prefix. Any stored negation for `item` is dropped.
+    fn push_if_new(&mut self, item: String) {
+        if let Some(negpos) = item_pos(&self.negations, &item) {
+            // Drop the stored negation, since it's superseded by adding the item.
+            self.negations.remove(negpos);
+        }
+        if item_pos(&self.items, &item).is_none() {
+            // Insert item if it isn't present in the list yet.
+            self.items.push(item);
+        }
+    }
+
     /// Handles negating the provided item
     ///
     /// Assumes that the negation prefix is already stripped from `negitem`
@@ -37,9 +72,10 @@ impl RemovableList {
         for n in negiter {
             self.handle_negation(n);
         }
-        // take items from other and append them using append_if_new
+        // take items from other and append them using push_if_new. The items in other have
+        // already been checked for the removal prefix.
         for it in itemiter {
-            self.append_if_new(it);
+            self.push_if_new(it);
         }
     }
 }
@@ -95,19 +131,14 @@ impl List for RemovableList {
     /// Regular strings are inserted in the list if they're not present yet. When `item` is
     /// prefixed with ~ it's removed from the list if present.  Negated items which can't be
     /// removed immediately are stored as negations, for later processing.
+    ///
+    /// A negation only removes items which were added before it. Adding an item after it was
+    /// negated re-includes the item. This matches Python reclass, which treats the removal of an
+    /// item that hasn't been added yet as a no-op.
+    ///
+    /// Use `RemovableList::from_prefixed()` to construct a list with a different removal prefix.
     fn append_if_new(&mut self, item: String) {
-        if let Some(neg) = item.strip_prefix('~') {
-            // handle negation
-            self.handle_negation(neg.to_string());
-        } else if let Some(negpos) = item_pos(&self.negations, &item) {
-            // Remove previously negated item from negations list instead of
-            // inserting it into the list.
-            self.negations.remove(negpos);
-        } else if item_pos(&self.items, &item).is_none() {
-            // Finally, insert item if neither condition applies and the item
-            // isn't present in the list yet.
-            self.items.push(item);
-        };
+        self.append_or_remove(item, DEFAULT_REMOVAL_PREFIX);
     }
 
     /// Merges other into self, consuming other
@@ -115,6 +146,9 @@ impl List for RemovableList {
     /// Negations from other are processed first, removing items which are already present from our
     /// list. Negations which weren't processed are kept and merged into the list's negations.
     /// Afterwards all items in other are taken and appended if they're not present in our list.
+    ///
+    /// Negations in other therefore only remove items which were merged into our list before
+    /// other. Items in other which we've stored negations for are re-included.
     fn merge(&mut self, other: Self) {
         self.merge_impl(other.items.into_iter(), other.negations.into_iter());
     }
@@ -222,7 +256,7 @@ mod removable_list_tests {
         let mut l = make_abc();
         l.append_if_new("~d".into());
         l.append_if_new("d".into());
-        let expected: Vec<String> = vec!["a".into(), "b".into(), "c".into()];
+        let expected: Vec<String> = vec!["a".into(), "b".into(), "c".into(), "d".into()];
         assert_eq!(l.items, expected);
         assert_eq!(l.negations, Vec::<String>::new());
     }
@@ -300,7 +334,7 @@ mod removable_list_tests {
     }
 
     #[test]
-    fn test_merge_add_apply_removal() {
+    fn test_merge_add_after_removal() {
         let mut l = make_abc();
         l.append_if_new("~d".into());
         let o: RemovableList = vec!["d".into()].into();
@@ -308,11 +342,38 @@ mod removable_list_tests {
 
         assert_eq!(
             l.items,
-            vec!["a".to_string(), "b".to_string(), "c".to_string()]
+            vec![
+                "a".to_string(),
+                "b".to_string(),
+                "c".to_string(),
+                "d".to_string()
+            ]
         );
         assert_eq!(l.negations, Vec::<String>::new());
     }
 
+    #[test]
+    fn test_merge_add_remove_add() {
+        // Entities are merged into the result list in order, e.g. class A, B and C.
+        let mut l = RemovableList::default();
+        l.merge(vec!["foo".into(), "bar".into()].into());
+        l.merge(vec!["~foo".into()].into());
+        assert_eq!(l.items, vec!["bar".to_string()]);
+        l.merge(vec!["foo".into()].into());
+        assert_eq!(l.items, vec!["bar".to_string(), "foo".to_string()]);
+        assert_eq!(l.negations, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_merge_remove_before_add() {
+        let mut l = RemovableList::default();
+        l.merge(vec!["~foo".into()].into());
+        l.merge(vec!["bar".into()].into());
+        l.merge(vec!["foo".into()].into());
+        assert_eq!(l.items, vec!["bar".to_string(), "foo".to_string()]);
+        assert_eq!(l.negations, Vec::<String>::new());
+    }
+
     #[test]
     fn test_merge_add_store_unique() {
         let mut l = make_abc();
@@ -330,11 +391,11 @@ mod removable_list_tests {
 
     #[test]
     fn test_deserialize_process_negations() {
-        let yaml = r#"
+        let yaml = r"
         - a
         - b
         - ~b
-        "#;
+        ";
         let l: RemovableList = serde_yaml::from_str(yaml).unwrap();
 
         assert_eq!(l.items, vec!["a".to_string()]);
@@ -343,11 +404,11 @@ mod removable_list_tests {
 
     #[test]
     fn test_deserialize_remove_duplicates() {
-        let yaml = r#"
+        let yaml = r"
         - a
         - a
         - b
-        "#;
+        ";
         let l: RemovableList = serde_yaml::from_str(yaml).unwrap();
 
         assert_eq!(l.items, vec!["a".to_string(), "b".to_string()]);
@@ -356,14 +417,46 @@ mod removable_list_tests {
 
     #[test]
     fn test_deserialize_store_negations() {
-        let yaml = r#"
+        let yaml = r"
         - a
         - b
         - ~c
-        "#;
+        ";
         let l: RemovableList = serde_yaml::from_str(yaml).unwrap();
 
         assert_eq!(l.items, vec!["a".to_string(), "b".to_string()]);
         assert_eq!(l.negations, vec!["c".to_string()]);
     }
+
+    #[test]
+    fn test_from_prefixed_default() {
+        let l = RemovableList::from_prefixed(
+            vec!["a".into(), "b".into(), "~a".into(), "-b".into()],
+            DEFAULT_REMOVAL_PREFIX,
+        );
+        assert_eq!(l.items, vec!["b".to_string(), "-b".to_string()]);
+    }
+
+    #[test]
+    fn test_from_prefixed_custom() {
+        let l = RemovableList::from_prefixed(
+            vec![
+                "a".into(),
+                "~b".into(),
+                "c".into(),
+                "--a".into(),
+                "--d".into(),
+            ],
+            "--",
+        );
+        // `~b` is a regular item for prefix `--`
+        assert_eq!(l.items, vec!["~b".to_string(), "c".to_string()]);
+        assert_eq!(l.negations, vec!["d".to_string()]);
+
+        // Merging doesn't process the default prefix for items of lists with a custom prefix
+        let mut m = make_abc();
+        m.merge(l);
+        assert_eq!(m.items, vec!["a", "b", "c", "~b"]);
+        assert_eq!(m.negations, vec!["d".to_string()]);
+    }
 }
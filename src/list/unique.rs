@@ -161,10 +161,10 @@ mod unique_list_tests {
 
     #[test]
     fn test_deserialize() {
-        let yaml = r#"
+        let yaml = r"
         - a
         - b
-        "#;
+        ";
         let l: UniqueList = serde_yaml::from_str(yaml).unwrap();
 
         assert_eq!(l.items, vec!["a".to_string(), "b".to_string()]);
@@ -172,11 +172,11 @@ mod unique_list_tests {
 
     #[test]
     fn test_deserialize_unique() {
-        let yaml = r#"
+        let yaml = r"
         - a
         - b
         - a
-        "#;
+        ";
         let l: UniqueList = serde_yaml::from_str(yaml).unwrap();
 
         assert_eq!(l.items, vec!["a".to_string(), "b".to_string()]);
@@ -1,12 +1,14 @@
 use anyhow::{anyhow, Result};
 use serde::Deserialize;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
 // TODO(sg): Switch to serde_yaml's `apply_merge()` once it supports recursive merges, cf.
 // https://github.com/dtolnay/serde-yaml/issues/362
 use yaml_merge_keys::merge_keys_serde;
 
-use crate::fsutil::to_lexical_absolute;
-use crate::list::{List, RemovableList, UniqueList};
+use crate::config::{Config, ReclassMetaPolicy};
+use crate::fsutil::{strip_extension, to_lexical_absolute};
+use crate::list::{List, RemovableList, UniqueList, DEFAULT_REMOVAL_PREFIX};
 use crate::refs::{ResolveState, Token};
 use crate::types::{Mapping, Value};
 use crate::Reclass;
@@ -15,27 +17,72 @@ mod nodeinfo;
 
 pub(crate) use nodeinfo::*;
 
+/// Suffix which marks an entry in `classes` as soft include. Soft includes of classes which don't
+/// exist are skipped, regardless of config option `ignore_class_notfound`.
+const SOFT_INCLUDE_MARKER: char = '?';
+
 /// Represents a Reclass node or class
 #[derive(Debug, Default, Deserialize)]
 pub struct Node {
-    /// List of Reclass applications for this node
-    #[serde(default)]
+    /// List of Reclass applications for this node as parsed from YAML
+    #[serde(default, rename = "applications")]
+    apps: Vec<String>,
+    /// List of Reclass applications for this node. Applications prefixed with the configured
+    /// removal prefix are processed as removals.
+    #[serde(skip)]
     pub applications: RemovableList,
     /// List of Reclass classes included by this node
     #[serde(default)]
     pub classes: UniqueList,
+    /// Environment of this node as parsed from YAML. The node's environment defaults to the
+    /// configured `default_environment` if the field is not present.
+    #[serde(default)]
+    environment: Option<String>,
+    /// Per-node override for compat flag `ComposeNodeNameLiteralDots` as parsed from YAML. Only
+    /// has an effect for nodes.
+    #[serde(default)]
+    compose_node_name_literal_dots: Option<bool>,
     /// Reclass parameters for this node as parsed from YAML
     #[serde(default, rename = "parameters")]
     params: serde_yaml::Mapping,
     /// Reclass parameters for this node converted into our own mapping type
     #[serde(skip)]
     parameters: Mapping,
+    /// Reclass exports for this node as parsed from YAML
+    #[serde(default, rename = "exports")]
+    exps: serde_yaml::Mapping,
+    /// Reclass exports for this node converted into our own mapping type
+    #[serde(skip)]
+    exports: Mapping,
     /// Location of this node relative to `classes_path`. `None` for nodes.
     #[serde(skip)]
     own_loc: Option<PathBuf>,
     /// Information about the node, empty (default value) for Node objects parsed from classes.
     #[serde(skip)]
     meta: NodeInfoMeta,
+    /// Warnings which were generated while rendering the node.
+    #[serde(skip)]
+    warnings: Vec<String>,
+    /// Missing classes which were ignored while rendering the node.
+    #[serde(skip)]
+    ignored_classes: Vec<String>,
+    /// Comments preceding top-level parameter keys, keyed by parameter name. Only populated if
+    /// config option `extract_parameter_docs` is enabled.
+    #[serde(skip)]
+    parameter_docs: HashMap<String, String>,
+    /// Classes which were loaded while rendering the node, in the order in which they were
+    /// merged.
+    #[serde(skip)]
+    class_load_order: Vec<String>,
+    /// Label of this entity in `parameter_sources`, i.e. the absolute class name for classes and
+    /// the node name for nodes. Parameters of entities with an empty label aren't tracked.
+    #[serde(skip)]
+    source: String,
+    /// Leaf parameter paths mapped to the label of the entity which last wrote them. Only
+    /// populated if config option `track_parameter_sources` is enabled. We use a `BTreeMap` so
+    /// that all paths below a given path can be looked up efficiently.
+    #[serde(skip)]
+    parameter_sources: BTreeMap<String, String>,
 }
 
 impl Node {
@@ -43,13 +90,74 @@ impl Node {
     ///
     /// The heavy lifting is done in `Reclass.discover_nodes()` and `Node::from_str`.
     pub fn parse(r: &Reclass, name: &str) -> Result<Self> {
+        Self::parse_with_sources(r, name, None)
+    }
+
+    /// Parse node from file with basename `name` in `r.nodes_path`.
+    ///
+    /// If `sources` is `Some`, the raw contents of the node file are inserted into the provided
+    /// map, keyed by the node's URI. If the map already holds contents for the node's URI, those
+    /// contents are used instead of reading the node file.
+    pub(crate) fn parse_with_sources(
+        r: &Reclass,
+        name: &str,
+        sources: Option<&mut HashMap<String, String>>,
+    ) -> Result<Self> {
         let nodeinfo = r.nodes.get(name).ok_or(anyhow!("Unknown node {name}"))?;
         let invpath = r.config.node_path(&nodeinfo.path);
-        let ncontents = std::fs::read_to_string(invpath.canonicalize()?)?;
+        let uri = Self::node_uri(r, &invpath)?;
+        let ncontents = read_source(&invpath, &uri, sources)?;
+        let meta = NodeInfoMeta::new(
+            name,
+            name,
+            &uri,
+            strip_extension(&nodeinfo.path, &r.config.entity_extensions())
+                .unwrap_or_else(|| nodeinfo.path.with_extension("")),
+            &r.config.default_environment,
+        );
+        Node::from_source(r, meta, None, &ncontents)
+            .map_err(|e| anyhow!("Deserializing node {name}: {e}"))
+    }
+
+    /// Reads the node file for node `name` and the class files of all classes in the node's class
+    /// closure, see `class_closure()`. Returns the raw file contents keyed by the node's or
+    /// class's URI.
+    ///
+    /// The returned map can be passed to `parse_with_sources()` and `render_with_sources()` to
+    /// render the node without reading any further files. Classes whose name contains references
+    /// aren't part of the class closure, and are read from disk when rendering the node.
+    pub(crate) fn snapshot_sources(r: &Reclass, name: &str) -> Result<HashMap<String, String>> {
+        let mut sources = HashMap::new();
+        let n = Self::parse_with_sources(r, name, Some(&mut sources))?;
+        n.class_closure_impl(
+            r,
+            &mut vec![],
+            &mut vec![],
+            &mut UniqueList::default(),
+            Some(&mut sources),
+        )?;
+        Ok(sources)
+    }
 
-        let uri = format!("yaml_fs://{}", to_lexical_absolute(&invpath)?.display());
-        let meta = NodeInfoMeta::new(name, name, &uri, nodeinfo.path.with_extension(""), "base");
-        Node::from_str(meta, None, &ncontents)
+    /// Returns the URI of the node file at `invpath`.
+    fn node_uri(r: &Reclass, invpath: &Path) -> Result<String> {
+        if r.config.relative_uri {
+            r.config.relative_entity_uri(invpath)
+        } else {
+            Ok(format!(
+                "yaml_fs://{}",
+                to_lexical_absolute(invpath)?.display()
+            ))
+        }
+    }
+
+    /// Returns the URI of the class file at `invpath`.
+    fn class_uri(r: &Reclass, invpath: &Path) -> Result<String> {
+        if r.config.relative_uri {
+            r.config.relative_entity_uri(invpath)
+        } else {
+            Ok(format!("yaml_fs://{}", invpath.canonicalize()?.display()))
+        }
     }
 
     /// Initializes a `Node` struct from a string.
@@ -58,9 +166,25 @@ impl Node {
     /// in the class hierarchy. If the parameter is `None`, relative includes are treated as
     /// relative to `classes_path`.
     pub fn from_str(meta: NodeInfoMeta, npath: Option<PathBuf>, ncontents: &str) -> Result<Self> {
+        Self::from_str_impl(meta, npath, ncontents, DEFAULT_REMOVAL_PREFIX)
+    }
+
+    /// Initializes a `Node` struct from a string, see `from_str()`. Applications which start with
+    /// `removal_prefix` are processed as removals.
+    fn from_str_impl(
+        meta: NodeInfoMeta,
+        npath: Option<PathBuf>,
+        ncontents: &str,
+        removal_prefix: &str,
+    ) -> Result<Self> {
         let mut n: Node = serde_yaml::from_str(ncontents)?;
+        n.applications = RemovableList::from_prefixed(std::mem::take(&mut n.apps), removal_prefix);
         n.own_loc = npath;
         n.meta = meta;
+        if let Some(env) = &n.environment {
+            n.meta.environment.clone_from(env);
+        }
+        n.meta.literal_dots = n.compose_node_name_literal_dots;
 
         // Transform any relative class names to absolute class names, based on the new node's
         // `own_loc`.
@@ -71,25 +195,71 @@ impl Node {
         classes.shrink_to_fit();
         n.classes = classes;
 
-        // Resolve YAML merge keys in `params`
+        // Resolve YAML merge keys in `params` and `exps`
         let p = merge_keys_serde(serde_yaml::Value::from(n.params))?
             .as_mapping()
             .unwrap()
             .clone();
         n.params = p;
+        let e = merge_keys_serde(serde_yaml::Value::from(n.exps))?
+            .as_mapping()
+            .unwrap()
+            .clone();
+        n.exps = e;
 
         // Convert serde_yaml::Mapping into our own Mapping type
         n.parameters = n.params.clone().into();
+        n.exports = n.exps.clone().into();
+
+        Ok(n)
+    }
 
+    /// Initializes a `Node` struct from the contents of a node or class file.
+    ///
+    /// In contrast to `from_str()`, this method also extracts the parameter documentation comments
+    /// if config option `extract_parameter_docs` is enabled.
+    pub(crate) fn from_source(
+        r: &Reclass,
+        meta: NodeInfoMeta,
+        npath: Option<PathBuf>,
+        contents: &str,
+    ) -> Result<Self> {
+        let mut n =
+            Self::from_str_impl(meta, npath, contents, &r.config.application_removal_prefix)?;
+        if r.config.extract_parameter_docs {
+            n.parameter_docs = extract_parameter_docs(contents);
+        }
         Ok(n)
     }
 
+    /// Returns the reference paths of all references which are present in the Node's unrendered
+    /// parameters, in the order in which they appear.
+    ///
+    /// Reference paths which appear multiple times are only returned once. See
+    /// [`Token::references()`] for details on how nested references are reported.
+    pub fn references(&self) -> Result<Vec<String>> {
+        let mut refs = UniqueList::default();
+        for (_, v) in &self.parameters {
+            collect_references(v, &mut refs)?;
+        }
+        Ok(refs.into())
+    }
+
     /// Turns a relative class name (prefixed with one or more `.`) into an absolute class name
     /// based on the current `Node`'s location (field `own_loc`).
     ///
     /// Note that an arbitrary number of leading dots will be consumed, but the top-most directory
     /// which can anchor the class is the directory given as `classes_path`.
-    fn abs_class_name(&self, class: &str) -> Result<String> {
+    ///
+    /// Returns an error if the class name contains consecutive dots after any leading dots (e.g.
+    /// `foo..bar`), since such names would produce an empty class name segment.
+    pub(crate) fn abs_class_name(&self, class: &str) -> Result<String> {
+        if class.trim_start_matches('.').contains("..") {
+            return Err(anyhow!(
+                "Invalid class name '{class}': consecutive dots are only allowed at the start of \
+                a relative class name"
+            ));
+        }
         if !class.starts_with('.') {
             // bail early for absolute classes
             return Ok(class.to_string());
@@ -128,8 +298,7 @@ impl Node {
                 std::path::Component::CurDir => {}
                 _ => {
                     return Err(anyhow!(
-                        "Unexpected non-normal path segment in class lookup: {:?}",
-                        d
+                        "Unexpected non-normal path segment in class lookup: {d:?}"
                     ))
                 }
             }
@@ -139,6 +308,27 @@ impl Node {
         Ok(absclass)
     }
 
+    /// Returns the relative class reference (with leading dot) for the absolute class reference
+    /// `class`, if a class with that name exists relative to the current Node's location. Returns
+    /// None for relative references, for Nodes which aren't located in a subdirectory of
+    /// `r.classes_path` and if no matching relative class exists.
+    fn relative_class_hint(&self, r: &Reclass, class: &str) -> Result<Option<String>> {
+        if class.starts_with('.')
+            || self
+                .own_loc
+                .as_ref()
+                .is_none_or(|l| l.as_os_str().is_empty())
+        {
+            return Ok(None);
+        }
+        let relative = format!(".{class}");
+        if r.class_info(&self.abs_class_name(&relative)?).is_some() {
+            Ok(Some(relative))
+        } else {
+            Ok(None)
+        }
+    }
+
     /// Looks up and parses `Node` from provided `class` string relative to own location.
     ///
     /// If the current Node's location is empty, relative class references inherently turn into
@@ -150,11 +340,26 @@ impl Node {
     ///
     /// The method extracts the the relative file path for the class in `r.classes_dir` from
     /// `r.classes`.
-    fn read_class(&self, r: &Reclass, class: &str) -> Result<Option<Self>> {
+    ///
+    /// If `sources` is `Some`, the raw contents of the class file are inserted into the provided
+    /// map, keyed by the class's URI. If the map already holds contents for the class's URI, those
+    /// contents are used instead of reading the class file.
+    fn read_class(
+        &self,
+        r: &Reclass,
+        class: &str,
+        sources: Option<&mut HashMap<String, String>>,
+    ) -> Result<Option<Self>> {
         let cls = self.abs_class_name(class)?;
 
         // Lookup path for provided class in r.classes, handling ignore_class_notfound
-        let Some(classinfo) = r.classes.get(&cls) else {
+        let Some(classinfo) = r.class_info(&cls) else {
+            if r.config.strict_relative_classes {
+                if let Some(hint) = self.relative_class_hint(r, class)? {
+                    return Err(anyhow!("Class {cls} not found, did you mean '{hint}'?"));
+                }
+            }
+
             // ignore_class_notfound_regexp is only applied if ignore_class_notfound == true.
             // By default the regexset has a single pattern for .* so that all missing classes are
             // ignored.
@@ -164,9 +369,9 @@ impl Node {
 
             if r.config.ignore_class_notfound {
                 // return an error informing the user that we didn't ignore the missing class
-                // based on the configured regex patterns.
+                // based on the configured patterns.
                 eprintln!(
-                    "Missing class '{cls}' not ignored due to configured regex patterns: [{}]",
+                    "Missing class '{cls}' not ignored due to configured patterns: [{}]",
                     r.config
                         .get_ignore_class_notfound_regexp()
                         .iter()
@@ -183,16 +388,29 @@ impl Node {
 
         // Load file contents and create Node
         let mut meta = NodeInfoMeta::default();
-        let ccontents = std::fs::read_to_string(invpath.canonicalize()?)?;
-        meta.uri = format!("yaml_fs://{}", invpath.canonicalize()?.display());
-        Ok(Some(
-            Node::from_str(meta, Some(classinfo.loc.clone()), &ccontents)
-                .map_err(|e| anyhow!("Deserializing {cls}: {e}"))?,
-        ))
+        meta.uri = Self::class_uri(r, &invpath)?;
+        let ccontents = read_source(&invpath, &meta.uri, sources)?;
+        let mut c = Node::from_source(r, meta, Some(classinfo.loc.clone()), &ccontents)
+            .map_err(|e| anyhow!("Deserializing {cls}: {e}"))?;
+        c.source = cls;
+        Ok(Some(c))
     }
 
-    /// Merges self into other, then updates self with merged values from other
-    fn merge_into(&mut self, other: &mut Self) -> Result<()> {
+    /// Merges self into other, then updates self with merged values from other.
+    ///
+    /// Returns an error if merging parameters or exports produces a ValueList which is longer
+    /// than the configured `max_value_list_length`. If config option `sequence_index_keys` is
+    /// enabled, keys of the form `path:N` override individual sequence elements.
+    fn merge_into(&mut self, other: &mut Self, config: &Config) -> Result<()> {
+        let max_value_list_len = config.max_value_list_length;
+        let merge = |base: &mut Mapping, m: &Mapping| {
+            if config.sequence_index_keys {
+                base.merge_with_index_keys(m, max_value_list_len, config.unique_sequence_merge)
+            } else {
+                base.merge_with_limit(m, max_value_list_len)
+            }
+        };
+
         // We use std::mem::take() here so we can merge self.applications into other.applications
         // without having to call `clone()` twice. This doesn't destroy `self.applications` because
         // we update `self.applications` with the result of the merge immediately afterwards.
@@ -207,18 +425,67 @@ impl Node {
         other.classes.merge(self_classes);
         self.classes = other.classes.clone();
 
-        other.parameters.merge(&self.parameters)?;
+        if config.track_parameter_sources && !self.source.is_empty() {
+            record_parameter_sources(
+                &self.parameters,
+                &self.source,
+                &mut vec![],
+                &mut other.parameter_sources,
+            );
+        }
+        merge(&mut other.parameters, &self.parameters)
+            .map_err(|e| anyhow!("While merging parameters: {e}"))?;
         self.parameters = other.parameters.clone();
+
+        merge(&mut other.exports, &self.exports)
+            .map_err(|e| anyhow!("While merging exports: {e}"))?;
+        self.exports = other.exports.clone();
+
+        self.warnings.clone_from(&other.warnings);
+        self.ignored_classes.clone_from(&other.ignored_classes);
+
+        // Comments in entities which are merged later take precedence
+        other
+            .parameter_docs
+            .extend(std::mem::take(&mut self.parameter_docs));
+        self.parameter_docs.clone_from(&other.parameter_docs);
+        self.parameter_sources.clone_from(&other.parameter_sources);
         Ok(())
     }
 
-    /// Recursively loads classes and merges loaded data into self
-    fn render_impl(&mut self, r: &Reclass, seen: &mut Vec<String>, root: &mut Node) -> Result<()> {
-        for cls in self.classes.items_iter() {
-            let cls = if cls.contains("${") {
+    /// Recursively loads classes and merges loaded data into self. If `sources` is `Some`, the raw
+    /// contents of all loaded classes are inserted into the provided map.
+    ///
+    /// Parameter `stack` holds the classes which are currently being loaded, i.e. the chain of
+    /// includes which lead to self. If config option `class_include_cycle_error` is enabled, an
+    /// error is returned when a class on the stack is included again.
+    fn render_impl(
+        &mut self,
+        r: &Reclass,
+        seen: &mut Vec<String>,
+        stack: &mut Vec<String>,
+        root: &mut Node,
+        mut sources: Option<&mut HashMap<String, String>>,
+    ) -> Result<()> {
+        let classes: Vec<&String> = if r.config.defer_class_references {
+            // Load classes whose name contains references after all other classes of this Node,
+            // so that the references can be resolved against the parameters of those classes.
+            let (dynamic, mut classes): (Vec<_>, Vec<_>) = self
+                .classes
+                .items_iter()
+                .partition(|cls| cls.contains("${"));
+            classes.extend(dynamic);
+            classes
+        } else {
+            self.classes.items_iter().collect()
+        };
+        // Soft includes of missing classes, which are left out of the Node's class list
+        let mut skipped = vec![];
+        for entry in classes {
+            let cls = if entry.contains("${") {
                 // Resolve any potential references if the class name contains an opening reference
                 // symbol.
-                let clstoken = Token::parse(&cls.clone())?;
+                let clstoken = Token::parse(&entry.clone())?;
                 if let Some(clstoken) = clstoken {
                     // If we got a token, render it, and convert it into a string with
                     // `raw_string()` to ensure no spurious quotes are injected.
@@ -229,13 +496,20 @@ impl Node {
                 } else {
                     // If Token::parse() returns None, the class name can't contain any references,
                     // just convert cls into an owned String.
-                    cls.to_string()
+                    entry.clone()
                 }
             } else {
                 // If the class name doesn't contain any opening reference symbols, it can't
                 // contain any references, just convert cls into an owned String.
-                cls.to_string()
+                entry.clone()
+            };
+
+            // Strip the soft include marker, so that the class is tracked under its actual name.
+            let (cls, soft) = match cls.strip_suffix(SOFT_INCLUDE_MARKER) {
+                Some(c) => (c.to_string(), true),
+                None => (cls, false),
             };
+            let cls = self.canonical_class_name(r, cls)?;
 
             // Check if we've seen the class already after resolving any references in the class
             // name.
@@ -243,35 +517,253 @@ impl Node {
                 continue;
             }
 
+            // Classes are only added to `seen` once they're fully loaded. Therefore a class which
+            // is included while it's being loaded can't be in `seen` yet.
+            if let Some(pos) = stack.iter().position(|c| *c == cls) {
+                if r.config.class_include_cycle_error {
+                    let mut cycle = stack[pos..].to_vec();
+                    cycle.push(cls);
+                    return Err(anyhow!(
+                        "Detected class include cycle: {}",
+                        cycle.join(" -> ")
+                    ));
+                }
+                continue;
+            }
+
+            // Soft includes of missing classes are skipped regardless of `ignore_class_notfound`
+            if soft && r.class_info(&self.abs_class_name(&cls)?).is_none() {
+                skipped.push(entry.clone());
+                continue;
+            }
+
             // Load class, respecting the `ignore_class_notfound` option
-            let maybec = self.read_class(r, &cls);
+            let maybec = self.read_class(r, &cls, sources.as_deref_mut());
             let Ok(Some(mut c)) = maybec else {
                 if let Ok(None) = maybec {
                     #[cfg(not(feature = "bench"))]
                     eprintln!("ignore missing class {cls}");
+                    if !root.ignored_classes.contains(&cls) {
+                        root.ignored_classes.push(cls);
+                    }
                     continue;
                 }
                 return Err(maybec.unwrap_err());
             };
 
+            c.apply_reclass_meta_policy(r, &format!("class {cls}"), &mut root.warnings)?;
+
             // render class so we pick up further classes included in it
-            c.render_impl(r, seen, root)?;
+            stack.push(cls.clone());
+            c.render_impl(r, seen, stack, root, sources.as_deref_mut())?;
+            stack.pop();
             // NOTE(sg): we don't need to merge here, since we've already mergeed into root as part
             // of the recursive call to `render_impl()`
 
-            seen.push(cls.to_string());
+            seen.push(cls.clone());
+        }
+
+        if r.config.warn_dangling_overrides {
+            let entity = if self.meta.name.is_empty() {
+                self.meta.uri.clone()
+            } else {
+                format!("node {}", self.meta.name)
+            };
+            for path in self.parameters.dangling_overrides(&root.parameters) {
+                root.warnings.push(format!(
+                    "Parameter '{path}' in {entity} is marked as override, but there's no \
+                    inherited value to override"
+                ));
+            }
         }
 
+        self.classes = self.class_list(r, &skipped)?;
+
         // merge root into self, then update self with merged values
-        self.merge_into(root)
+        self.merge_into(root, &r.config)
+    }
+
+    /// Returns the Node's class list with the soft include marker stripped from all class names.
+    /// Entries which are present in `skipped` are left out. Class names which don't contain
+    /// references are converted to their canonical name, see `canonical_class_name()`.
+    fn class_list(&self, r: &Reclass, skipped: &[String]) -> Result<UniqueList> {
+        let mut classes = UniqueList::with_capacity(self.classes.len());
+        for cls in self.classes.items_iter() {
+            if skipped.contains(cls) {
+                continue;
+            }
+            let cls = cls.strip_suffix(SOFT_INCLUDE_MARKER).unwrap_or(cls);
+            if cls.contains("${") {
+                classes.append_if_new(cls.to_string());
+            } else {
+                classes.append_if_new(self.canonical_class_name(r, cls.to_string())?);
+            }
+        }
+        Ok(classes)
+    }
+
+    /// Returns the name under which class `cls` is tracked while loading classes. If config
+    /// option `case_insensitive_classes` is enabled, that's the lowercased absolute class name.
+    /// Otherwise, `cls` is returned unchanged.
+    fn canonical_class_name(&self, r: &Reclass, cls: String) -> Result<String> {
+        if r.config.case_insensitive_classes {
+            Ok(self.abs_class_name(&cls)?.to_lowercase())
+        } else {
+            Ok(cls)
+        }
+    }
+
+    /// Returns the names of all classes which are included by this Node, either directly or through
+    /// other classes, in the same order in which rendering the Node would list them.
+    ///
+    /// In contrast to `render()`, this method doesn't resolve references or merge parameters.
+    /// Class names which contain references are returned unresolved, and their includes aren't
+    /// followed.
+    ///
+    /// Class include cycles are handled in the same way as in `render()`.
+    pub(crate) fn class_closure(&self, r: &Reclass) -> Result<Vec<String>> {
+        let mut seen = vec![];
+        let mut classes = UniqueList::default();
+        self.class_closure_impl(r, &mut seen, &mut vec![], &mut classes, None)?;
+        Ok(classes.into())
+    }
+
+    /// Returns the classes which are directly included by the discovered class `cls`.
+    ///
+    /// Only the class's `classes` key is parsed, so invalid parameters in the class don't cause
+    /// an error. Relative class names are resolved based on the class's location. Class names
+    /// which contain references are returned unresolved.
+    pub(crate) fn class_includes(r: &Reclass, cls: &str) -> Result<Vec<String>> {
+        #[derive(Deserialize)]
+        struct Includes {
+            #[serde(default)]
+            classes: UniqueList,
+        }
+
+        let classinfo = r.class_info(cls).ok_or(anyhow!("Class {cls} not found"))?;
+        let invpath = r.config.class_path(&classinfo.path);
+        let contents = read_source(&invpath, "", None)?;
+        let includes: Includes =
+            serde_yaml::from_str(&contents).map_err(|e| anyhow!("Deserializing {cls}: {e}"))?;
+        let c = Node {
+            own_loc: Some(classinfo.loc.clone()),
+            ..Default::default()
+        };
+        includes
+            .classes
+            .items_iter()
+            .map(|inc| c.abs_class_name(inc))
+            .collect()
+    }
+
+    /// Recursively collects the classes included by self into `classes`. See `render_impl()` for
+    /// the meaning of `seen`, `stack` and `sources`.
+    fn class_closure_impl(
+        &self,
+        r: &Reclass,
+        seen: &mut Vec<String>,
+        stack: &mut Vec<String>,
+        classes: &mut UniqueList,
+        mut sources: Option<&mut HashMap<String, String>>,
+    ) -> Result<()> {
+        let mut skipped = vec![];
+        for entry in self.classes.items_iter() {
+            // We can't load classes whose name contains references without rendering parameters.
+            if entry.contains("${") {
+                continue;
+            }
+            let (cls, soft) = match entry.strip_suffix(SOFT_INCLUDE_MARKER) {
+                Some(c) => (c, true),
+                None => (entry.as_str(), false),
+            };
+            let cls = self.canonical_class_name(r, cls.to_string())?;
+            if seen.contains(&cls) {
+                continue;
+            }
+            if let Some(pos) = stack.iter().position(|c| *c == cls) {
+                if r.config.class_include_cycle_error {
+                    let mut cycle = stack[pos..].to_vec();
+                    cycle.push(cls);
+                    return Err(anyhow!(
+                        "Detected class include cycle: {}",
+                        cycle.join(" -> ")
+                    ));
+                }
+                continue;
+            }
+            if soft && r.class_info(&self.abs_class_name(&cls)?).is_none() {
+                skipped.push(entry.clone());
+                continue;
+            }
+            // Missing classes which are ignored are skipped, similar to `render_impl()`.
+            let Some(c) = self.read_class(r, &cls, sources.as_deref_mut())? else {
+                continue;
+            };
+            stack.push(cls.clone());
+            c.class_closure_impl(r, seen, stack, classes, sources.as_deref_mut())?;
+            stack.pop();
+            seen.push(cls);
+        }
+        classes.merge_from(&self.class_list(r, &skipped)?);
+        Ok(())
+    }
+
+    /// Applies the configured `ReclassMetaPolicy` to a user-defined `_reclass_` parameter in this
+    /// Node. Parameter `entity` is used to identify the Node in error and warning messages.
+    /// Warnings are added to `warnings`.
+    fn apply_reclass_meta_policy(
+        &mut self,
+        r: &Reclass,
+        entity: &str,
+        warnings: &mut Vec<String>,
+    ) -> Result<()> {
+        let key = Value::from("_reclass_");
+        if !self.parameters.contains_key(&key) {
+            return Ok(());
+        }
+        match r.config.reclass_meta_policy {
+            ReclassMetaPolicy::Merge => Ok(()),
+            ReclassMetaPolicy::Warn => {
+                warnings.push(format!(
+                    "Parameter _reclass_ is reserved, but is defined in {entity}"
+                ));
+                Ok(())
+            }
+            ReclassMetaPolicy::Override => {
+                // Mark the user-defined value as overriding, so that it replaces the automatic
+                // parameter (or any previous user-defined values) when it's merged into the base
+                // node.
+                self.parameters.mark_override(&key);
+                Ok(())
+            }
+            ReclassMetaPolicy::Reserve => Err(anyhow!(
+                "Parameter _reclass_ is reserved, but is defined in {entity}"
+            )),
+        }
+    }
+
+    /// Returns a description of the node for render errors, which includes the node's URI if it's
+    /// known.
+    fn render_entity(&self) -> String {
+        if self.meta.uri.is_empty() {
+            format!("node '{}'", self.meta.name)
+        } else {
+            format!("node '{}' ({})", self.meta.name, self.meta.uri)
+        }
     }
 
     /// Renders the Node's parameters by interpolating Reclass references and flattening
     /// ValueLists.
-    fn render_parameters(&mut self) -> Result<()> {
+    fn render_parameters(&mut self, r: &Reclass) -> Result<()> {
         let p = std::mem::take(&mut self.parameters);
         let mut f = Value::Mapping(p);
-        f.render_with_self()?;
+        f.render_with_self_state(ResolveState::with_opts(r.config.render_opts()))
+            .map_err(|e| {
+                anyhow!(
+                    "While rendering parameters of {}: {e}",
+                    self.render_entity()
+                )
+            })?;
         match f {
             Value::Mapping(m) => {
                 self.parameters = m;
@@ -284,10 +776,76 @@ impl Node {
         }
     }
 
+    /// Interpolates Reclass references in the Node's application names. References are looked up
+    /// in the Node's parameters. Therefore, this method must be called after
+    /// `render_parameters()`.
+    fn render_applications(&mut self, r: &Reclass) -> Result<()> {
+        let apps: Vec<String> = std::mem::take(&mut self.applications).into();
+        let mut rendered = Vec::with_capacity(apps.len());
+        for app in apps {
+            // Similar to class names, we convert the rendered reference with `raw_string()` to
+            // ensure no spurious quotes are injected.
+            let app = match Token::parse(&app)? {
+                Some(token) => token
+                    .render(
+                        &self.parameters,
+                        &mut ResolveState::with_opts(r.config.render_opts()),
+                    )
+                    .and_then(|v| v.raw_string())
+                    .map_err(|e| {
+                        anyhow!(
+                            "While rendering application '{app}' of {}: {e}",
+                            self.render_entity()
+                        )
+                    })?,
+                None => app,
+            };
+            rendered.push(app);
+        }
+        self.applications =
+            RemovableList::from_prefixed(rendered, &r.config.application_removal_prefix);
+        Ok(())
+    }
+
+    /// Renders the Node's exports by interpolating Reclass references and flattening ValueLists.
+    /// References in exports are looked up in the Node's parameters. Therefore, this method must
+    /// be called after `render_parameters()`.
+    fn render_exports(&mut self, r: &Reclass) -> Result<()> {
+        let e = std::mem::take(&mut self.exports);
+        let f = Value::Mapping(e)
+            .rendered_with_state(
+                &self.parameters,
+                ResolveState::with_opts(r.config.render_opts()),
+            )
+            .map_err(|e| anyhow!("While rendering exports of {}: {e}", self.render_entity()))?;
+        match f {
+            Value::Mapping(m) => {
+                self.exports = m;
+                Ok(())
+            }
+            _ => Err(anyhow!(
+                "Rendered exports are not a Mapping but a {}",
+                f.variant()
+            )),
+        }
+    }
+
     /// Load included classes (recursively), and merge parameters.
     ///
     /// Note that this method doesn't flatten overwritten parameters.
     pub fn render(&mut self, r: &Reclass) -> Result<()> {
+        self.render_with_sources(r, None)
+    }
+
+    /// Load included classes (recursively), and merge parameters.
+    ///
+    /// If `sources` is `Some`, the raw contents of all classes which are loaded are inserted into
+    /// the provided map, keyed by the class's URI.
+    pub(crate) fn render_with_sources(
+        &mut self,
+        r: &Reclass,
+        mut sources: Option<&mut HashMap<String, String>>,
+    ) -> Result<()> {
         let mut base = Node {
             // NOTE(sg): We initialize a base node with our classes to start the class rendering
             // process.  This roughly corresponds to Python reclass's
@@ -303,10 +861,192 @@ impl Node {
 
         let mut seen = vec![];
         let mut root = Node::default();
-        base.render_impl(r, &mut seen, &mut root)?;
-        self.render_impl(r, &mut seen, &mut base)?;
-        self.render_parameters()
+
+        let entity = format!("node {}", self.meta.name);
+        // Warnings for the node are added to the root node, since the node's own
+        // warnings are replaced by the merged warnings when it's merged into the base node.
+        self.apply_reclass_meta_policy(r, &entity, &mut root.warnings)?;
+        self.source.clone_from(&self.meta.name);
+
+        base.render_impl(r, &mut seen, &mut vec![], &mut root, sources.as_deref_mut())?;
+        self.render_impl(r, &mut seen, &mut vec![], &mut base, sources)?;
+        // `seen` holds the loaded classes in the order in which they were merged, since classes
+        // are only added to it after their own includes have been processed.
+        self.class_load_order = seen;
+        self.render_parameters(r)?;
+        self.render_applications(r)?;
+        self.render_exports(r)
+    }
+}
+
+/// Records `source` as the source of all leaf parameter paths in `params` in `sources`.
+///
+/// Values which aren't Mappings are treated as leaves. Recorded paths below keys which are marked
+/// as overriding are removed first, since the override replaces the whole value. Similarly, a leaf
+/// replaces the recorded paths below it, and a Mapping replaces a leaf which was recorded for its
+/// own path.
+fn record_parameter_sources(
+    params: &Mapping,
+    source: &str,
+    path: &mut Vec<String>,
+    sources: &mut BTreeMap<String, String>,
+) {
+    for (k, v) in params {
+        path.push(
+            k.as_str()
+                .map_or_else(|| k.to_string(), ToString::to_string),
+        );
+        let p = path.join(":");
+        if let Value::Mapping(m) = v {
+            sources.remove(&p);
+            if params.is_override(k) {
+                remove_child_sources(sources, &p);
+            }
+            record_parameter_sources(m, source, path, sources);
+        } else {
+            remove_child_sources(sources, &p);
+            sources.insert(p, source.to_string());
+        }
+        path.pop();
+    }
+}
+
+/// Removes the recorded sources of all paths below `path` from `sources`.
+fn remove_child_sources(sources: &mut BTreeMap<String, String>, path: &str) {
+    let prefix = format!("{path}:");
+    let children = sources
+        .range(prefix.clone()..)
+        .take_while(|(k, _)| k.starts_with(&prefix))
+        .map(|(k, _)| k.clone())
+        .collect::<Vec<_>>();
+    for k in children {
+        sources.remove(&k);
+    }
+}
+
+/// Recursively collects the reference paths of all references in `v` into `refs`.
+fn collect_references(v: &Value, refs: &mut UniqueList) -> Result<()> {
+    match v {
+        Value::String(s) => {
+            if let Some(token) = Token::parse(s)? {
+                for path in token.references() {
+                    refs.append_if_new(path);
+                }
+            }
+        }
+        Value::Mapping(m) => {
+            for (_, v) in m {
+                collect_references(v, refs)?;
+            }
+        }
+        Value::Sequence(s) | Value::ValueList(s) => {
+            for v in s {
+                collect_references(v, refs)?;
+            }
+        }
+        _ => {}
     }
+    Ok(())
+}
+
+/// Reads the entity file at `invpath`. If `sources` is `Some`, contents which are already present
+/// for `uri` are returned instead of reading the file, and contents which are read from the file
+/// are inserted into the map.
+fn read_source(
+    invpath: &Path,
+    uri: &str,
+    sources: Option<&mut HashMap<String, String>>,
+) -> Result<String> {
+    if let Some(contents) = sources.as_ref().and_then(|s| s.get(uri)) {
+        return Ok(contents.clone());
+    }
+    let contents = read_entity_file(&invpath.canonicalize()?)?;
+    if let Some(sources) = sources {
+        sources.insert(uri.to_string(), contents.clone());
+    }
+    Ok(contents)
+}
+
+/// Reads the entity file at `path` into a string. If feature `gzip` is enabled, files with
+/// extension `.gz` are decompressed.
+fn read_entity_file(path: &Path) -> Result<String> {
+    #[cfg(feature = "gzip")]
+    if path.extension().is_some_and(|ext| ext == "gz") {
+        use std::io::Read;
+        let mut contents = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(path)?).read_to_string(&mut contents)?;
+        return Ok(contents);
+    }
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Extracts the comments which directly precede top-level keys of the `parameters` mapping in the
+/// provided YAML document. Returns the comment text keyed by parameter name. Consecutive comment
+/// lines are joined with newlines, and a blank line discards preceding comment lines. Key prefixes
+/// (e.g. `~` for overrides) aren't part of the parameter name.
+///
+/// serde_yaml discards comments, so we scan the raw document lines. Only block-style `parameters`
+/// mappings are supported, and comments which aren't indented at the same level as the parameter
+/// keys are ignored.
+fn extract_parameter_docs(contents: &str) -> HashMap<String, String> {
+    let mut docs = HashMap::new();
+    let mut in_params = false;
+    // Indentation of the document's top-level keys and of the keys of `parameters`
+    let mut top_indent = None;
+    let mut key_indent = None;
+    let mut comment: Vec<&str> = vec![];
+    for line in contents.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+        if trimmed.is_empty() {
+            comment.clear();
+            continue;
+        }
+        if trimmed.starts_with('#') && top_indent.is_none() {
+            continue;
+        }
+        if indent <= *top_indent.get_or_insert(indent) && !trimmed.starts_with('#') {
+            // Any top-level key ends the `parameters` mapping
+            in_params = trimmed.split('#').next().map(str::trim_end) == Some("parameters:");
+            key_indent = None;
+            comment.clear();
+            continue;
+        }
+        if !in_params {
+            continue;
+        }
+        if let Some(text) = trimmed.strip_prefix('#') {
+            if key_indent.is_none_or(|ki| ki == indent) {
+                comment.push(text.strip_prefix(' ').unwrap_or(text).trim_end());
+            }
+            continue;
+        }
+        let ki = *key_indent.get_or_insert(indent);
+        if indent == ki && !comment.is_empty() {
+            if let Some(key) = parameter_key(trimmed) {
+                let key = key.strip_prefix(['=', '~', '+']).unwrap_or(key);
+                docs.insert(key.to_string(), comment.join("\n"));
+            }
+        }
+        comment.clear();
+    }
+    docs
+}
+
+/// Returns the mapping key of the provided YAML line, if the line starts with a simple or quoted
+/// mapping key. YAML merge keys are ignored.
+fn parameter_key(line: &str) -> Option<&str> {
+    if let Some(q) = line.chars().next().filter(|c| *c == '"' || *c == '\'') {
+        let rest = &line[1..];
+        let end = rest.find(q)?;
+        return rest[end + 1..].starts_with(':').then(|| &rest[..end]);
+    }
+    let end = line
+        .match_indices(':')
+        .map(|(i, _)| i)
+        .find(|i| line[i + 1..].is_empty() || line[i + 1..].starts_with([' ', '\t']))?;
+    let key = line[..end].trim_end();
+    (!key.is_empty() && key != "<<").then_some(key)
 }
 
 #[cfg(test)]
@@ -331,16 +1071,26 @@ mod node_tests {
             n.applications,
             RemovableList::from(vec!["app1".to_owned(), "app2".to_owned()])
         );
-        let expected = r#"
+        let expected = r"
         foo:
           foo: foo
         bar:
           foo: foo
-        "#;
+        ";
         let expected: serde_yaml::Mapping = serde_yaml::from_str(expected).unwrap();
         assert_eq!(n.parameters, expected.into());
     }
 
+    #[test]
+    fn test_parse_relative_uri() {
+        let mut r = make_reclass();
+        r.config.relative_uri = true;
+        let n = Node::parse(&r, "n1").unwrap();
+        assert_eq!(n.meta.uri, "yaml_fs://nodes/n1.yml");
+        let c = n.read_class(&r, "nested.cls1", None).unwrap().unwrap();
+        assert_eq!(c.meta.uri, "yaml_fs://classes/nested/cls1.yml");
+    }
+
     #[test]
     #[should_panic(expected = "Unknown node n0")]
     fn test_parse_error() {
@@ -350,7 +1100,7 @@ mod node_tests {
 
     #[test]
     fn test_from_str() {
-        let node = r#"
+        let node = r"
         classes:
           - foo
           - bar
@@ -360,7 +1110,7 @@ mod node_tests {
         parameters:
           foo:
             bar: bar
-        "#;
+        ";
 
         let n = Node::from_str(NodeInfoMeta::default(), None, node).unwrap();
         assert_eq!(
@@ -381,44 +1131,62 @@ mod node_tests {
         assert_eq!(n.params, params);
     }
 
+    #[test]
+    fn test_references() {
+        let node = r"
+        parameters:
+          foo:
+            bar: bar
+          a: ${foo:bar}
+          b:
+            - a-${baz}-b
+            - ${foo:bar}
+        ";
+        let n = Node::from_str(NodeInfoMeta::default(), None, node).unwrap();
+        assert_eq!(
+            n.references().unwrap(),
+            vec!["foo:bar".to_owned(), "baz".to_owned()]
+        );
+    }
+
     #[test]
     fn test_from_str_merge_keys() {
-        let node = r#"
+        let node = r"
         parameters:
           foo: &foo
             bar: bar
           fooer:
             <<: *foo
-        "#;
+        ";
         let n = Node::from_str(NodeInfoMeta::default(), None, node).unwrap();
-        let expected = r#"
+        let expected = r"
         foo:
           bar: bar
         fooer:
           bar: bar
-        "#;
+        ";
         let expected: serde_yaml::Mapping = serde_yaml::from_str(expected).unwrap();
         assert_eq!(n.params, expected);
     }
 
     #[test]
     fn test_from_str_merge_keys_nested() {
-        let node = r#"
+        let node = r"
         parameters:
           foo: &foo
             bar: bar
           fooer:
             bar:
               <<: *foo
-        "#;
+        ";
         let n = Node::from_str(NodeInfoMeta::default(), None, node).unwrap();
-        let expected = r#"
+        let expected = r"
         foo:
           bar: bar
         fooer:
           bar:
             bar: bar
-        "#;
+        ";
         let expected: serde_yaml::Mapping = serde_yaml::from_str(expected).unwrap();
         assert_eq!(n.params, expected);
     }
@@ -427,7 +1195,7 @@ mod node_tests {
     fn test_from_str_merge_keys_recursive() {
         // NOTE(sg): This test fails when using serde_yaml's `apply_merge` instead of the
         // yaml-merge-keys crate. Example input taken from the serde_yaml issue linked at the top.
-        let node = r#"
+        let node = r"
         parameters:
           a: &a
             a: a
@@ -436,9 +1204,9 @@ mod node_tests {
           c:
             - <<: *a
             - <<: *b
-        "#;
+        ";
         let n = Node::from_str(NodeInfoMeta::default(), None, node).unwrap();
-        let expected = r#"
+        let expected = r"
         a:
           a: a
         b:
@@ -446,7 +1214,7 @@ mod node_tests {
         c:
           - a: a
           - a: a
-        "#;
+        ";
         let expected: serde_yaml::Mapping = serde_yaml::from_str(expected).unwrap();
         assert_eq!(n.params, expected);
     }
@@ -467,6 +1235,49 @@ mod node_tests {
         assert_eq!(p, "foo.bar");
     }
 
+    #[test]
+    fn test_extract_parameter_docs() {
+        let contents = r#"
+# Comments outside of parameters are ignored
+classes:
+  # A class
+  - cls1
+parameters:
+  # The cluster name
+  cluster_name: c-test
+  # Not documenting anything
+
+  # Quoted keys
+  # are supported
+  "some:key":
+    # Nested keys are ignored
+    nested: |
+      # not a comment
+  # Flow style values
+  list: [a, b] # trailing comments are ignored
+  # Prefixed keys
+  ~overridden: true
+  undocumented: true
+  <<: *anchor
+exports:
+  # An export
+  foo: bar
+"#;
+        let docs = extract_parameter_docs(contents);
+        assert_eq!(
+            docs,
+            HashMap::from([
+                ("cluster_name".to_string(), "The cluster name".to_string()),
+                (
+                    "some:key".to_string(),
+                    "Quoted keys\nare supported".to_string()
+                ),
+                ("list".to_string(), "Flow style values".to_string()),
+                ("overridden".to_string(), "Prefixed keys".to_string()),
+            ])
+        );
+    }
+
     #[test]
     fn abs_class_name_same_dir() {
         let mut c = Node::default();
@@ -521,6 +1332,24 @@ mod node_tests {
         assert_eq!(p, "foo");
     }
 
+    #[test]
+    fn abs_class_name_mid_name_double_dot() {
+        let mut c = Node::default();
+        let cpath = PathBuf::from("foo/bar/baz");
+        c.own_loc = Some(cpath);
+        assert_eq!(c.abs_class_name("..foo.bar").unwrap(), "foo.bar.foo.bar");
+        for cls in ["foo..bar", "..foo..bar", "foo...bar"] {
+            let err = c.abs_class_name(cls).unwrap_err();
+            assert_eq!(
+                err.to_string(),
+                format!(
+                    "Invalid class name '{cls}': consecutive dots are only allowed at the start \
+                    of a relative class name"
+                )
+            );
+        }
+    }
+
     #[test]
     fn abs_class_name_past_root_dir_subclass() {
         let mut c = Node::default();
@@ -530,17 +1359,54 @@ mod node_tests {
         assert_eq!(p, "foo.bar");
     }
 
+    #[test]
+    fn test_read_class_strict_relative_classes() {
+        let mut r = make_reclass();
+        let n = Node::from_str(NodeInfoMeta::default(), Some(PathBuf::from("nested")), "").unwrap();
+        let err = n.read_class(&r, "a_sub", None).unwrap_err();
+        assert_eq!(err.to_string(), "Class a_sub not found");
+
+        r.config.strict_relative_classes = true;
+        let err = n.read_class(&r, "a_sub", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Class a_sub not found, did you mean '.a_sub'?"
+        );
+        // absolute references which exist are loaded normally
+        assert!(n.read_class(&r, "cls1", None).unwrap().is_some());
+        // no hint for missing classes which don't exist as relative class either
+        let err = n.read_class(&r, "missing", None).unwrap_err();
+        assert_eq!(err.to_string(), "Class missing not found");
+
+        // no hints for nodes, since absolute and relative references are identical
+        let n = Node::from_str(NodeInfoMeta::default(), None, "").unwrap();
+        let err = n.read_class(&r, "a_sub", None).unwrap_err();
+        assert_eq!(err.to_string(), "Class a_sub not found");
+    }
+
+    #[test]
+    fn test_read_class_duplicate_key() {
+        let r = make_reclass();
+        let n = Node::from_str(NodeInfoMeta::default(), None, "classes: [duplicate-key]").unwrap();
+        let err = n.read_class(&r, "duplicate-key", None).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Deserializing duplicate-key: parameters: duplicate entry with key \"foo\" at line 2 \
+            column 3"
+        );
+    }
+
     #[test]
     fn test_read_class() {
         let r = make_reclass();
         let n = Node::parse(&r, "n1").unwrap();
-        let c = n.read_class(&r, "cls1").unwrap().unwrap();
-        let expected = r#"
+        let c = n.read_class(&r, "cls1", None).unwrap().unwrap();
+        let expected = r"
         foo:
           foo: cls1
           bar: cls1
           baz: cls1
-        "#;
+        ";
         let expected = Mapping::from_str(expected).unwrap();
         assert_eq!(c.parameters, expected);
     }
@@ -549,12 +1415,12 @@ mod node_tests {
     fn test_read_class_relative() {
         let r = make_reclass();
         let n = Node::parse(&r, "n1").unwrap();
-        let c1 = n.read_class(&r, "nested.cls1").unwrap().unwrap();
-        let c2 = c1.read_class(&r, ".cls2").unwrap().unwrap();
-        let expected = r#"
+        let c1 = n.read_class(&r, "nested.cls1", None).unwrap().unwrap();
+        let c2 = c1.read_class(&r, ".cls2", None).unwrap().unwrap();
+        let expected = r"
         foo:
           foo: nested.cls2
-        "#;
+        ";
         let expected = Mapping::from_str(expected).unwrap();
         assert_eq!(c2.parameters, expected);
     }
@@ -1,4 +1,5 @@
 use super::*;
+use std::fmt::Write;
 use std::str::FromStr;
 
 fn expected_params(nodename: &str, yaml: &str) -> Mapping {
@@ -7,12 +8,11 @@ fn expected_params(nodename: &str, yaml: &str) -> Mapping {
     _reclass_:
       environment: base
       name:
-        short: {}
-        parts: ["{}"]
-        full: {}
-        path: {}
-        "#,
-        nodename, nodename, nodename, nodename
+        short: {nodename}
+        parts: ["{nodename}"]
+        full: {nodename}
+        path: {nodename}
+        "#
     ))
     .unwrap();
 
@@ -40,14 +40,14 @@ fn test_render_n1() {
 
     let expected = expected_params(
         "n1",
-        r#"
+        r"
     foo:
       foo: foo
       bar: cls2
       baz: cls1
     bar:
       foo: foo
-    "#,
+    ",
     );
 
     assert_eq!(n.parameters, expected);
@@ -69,12 +69,12 @@ fn test_render_n2() {
 
     let expected = expected_params(
         "n2",
-        r#"
+        r"
     foo:
       foo: nested.cls1
       bar: n2
     bar: bar
-    "#,
+    ",
     );
 
     assert_eq!(n.parameters, expected);
@@ -119,13 +119,13 @@ fn test_render_n4() {
 
     let expected = expected_params(
         "n4",
-        r#"
+        r"
     foo:
       foo: cls1
       bar: cls1
       baz: cls1
     qux: cls1
-    "#,
+    ",
     );
 
     assert_eq!(n.parameters, expected);
@@ -140,7 +140,7 @@ fn test_render_n5() {
     assert_eq!(n.classes, vec!["cls9".to_string(), "cls10".to_string()]);
     let expected = expected_params(
         "n5",
-        r#"
+        r"
     # from cls9
     foo: bar
     =constant: foo
@@ -151,7 +151,7 @@ fn test_render_n5() {
     # from cls10
     bar: bar
     target: n1
-    "#,
+    ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -198,7 +198,7 @@ fn test_render_n7() {
 
     let expected = expected_params(
         "n7",
-        r#"
+        r"
         # from cls9
         foo: foo
         =constant: foo
@@ -206,7 +206,7 @@ fn test_render_n7() {
         foolist:
           - bar
         target: n3
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -223,13 +223,13 @@ fn test_render_n8() {
     );
     let expected = expected_params(
         "n8",
-        r#"
+        r"
         # from nested.a
         foo: foo
         # from nested.a_sub via nested.a
         baz: baz
         target: n4
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -252,7 +252,7 @@ fn test_render_n9() {
     );
     let expected = expected_params(
         "n9",
-        r#"
+        r"
         # from cls9 via cls13
         foo: foo
         =constant: foo
@@ -265,7 +265,7 @@ fn test_render_n9() {
         # from nested.a_sub
         baz: baz
         target: n5
-        "#,
+        ",
     );
 
     dbg!(&n.parameters);
@@ -283,7 +283,7 @@ fn test_render_n10() {
 
     let expected = expected_params(
         "n10",
-        r#"
+        r"
     # from cls9 via nested.b
     foo: foo
     =constant: foo
@@ -292,7 +292,7 @@ fn test_render_n10() {
       - b
       - c
     target: n6
-    "#,
+    ",
     );
 
     assert_eq!(n.parameters, expected);
@@ -326,7 +326,7 @@ fn test_render_n12() {
     // from cls9
     let expected = expected_params(
         "n12",
-        r#"
+        r"
         foo: foo
         =constant: foo
         foolist:
@@ -334,16 +334,19 @@ fn test_render_n12() {
           - b
           - c
         target: n8
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 
     // # Applications
-    let apps: Vec<String> = n.applications.into();
-    assert_eq!(apps, vec!["c".to_string(), "a".to_string()]);
+    let apps: Vec<String> = n.applications;
+    assert_eq!(
+        apps,
+        vec!["c".to_string(), "a".to_string(), "b".to_string()]
+    );
 
     // # Classes
-    let classes: Vec<String> = n.classes.into();
+    let classes: Vec<String> = n.classes;
     assert_eq!(classes, vec!["cls9".to_string(), "app1".to_string()]);
 }
 
@@ -356,7 +359,7 @@ fn test_render_n13() {
     // from cls9
     let expected = expected_params(
         "n13",
-        r#"
+        r"
         foo: foo
         =constant: foo
         foolist:
@@ -365,19 +368,19 @@ fn test_render_n13() {
           - c
         bar: bar
         target: n9
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 
     // # Applications
-    let apps: Vec<String> = n.applications.into();
+    let apps: Vec<String> = n.applications;
     assert_eq!(
         apps,
         vec!["d".to_string(), "a".to_string(), "b".to_string()]
     );
 
     // # Classes
-    let classes: Vec<String> = n.classes.into();
+    let classes: Vec<String> = n.classes;
     assert_eq!(
         classes,
         vec!["cls10".to_string(), "cls9".to_string(), "app2".to_string()]
@@ -392,7 +395,7 @@ fn test_render_n14() {
     // # Parameters
     let expected = expected_params(
         "n14",
-        r#"
+        r"
         foo: foo
         =constant: foo
         foolist: [a, b, c]
@@ -404,7 +407,7 @@ fn test_render_n14() {
           bar: bar
           baz: baz
         list: [a, b, c]
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -417,7 +420,7 @@ fn test_render_n15() {
     // # Parameters
     let expected = expected_params(
         "n15",
-        r#"
+        r"
         cls9: cls9
         foo: foo
         =constant: foo
@@ -426,7 +429,7 @@ fn test_render_n15() {
           - b
           - c
         target: n11
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -439,12 +442,12 @@ fn test_render_n16() {
     // # Parameters
     let expected = expected_params(
         "n16",
-        r#"
+        r"
         tenant: foo
         cluster: bar
         foobar: ishere
         target: n12
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -468,7 +471,7 @@ fn test_render_n18() {
     // # Parameters
     let expected = expected_params(
         "n18",
-        r#"
+        r"
     foo: foo
     =constant: foo
     foolist:
@@ -476,7 +479,7 @@ fn test_render_n18() {
       - b
       - c
     target: n14
-    "#,
+    ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -488,14 +491,14 @@ fn test_render_n19() {
 
     let expected = expected_params(
         "n19",
-        r#"
+        r"
     dist: foo
     instanceref: cluster
     foo: fooing
     some: syn-cluster
     target: n15
     _instance: cluster
-    "#,
+    ",
     );
 
     assert_eq!(n.parameters, expected);
@@ -508,7 +511,7 @@ fn test_render_n20() {
 
     let expected = expected_params(
         "n20",
-        r#"
+        r"
         bardict: notadict
         bar: [a]
         foo:
@@ -519,7 +522,7 @@ fn test_render_n20() {
           bar: bar
           baz: baz
           qux: qux
-        "#,
+        ",
     );
     // # Parameters
     assert_eq!(n.parameters, expected);
@@ -532,7 +535,7 @@ fn test_render_n21() {
 
     let expected = expected_params(
         "n21",
-        r#"
+        r"
         foo:
           bar: bar
           baz: baz
@@ -543,7 +546,7 @@ fn test_render_n21() {
         bar_merge:
           bar: bar
           baz: baz
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -580,7 +583,7 @@ fn test_render_n23() {
 
     let expected = expected_params(
         "n23",
-        r#"
+        r"
         ~baz:
           baz: cls7
           foo: cls7
@@ -589,7 +592,7 @@ fn test_render_n23() {
           bar: n19
           baz: baz
           qux: qux
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -601,12 +604,12 @@ fn test_render_n24() {
 
     let expected = expected_params(
         "n24",
-        r#"
+        r"
         fluentbit:
           config:
             inputs:
               systemd: {}
-        "#,
+        ",
     );
     assert_eq!(n.parameters, expected);
 }
@@ -618,13 +621,725 @@ fn test_render_n25() {
 
     let expected = expected_params(
         "n25",
-        r#"
+        r"
         foo:
           foo: foo/init
           bar: bar
-        "#,
+        ",
     );
     println!("{:#?}", n.parameters);
     assert_eq!(n.parameters, expected);
     assert_eq!(n.classes, vec!["bar", "foo"]);
 }
+
+#[test]
+fn test_render_node_from_str() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str(
+            "generated",
+            r"
+        classes:
+          - cls1
+        parameters:
+          foo:
+            bar: generated
+        ",
+        )
+        .unwrap();
+    assert_eq!(n.classes, vec!["cls1".to_owned()]);
+    assert_eq!(n.reclass.uri, "");
+
+    let expected = expected_params(
+        "generated",
+        r"
+    foo:
+      foo: cls1
+      bar: generated
+      baz: cls1
+    ",
+    );
+
+    assert_eq!(n.parameters, expected);
+}
+
+#[test]
+fn test_render_node_environment() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str(
+            "n-prod",
+            r"
+        environment: prod
+        classes:
+          - cls1
+        ",
+        )
+        .unwrap();
+    assert_eq!(n.reclass.environment, "prod");
+
+    let mut expected = expected_params(
+        "n-prod",
+        r"
+    foo:
+      foo: cls1
+      bar: cls1
+      baz: cls1
+    ",
+    );
+    // `expected_params()` always generates `_reclass_.environment: base`
+    let env = expected
+        .get_mut(&"_reclass_".into())
+        .unwrap()
+        .unwrap()
+        .as_mapping_mut()
+        .unwrap()
+        .get_mut(&"environment".into())
+        .unwrap()
+        .unwrap();
+    *env = Value::Literal("prod".into());
+
+    assert_eq!(n.parameters, expected);
+}
+
+#[test]
+fn test_render_node_default_environment() {
+    let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+    c.default_environment = "production".into();
+    let r = Reclass::new_from_config(c).unwrap();
+
+    let n = r.render_node("n1").unwrap();
+    assert_eq!(n.reclass.environment, "production");
+    let env = n.parameters.get_path("_reclass_:environment");
+    assert_eq!(env, Some(&Value::Literal("production".into())));
+
+    // An explicit environment takes precedence over the configured default
+    let n = r
+        .render_node_from_str("n-prod", "environment: prod")
+        .unwrap();
+    let env = n.parameters.get_path("_reclass_:environment");
+    assert_eq!(env, Some(&Value::Literal("prod".into())));
+}
+
+fn make_reclass_meta_policy(policy: crate::config::ReclassMetaPolicy) -> Reclass {
+    let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+    c.reclass_meta_policy = policy;
+    Reclass::new_from_config(c).unwrap()
+}
+
+const RECLASS_META_NODE: &str = r"
+classes:
+  - cls1
+parameters:
+  _reclass_:
+    environment: custom
+    user: foo
+";
+
+#[test]
+fn test_render_reclass_meta_policy_merge() {
+    let r = make_reclass_meta_policy(crate::config::ReclassMetaPolicy::Merge);
+    let n = r.render_node_from_str("n-meta", RECLASS_META_NODE).unwrap();
+
+    let expected = Mapping::from_str(
+        r#"
+    _reclass_:
+      environment: custom
+      name:
+        short: n-meta
+        parts: ["n-meta"]
+        full: n-meta
+        path: n-meta
+      user: foo
+    foo:
+      foo: cls1
+      bar: cls1
+      baz: cls1
+    "#,
+    )
+    .unwrap();
+    let expected = Value::Mapping(expected).rendered(&Mapping::new()).unwrap();
+
+    assert_eq!(n.parameters, *expected.as_mapping().unwrap());
+}
+
+#[test]
+fn test_render_reclass_meta_policy_override() {
+    let r = make_reclass_meta_policy(crate::config::ReclassMetaPolicy::Override);
+    let n = r.render_node_from_str("n-meta", RECLASS_META_NODE).unwrap();
+
+    let expected = Mapping::from_str(
+        r"
+    _reclass_:
+      environment: custom
+      user: foo
+    foo:
+      foo: cls1
+      bar: cls1
+      baz: cls1
+    ",
+    )
+    .unwrap();
+    let expected = Value::Mapping(expected).rendered(&Mapping::new()).unwrap();
+
+    assert_eq!(n.parameters, *expected.as_mapping().unwrap());
+}
+
+#[test]
+fn test_render_reclass_meta_policy_warn() {
+    let r = make_reclass_meta_policy(crate::config::ReclassMetaPolicy::Warn);
+    let n = r.render_node_from_str("n-meta", RECLASS_META_NODE).unwrap();
+
+    // User-defined values are merged with the automatic parameter
+    let meta = n.parameters.get(&"_reclass_".into()).unwrap();
+    assert_eq!(
+        meta.get(&"user".into()),
+        Some(&Value::Literal("foo".into()))
+    );
+    assert_eq!(
+        meta.get(&"environment".into()),
+        Some(&Value::Literal("custom".into()))
+    );
+    assert!(meta.get(&"name".into()).is_some());
+    assert_eq!(
+        n.warnings,
+        vec!["Parameter _reclass_ is reserved, but is defined in node n-meta"]
+    );
+
+    // Nodes which don't define `_reclass_` don't generate a warning
+    let n = r.render_node("n1").unwrap();
+    assert!(n.warnings.is_empty());
+}
+
+#[test]
+fn test_render_reclass_meta_policy_reserve() {
+    let r = make_reclass_meta_policy(crate::config::ReclassMetaPolicy::Reserve);
+    let err = r
+        .render_node_from_str("n-meta", RECLASS_META_NODE)
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Parameter _reclass_ is reserved, but is defined in node n-meta"
+    );
+
+    // Nodes which don't define `_reclass_` are rendered normally
+    let n = r.render_node("n1");
+    assert!(n.is_ok());
+}
+
+#[test]
+fn test_render_exports() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str(
+            "n-exports",
+            r"
+        classes:
+          - cls1
+        parameters:
+          hostname: ${_reclass_:name:short}
+          fqdn: ${hostname}.example.com
+        exports:
+          fqdn: ${fqdn}
+          foo: ${foo:baz}
+        ",
+        )
+        .unwrap();
+
+    let expected = Mapping::from_str(
+        r"
+    fqdn: n-exports.example.com
+    foo: cls1
+    ",
+    )
+    .unwrap();
+    let expected = Value::Mapping(expected).rendered(&Mapping::new()).unwrap();
+
+    assert_eq!(n.exports, *expected.as_mapping().unwrap());
+}
+
+#[test]
+fn test_render_max_value_list_length() {
+    let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+    // n1 includes `cls1` and `cls2` which both define parameter `foo`, and defines `foo` itself.
+    c.max_value_list_length = Some(3);
+    let r = Reclass::new_from_config(c.clone()).unwrap();
+    assert!(r.render_node("n1").is_ok());
+
+    c.max_value_list_length = Some(2);
+    let r = Reclass::new_from_config(c).unwrap();
+    let err = r.render_node("n1").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        r#"While merging parameters: ValueList for key "foo" exceeds the configured maximum length of 2 after merging"#
+    );
+}
+
+const UNIQUE_SEQUENCE_NODE: &str = r"
+classes:
+  - cls9
+parameters:
+  foolist:
+    - c
+    - d
+  foo: bar
+";
+
+#[test]
+fn test_render_sequence_merge_default() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str("generated", UNIQUE_SEQUENCE_NODE)
+        .unwrap();
+    let expected = expected_params(
+        "generated",
+        r"
+    =constant: foo
+    foo: bar
+    foolist: [a, b, c, c, d]
+    ",
+    );
+    assert_eq!(n.parameters, expected);
+}
+
+#[test]
+fn test_render_sequence_merge_unique() {
+    let mut r = make_reclass();
+    r.config.unique_sequence_merge = true;
+    let n = r
+        .render_node_from_str("generated", UNIQUE_SEQUENCE_NODE)
+        .unwrap();
+    // Only sequences are deduplicated, scalar values are still overwritten as usual
+    let expected = expected_params(
+        "generated",
+        r"
+    =constant: foo
+    foo: bar
+    foolist: [a, b, c, d]
+    ",
+    );
+    assert_eq!(n.parameters, expected);
+}
+
+#[test]
+fn test_render_sequence_index_keys() {
+    let mut r = make_reclass();
+    r.config.sequence_index_keys = true;
+    let n = r
+        .render_node_from_str(
+            "generated",
+            r"
+            classes:
+              - cls9
+            parameters:
+              foolist:1: x
+            ",
+        )
+        .unwrap();
+    let expected = expected_params(
+        "generated",
+        r"
+    =constant: foo
+    foo: foo
+    foolist: [a, x, c]
+    ",
+    );
+    assert_eq!(n.parameters, expected);
+}
+
+#[test]
+fn test_render_sequence_index_keys_out_of_range() {
+    let mut r = make_reclass();
+    r.config.sequence_index_keys = true;
+    let err = r
+        .render_node_from_str(
+            "generated",
+            r"
+            classes:
+              - cls9
+            parameters:
+              foolist:3: x
+            ",
+        )
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "While merging parameters: Can't override sequence element 'foolist:3': index 3 out of \
+        bounds for sequence of length 3"
+    );
+}
+
+#[test]
+fn test_render_warn_dangling_overrides() {
+    let mut c = crate::Config::new(Some("./tests/inventory"), None, None, None).unwrap();
+    c.relative_uri = true;
+    c.warn_dangling_overrides = true;
+    let r = Reclass::new_from_config(c).unwrap();
+    let n = r
+        .render_node_from_str(
+            "generated",
+            r"
+            classes:
+              - cls1
+              - override
+            parameters:
+              ~foo:
+                foo: generated
+              ~missing: x
+              fluentbit:
+                config:
+                  ~inputs: {}
+                  ~outputs: {}
+            ",
+        )
+        .unwrap();
+    assert_eq!(
+        n.warnings,
+        vec![
+            "Parameter 'fluentbit:config:inputs:systemd' in yaml_fs://classes/override.yml is \
+            marked as override, but there's no inherited value to override",
+            "Parameter 'missing' in node generated is marked as override, but there's no \
+            inherited value to override",
+            "Parameter 'fluentbit:config:outputs' in node generated is marked as override, but \
+            there's no inherited value to override",
+        ]
+    );
+}
+
+#[test]
+fn test_render_warn_dangling_overrides_disabled() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str("generated", "parameters: {~missing: x}")
+        .unwrap();
+    assert!(n.warnings.is_empty());
+}
+
+#[test]
+fn test_render_ignored_classes() {
+    let r = Reclass::from_config_file(
+        "./tests/inventory-class-notfound-regexp",
+        "reclass-config.yml",
+        false,
+    )
+    .unwrap();
+    let n = r.render_node("n1").unwrap();
+    assert_eq!(
+        n.ignored_classes,
+        vec!["service.foo", "service.bar", "missing", "amissing"]
+    );
+}
+
+#[test]
+fn test_render_ignored_classes_none() {
+    let r = make_reclass();
+    let n = r.render_node("n1").unwrap();
+    assert!(n.ignored_classes.is_empty());
+}
+
+#[test]
+fn test_render_class_load_order() {
+    let r = make_reclass();
+    let n = r.render_node("n3").unwrap();
+    assert_eq!(n.class_load_order, vec!["cls4", "cls5", "cls6", "cls3"]);
+}
+
+#[test]
+fn test_render_defer_class_references() {
+    let node = r"
+    classes:
+      - ${_instance}
+      - instance
+    ";
+    let mut r = make_reclass();
+    // `_instance` isn't known yet when `${_instance}` is loaded in file order
+    let err = r.render_node_from_str("generated", node).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lookup error for reference '${_instance}' in parameter '': key '_instance' not found"
+    );
+
+    r.config.defer_class_references = true;
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert_eq!(n.class_load_order, vec!["instance", "cls1"]);
+    assert_eq!(
+        n.parameters.get_path("foo:foo"),
+        Some(&Value::Literal("cls1".into()))
+    );
+}
+
+#[test]
+fn test_render_class_include_cycle() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str("generated", "classes: [cycle.a]")
+        .unwrap();
+    assert_eq!(n.classes, vec!["cycle.a", "cycle.b"]);
+    assert_eq!(n.class_load_order, vec!["cycle.b", "cycle.a"]);
+    assert_eq!(
+        n.parameters.get_path("cycle:a"),
+        Some(&Value::Literal("a".into()))
+    );
+    assert_eq!(
+        n.parameters.get_path("cycle:b"),
+        Some(&Value::Literal("b".into()))
+    );
+}
+
+#[test]
+fn test_render_class_include_cycle_error() {
+    let mut r = make_reclass();
+    r.config.class_include_cycle_error = true;
+    let err = r
+        .render_node_from_str("generated", "classes: [cycle.a]")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Detected class include cycle: cycle.a -> cycle.b -> cycle.a"
+    );
+}
+
+#[test]
+fn test_class_closure_include_cycle() {
+    let mut r = make_reclass();
+    let n = Node::from_str(NodeInfoMeta::default(), None, "classes: [cycle.a]").unwrap();
+    assert_eq!(n.class_closure(&r).unwrap(), vec!["cycle.a", "cycle.b"]);
+
+    r.config.class_include_cycle_error = true;
+    let err = n.class_closure(&r).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Detected class include cycle: cycle.a -> cycle.b -> cycle.a"
+    );
+}
+
+#[test]
+fn test_render_resolve_max_depth() {
+    // Generates a node whose parameter `k0` is resolved through a chain of `depth` references
+    // `k0: ${k1}`, `k1: ${k2}`, ..., `k{depth}: end`.
+    let chained_node = |depth: usize| {
+        let mut node = "parameters:\n".to_string();
+        for i in 0..depth {
+            let _ = writeln!(node, "  k{i}: ${{k{}}}", i + 1);
+        }
+        let _ = writeln!(node, "  k{depth}: end");
+        node
+    };
+
+    let mut r = make_reclass();
+    r.config.resolve_max_depth = 4;
+
+    let err = r
+        .render_node_from_str("generated", &chained_node(5))
+        .unwrap_err();
+    assert!(format!("{err:#}").contains("Token resolution exceeded recursion depth of 4"));
+
+    let n = r
+        .render_node_from_str("generated", &chained_node(3))
+        .unwrap();
+    assert_eq!(
+        n.parameters.get(&"k0".into()),
+        Some(&Value::Literal("end".into()))
+    );
+}
+
+#[test]
+fn test_render_extract_parameter_docs() {
+    let node = r"
+    classes:
+      - cls9
+    parameters:
+      # Documented in the node
+      foo: bar
+    ";
+    let mut r = make_reclass();
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert!(n.parameter_docs.is_empty());
+
+    r.config.extract_parameter_docs = true;
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert_eq!(
+        n.parameter_docs,
+        HashMap::from([
+            ("foo".to_string(), "Documented in the node".to_string()),
+            (
+                "foolist".to_string(),
+                "List of letters which can be extended or\ndeduplicated by other classes"
+                    .to_string()
+            ),
+        ])
+    );
+}
+
+#[test]
+fn test_render_application_references() {
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str(
+            "generated",
+            r"
+            applications:
+              - ${app_name}
+              - app-${app_suffix}
+              - plain
+              - ${plain_app}
+            parameters:
+              app_name: foo
+              app_suffix: ${app_name}
+              plain_app: plain
+            ",
+        )
+        .unwrap();
+    assert_eq!(n.applications, vec!["foo", "app-foo", "plain"]);
+
+    let err = r
+        .render_node_from_str("generated", "applications: [\"${missing}\"]")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "While rendering application '${missing}' of node 'generated': lookup error for \
+        reference '${missing}' in parameter '': key 'missing' not found"
+    );
+}
+
+#[test]
+fn test_render_applications_removal_prefix() {
+    let node = r"
+    applications:
+      - foo
+      - bar
+      - ~foo
+      - -bar
+    ";
+    let mut r = make_reclass();
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert_eq!(n.applications, vec!["bar", "-bar"]);
+
+    r.config.application_removal_prefix = "-".to_string();
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert_eq!(n.applications, vec!["foo", "~foo"]);
+}
+
+#[test]
+fn test_render_applications_remove_before_add() {
+    // Class app1 removes application `b` which hasn't been added yet, so the removal has no
+    // effect on the node's application `b`.
+    let r = make_reclass();
+    let n = r
+        .render_node_from_str(
+            "generated",
+            r"
+            classes:
+              - app1
+            applications:
+              - b
+            ",
+        )
+        .unwrap();
+    assert_eq!(n.applications, vec!["c", "b"]);
+}
+
+#[test]
+fn test_render_type_changing_merge() {
+    let node = r"
+    classes:
+      - cls1
+    parameters:
+      foo: replaced
+    ";
+    let mut r = make_reclass();
+    let err = r.render_node_from_str("generated", node).unwrap_err();
+    assert!(err.to_string().contains("Can't merge"), "{err}");
+
+    r.config.type_changing_merge = true;
+    let n = r.render_node_from_str("generated", node).unwrap();
+    assert_eq!(
+        n.parameters.get(&"foo".into()),
+        Some(&Value::Literal("replaced".into()))
+    );
+}
+
+#[test]
+fn test_render_soft_include() {
+    let r = make_reclass();
+    assert!(!r.config.ignore_class_notfound);
+    let n = r
+        .render_node_from_str("generated", "classes: [cls1?, missing?]")
+        .unwrap();
+    assert_eq!(n.class_load_order, vec!["cls1"]);
+    // The soft include marker isn't part of the class names, and skipped soft includes aren't
+    // listed
+    assert_eq!(n.classes, vec!["cls1"]);
+    assert!(n.ignored_classes.is_empty());
+    assert_eq!(
+        n.parameters.get_path("foo:foo"),
+        Some(&Value::Literal("cls1".into()))
+    );
+
+    let node = Node::from_str(NodeInfoMeta::default(), None, "classes: [cls1?, missing?]").unwrap();
+    assert_eq!(node.class_closure(&r).unwrap(), vec!["cls1"]);
+
+    // Missing classes without the soft include marker are still an error
+    let err = r
+        .render_node_from_str("generated", "classes: [missing?, missing]")
+        .unwrap_err();
+    assert_eq!(err.to_string(), "Class missing not found");
+}
+
+#[test]
+fn test_render_parameter_sources() {
+    let mut r = make_reclass();
+    assert!(r.render_node("n1").unwrap().parameter_sources.is_empty());
+
+    r.config.track_parameter_sources = true;
+    let n = r.render_node("n1").unwrap();
+    let sources = &n.parameter_sources;
+    assert_eq!(sources["foo:foo"], "n1");
+    assert_eq!(sources["foo:bar"], "cls2");
+    assert_eq!(sources["foo:baz"], "cls1");
+    assert_eq!(sources["bar:foo"], "n1");
+    assert!(!sources.keys().any(|k| k.starts_with("_reclass_")));
+
+    // Overriding a mapping discards the sources of the replaced leaves
+    let n = r
+        .render_node_from_str(
+            "generated",
+            "classes: [cls1]\nparameters: {~foo: {qux: node}}",
+        )
+        .unwrap();
+    let mut paths = n
+        .parameter_sources
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect::<Vec<_>>();
+    paths.sort_unstable();
+    assert_eq!(paths, vec![("foo:qux", "generated")]);
+
+    // Replacing a mapping with a leaf (or vice versa) discards the sources of the replaced value
+    r.config.type_changing_merge = true;
+    for (params, expected) in [
+        ("{foo: node}", vec![("foo", "generated")]),
+        (
+            "{foo: {foo: {qux: node}}}",
+            vec![
+                ("foo:bar", "cls1"),
+                ("foo:baz", "cls1"),
+                ("foo:foo:qux", "generated"),
+            ],
+        ),
+    ] {
+        let n = r
+            .render_node_from_str(
+                "generated",
+                &format!("classes: [cls1]\nparameters: {params}"),
+            )
+            .unwrap();
+        let mut paths = n
+            .parameter_sources
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect::<Vec<_>>();
+        paths.sort_unstable();
+        assert_eq!(paths, expected);
+    }
+}
@@ -2,11 +2,13 @@ use anyhow::{anyhow, Result};
 use chrono::offset::Local;
 use chrono::DateTime;
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::path::PathBuf;
 
 use crate::config::{CompatFlag, Config};
-use crate::types::{Mapping, Value};
+use crate::types::{Mapping, Value, TEMPLATE_TAG};
 
 /// Contains metadata for a Reclass node's rendered data
 #[pyclass]
@@ -29,6 +31,8 @@ pub struct NodeInfoMeta {
     #[pyo3(get)]
     /// `chrono::DateTime<Local>` at which we started rendering the data for the node
     render_time: DateTime<Local>,
+    /// Per-node override for compat flag `ComposeNodeNameLiteralDots`
+    pub(crate) literal_dots: Option<bool>,
 }
 
 impl Default for NodeInfoMeta {
@@ -46,6 +50,7 @@ impl NodeInfoMeta {
             uri: uri.into(),
             environment: environment.into(),
             render_time: Local::now(),
+            literal_dots: None,
         }
     }
 
@@ -58,11 +63,14 @@ impl NodeInfoMeta {
             .ok_or(anyhow!("Can't extract first path segment for node"))?
             .to_str()
             .ok_or(anyhow!("Unable to convert path segment to a string"))?;
-        let parts = if config.compose_node_name
-            && config
+        // The node-level setting `compose_node_name_literal_dots` takes precedence over the
+        // globally configured compat flag.
+        let literal_dots = self.literal_dots.unwrap_or_else(|| {
+            config
                 .compatflags
                 .contains(&CompatFlag::ComposeNodeNameLiteralDots)
-        {
+        });
+        let parts = if config.compose_node_name && literal_dots {
             // when CompatFlag ComposeNodeNameLiteralDots is set, we naively split the node's name
             // by dots to generate the parts list for the node metadata.
             // This matches Python reclass's behavior, but is incorrect for nodes which contain
@@ -73,7 +81,7 @@ impl NodeInfoMeta {
             vec![self
                 .parts
                 .iter()
-                .last()
+                .next_back()
                 .ok_or(anyhow!("Unable to extract last segment from node"))?
                 .to_str()
                 .ok_or(anyhow!("Unable to convert path segment to a string"))?]
@@ -84,8 +92,10 @@ impl NodeInfoMeta {
             self.parts
                 .iter()
                 .map(|s| {
-                    s.to_str()
-                        .ok_or(anyhow!("Unable to convert path segment {s:?} to a string"))
+                    s.to_str().ok_or(anyhow!(
+                        "Unable to convert path segment {} to a string",
+                        s.to_string_lossy()
+                    ))
                 })
                 .collect::<Result<Vec<&str>, _>>()?
         };
@@ -132,10 +142,32 @@ pub struct NodeInfo {
     #[pyo3(get)]
     pub classes: Vec<String>,
     /// Exports defined for the node.
-    /// Note that the exports functionality is not yet implemented.
     pub exports: Mapping,
     /// Parameters defined for the node.
     pub parameters: Mapping,
+    /// Warnings which were generated while rendering the node.
+    #[pyo3(get)]
+    pub warnings: Vec<String>,
+    /// Missing classes which were ignored while rendering the node, in the order in which they
+    /// were encountered. See config options `ignore_class_notfound` and
+    /// `ignore_class_notfound_regexp`.
+    #[pyo3(get)]
+    pub ignored_classes: Vec<String>,
+    /// Comments preceding top-level parameter keys in the node and its classes, keyed by
+    /// parameter name. Only populated if config option `extract_parameter_docs` is enabled.
+    #[pyo3(get)]
+    pub parameter_docs: HashMap<String, String>,
+    /// Classes which were loaded for the node, in the order in which they were merged. Classes
+    /// are merged after all the classes which they include, so the list shows the effective
+    /// precedence of the classes, with later classes taking precedence.
+    #[pyo3(get)]
+    pub class_load_order: Vec<String>,
+    /// Leaf parameter paths (e.g. `foo:bar`) mapped to the entity which last set them, i.e. the
+    /// absolute class name for parameters set in classes, and the node name for parameters set
+    /// in the node itself. The automatic `_reclass_` parameters aren't included. Only populated if
+    /// config option `track_parameter_sources` is enabled.
+    #[pyo3(get)]
+    pub parameter_sources: HashMap<String, String>,
 }
 
 impl From<super::Node> for NodeInfo {
@@ -146,65 +178,429 @@ impl From<super::Node> for NodeInfo {
             applications: n.applications.into(),
             classes: n.classes.into(),
             parameters: n.parameters,
-            // NOTE(sg): Python reclass's exports functionality is not implemented yet.
-            exports: Mapping::new(),
+            exports: n.exports,
+            warnings: n.warnings,
+            ignored_classes: n.ignored_classes,
+            parameter_docs: n.parameter_docs,
+            class_load_order: n.class_load_order,
+            parameter_sources: n.parameter_sources.into_iter().collect(),
         }
     }
 }
 
-#[pymethods]
 impl NodeInfo {
-    fn __repr__(&self) -> String {
-        format!("{self:#?}")
+    /// Returns a content hash of the NodeInfo's applications, classes, parameters and exports
+    /// which is suitable as a cache key. The render time in the NodeInfo's metadata isn't
+    /// included in the hash.
+    ///
+    /// See [`Value::content_hash()`](crate::types::Value::content_hash) for details.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.applications.hash(&mut hasher);
+        self.classes.hash(&mut hasher);
+        self.parameters.content_hash().hash(&mut hasher);
+        self.exports.content_hash().hash(&mut hasher);
+        hasher.finish()
     }
 
-    /// Returns the NodeInfo `parameters` field as a PyDict
-    #[getter]
-    fn parameters<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
-        self.parameters.as_py_dict(py)
+    /// Returns the `:`-separated paths of all rendered parameters which are marked as constant,
+    /// e.g. `cluster:name` for a parameter which is defined as `=name` in mapping `cluster`.
+    #[must_use]
+    pub fn constant_parameters(&self) -> Vec<String> {
+        self.parameters.constant_paths()
     }
 
-    /// Returns the NodeInfo `exports` field as a PyDict
-    #[getter]
-    fn exports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
-        #[cfg(debug_assertions)]
-        eprintln!("reclass_rs doesn't support exports yet!");
-        self.exports.as_py_dict(py)
-    }
+    /// Returns the NodeInfo data as a `Mapping` which is structured identically to the dict
+    /// returned by `as_dict()`. The provided `timestamp` is used as the render timestamp in the
+    /// `__reclass__` metadata.
+    pub(crate) fn as_output_mapping(&self, timestamp: &str) -> Result<Mapping> {
+        let mut meta = Mapping::new();
+        meta.insert("node".into(), self.reclass.node.clone().into())?;
+        meta.insert("name".into(), self.reclass.name.clone().into())?;
+        meta.insert("uri".into(), self.reclass.uri.clone().into())?;
+        meta.insert(
+            "environment".into(),
+            self.reclass.environment.clone().into(),
+        )?;
+        meta.insert("timestamp".into(), timestamp.into())?;
 
-    /// Returns the NodeInfo data as a PyDict
-    ///
-    /// This method generates a PyDict which should be structured identically to Python Reclass's
-    /// `nodeinfo` return value.
-    pub(crate) fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
-        let dict = PyDict::new(py);
-        dict.set_item("__reclass__", self.reclass_as_dict(py)?)?;
-        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
-        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
-        dict.set_item(
-            "environment",
-            self.reclass.environment.clone().into_pyobject(py)?,
+        let mut m = Mapping::new();
+        m.insert("__reclass__".into(), meta.into())?;
+        m.insert("applications".into(), self.applications.clone().into())?;
+        m.insert("classes".into(), self.classes.clone().into())?;
+        m.insert(
+            "environment".into(),
+            self.reclass.environment.clone().into(),
         )?;
-        dict.set_item("exports", self.exports(py)?)?;
-        dict.set_item("parameters", self.parameters(py)?)?;
-        Ok(dict)
-    }
-
-    /// Returns the NodeInfo `meta` field as a PyDict
-    fn reclass_as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
-        let dict = PyDict::new(py);
-        dict.set_item("node", self.reclass.node.clone().into_pyobject(py)?)?;
-        dict.set_item("name", self.reclass.name.clone().into_pyobject(py)?)?;
-        dict.set_item("uri", self.reclass.uri.clone().into_pyobject(py)?)?;
-        dict.set_item(
-            "environment",
-            self.reclass.environment.clone().into_pyobject(py)?,
+        // Rendered exports and parameters shouldn't contain any ValueLists, but we flatten them
+        // anyway to ensure that the resulting Mapping can be serialized.
+        m.insert(
+            "exports".into(),
+            Value::Mapping(self.exports.clone()).flattened()?,
         )?;
-        // Format time as strftime %c for Python compatibility
-        dict.set_item(
-            "timestamp",
-            self.reclass.render_time.format("%c").to_string(),
+        m.insert(
+            "parameters".into(),
+            Value::Mapping(self.parameters.clone()).flattened()?,
         )?;
-        Ok(dict)
+        Ok(m)
+    }
+
+    /// Returns the node's render time formatted for the `__reclass__` metadata.
+    pub(crate) fn render_timestamp(&self) -> String {
+        // Format time as strftime %c for Python compatibility
+        self.reclass.render_time.format("%c").to_string()
+    }
+
+    /// Serializes the NodeInfo's rendered parameters as a TOML document.
+    ///
+    /// Returns an error if the parameters contain values which can't be represented in TOML,
+    /// e.g. null values.
+    #[cfg(feature = "toml")]
+    pub fn to_toml_string(&self) -> Result<String> {
+        let table = toml_table(&self.parameters, &mut vec![])?;
+        Ok(toml::to_string(&table)?)
+    }
+
+    /// Generates a draft-07 JSON Schema which describes the structure of the NodeInfo's rendered
+    /// parameters.
+    ///
+    /// Types are inferred from the rendered values. Mapping keys whose value isn't null are marked
+    /// as required. Sequences whose elements don't all have the same inferred schema are described
+    /// with `anyOf`.
+    #[must_use]
+    pub fn to_json_schema(&self) -> serde_json::Value {
+        let mut schema = json_schema_mapping(&self.parameters);
+        schema.insert(
+            "$schema".to_string(),
+            "http://json-schema.org/draft-07/schema#".into(),
+        );
+        serde_json::Value::Object(schema)
+    }
+}
+
+/// Converts the provided Mapping into a TOML table. Parameter `path` holds the keys of the
+/// Mapping's parents and is used in error messages.
+#[cfg(feature = "toml")]
+fn toml_table(m: &Mapping, path: &mut Vec<String>) -> Result<toml::Table> {
+    let mut table = toml::Table::new();
+    for (k, v) in m {
+        // TOML keys must be strings, we convert the same Value variants to strings as the
+        // conversion of `Mapping` to JSON.
+        let k = match k {
+            Value::String(s) | Value::Literal(s) => s.clone(),
+            Value::Bool(b) => format!("{b}"),
+            Value::Number(n) => format!("{n}"),
+            Value::Null => "null".to_owned(),
+            _ => return Err(anyhow!("Can't serialize {} as TOML key", k.variant())),
+        };
+        path.push(k.clone());
+        table.insert(k, toml_value(v, path)?);
+        path.pop();
+    }
+    Ok(table)
+}
+
+/// Converts the provided Value into a TOML value. Returns an error for values which can't be
+/// represented in TOML, most notably null values.
+#[cfg(feature = "toml")]
+fn toml_value(v: &Value, path: &mut Vec<String>) -> Result<toml::Value> {
+    Ok(match v {
+        Value::Null => {
+            return Err(anyhow!(
+                "Can't serialize parameter '{}' as TOML: TOML doesn't support null values",
+                path.join(":")
+            ))
+        }
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                toml::Value::Integer(i)
+            } else if let Some(f) = n.as_f64().filter(|_| !n.is_u64()) {
+                toml::Value::Float(f)
+            } else {
+                return Err(anyhow!(
+                    "Can't serialize parameter '{}' as TOML: {n} is out of range for a TOML \
+                    integer",
+                    path.join(":")
+                ));
+            }
+        }
+        Value::String(s) | Value::Literal(s) => toml::Value::String(s.clone()),
+        // Template values are serialized as a single-key table, cf. the conversion of `Value` to
+        // JSON.
+        Value::Template(s) => toml::Value::Table(toml::Table::from_iter([(
+            TEMPLATE_TAG.to_string(),
+            toml::Value::String(s.clone()),
+        )])),
+        Value::Mapping(m) => toml::Value::Table(toml_table(m, path)?),
+        Value::Sequence(s) | Value::ValueList(s) => {
+            let mut arr = toml::value::Array::with_capacity(s.len());
+            for (i, it) in s.iter().enumerate() {
+                path.push(i.to_string());
+                arr.push(toml_value(it, path)?);
+                path.pop();
+            }
+            toml::Value::Array(arr)
+        }
+    })
+}
+
+/// Infers a JSON Schema for the provided Mapping.
+fn json_schema_mapping(m: &Mapping) -> serde_json::Map<String, serde_json::Value> {
+    let mut properties = serde_json::Map::new();
+    let mut required = vec![];
+    for (k, v) in m {
+        // JSON keys must be strings, we convert the same Value variants to strings as the
+        // conversion of `Mapping` to JSON.
+        let k = match k {
+            Value::String(s) | Value::Literal(s) => s.clone(),
+            Value::Bool(b) => format!("{b}"),
+            Value::Number(n) => format!("{n}"),
+            Value::Null => "null".to_owned(),
+            _ => panic!("Can't serialize {} as JSON key", k.variant()),
+        };
+        if !v.is_null() {
+            required.push(serde_json::Value::String(k.clone()));
+        }
+        properties.insert(k, json_schema(v));
+    }
+    let mut schema = serde_json::Map::new();
+    schema.insert("type".to_string(), "object".into());
+    schema.insert("properties".to_string(), properties.into());
+    schema.insert("required".to_string(), required.into());
+    schema
+}
+
+/// Infers a JSON Schema for the provided Value.
+fn json_schema(v: &Value) -> serde_json::Value {
+    let mut schema = serde_json::Map::new();
+    match v {
+        Value::Null => {
+            schema.insert("type".to_string(), "null".into());
+        }
+        Value::Bool(_) => {
+            schema.insert("type".to_string(), "boolean".into());
+        }
+        Value::Number(n) => {
+            let t = if n.is_i64() || n.is_u64() {
+                "integer"
+            } else {
+                "number"
+            };
+            schema.insert("type".to_string(), t.into());
+        }
+        Value::String(_) | Value::Literal(_) => {
+            schema.insert("type".to_string(), "string".into());
+        }
+        // Template values are serialized as a single-key object, cf. the conversion of `Value` to
+        // JSON.
+        Value::Template(s) => {
+            let properties = serde_json::Map::from_iter([(
+                TEMPLATE_TAG.to_string(),
+                json_schema(&Value::Literal(s.clone())),
+            )]);
+            schema.insert("type".to_string(), "object".into());
+            schema.insert("properties".to_string(), properties.into());
+            schema.insert("required".to_string(), vec![TEMPLATE_TAG].into());
+        }
+        Value::Mapping(m) => return serde_json::Value::Object(json_schema_mapping(m)),
+        Value::Sequence(s) | Value::ValueList(s) => {
+            schema.insert("type".to_string(), "array".into());
+            let mut items: Vec<serde_json::Value> = vec![];
+            for it in s {
+                let it = json_schema(it);
+                if !items.contains(&it) {
+                    items.push(it);
+                }
+            }
+            match items.len() {
+                0 => {}
+                1 => {
+                    schema.insert("items".to_string(), items.pop().unwrap());
+                }
+                _ => {
+                    let mut anyof = serde_json::Map::new();
+                    anyof.insert("anyOf".to_string(), items.into());
+                    schema.insert("items".to_string(), anyof.into());
+                }
+            }
+        }
+    }
+    serde_json::Value::Object(schema)
+}
+
+#[cfg(test)]
+mod nodeinfo_tests {
+    use pyo3::prelude::*;
+
+    #[test]
+    fn test_parameter() {
+        let r = crate::node::make_reclass();
+        let n = r
+            .render_node_from_str("generated", "classes: [cls4]")
+            .unwrap();
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let name = n.parameter(py, "cluster:name").unwrap().unwrap();
+            assert_eq!(name.extract::<String>().unwrap(), "c-test-cluster-1234");
+            let cluster = n.parameter(py, "cluster").unwrap().unwrap();
+            assert!(cluster.is_instance_of::<pyo3::types::PyDict>());
+            assert!(n.parameter(py, "cluster:missing").unwrap().is_none());
+        });
+    }
+
+    #[test]
+    fn test_content_hash() {
+        let r = crate::node::make_reclass();
+        // The content hash doesn't depend on the render time
+        let a = r.render_node("n1").unwrap();
+        let b = r.render_node("n1").unwrap();
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(
+            a.content_hash(),
+            r.render_node("n2").unwrap().content_hash()
+        );
+    }
+
+    #[test]
+    fn test_sorted_classes_and_applications() {
+        let r = crate::node::make_reclass();
+        let n = r.render_node("n3").unwrap();
+        assert_eq!(n.sorted_classes(), vec!["cls3", "cls4", "cls5", "cls6"]);
+        // the merge-ordered list is left intact
+        assert_eq!(n.classes, vec!["cls4", "cls5", "cls6", "cls3"]);
+
+        let n = r.render_node("n12").unwrap();
+        assert_eq!(n.sorted_applications(), vec!["a", "b", "c"]);
+        assert_eq!(n.applications, vec!["c", "a", "b"]);
+        assert_eq!(n.sorted_classes(), vec!["app1", "cls9"]);
+    }
+
+    #[test]
+    fn test_constant_parameters() {
+        let r = crate::node::make_reclass();
+        let n = r
+            .render_node_from_str(
+                "constant",
+                r"
+            classes:
+              - cls1
+            parameters:
+              =const: foo
+              nested:
+                =name: bar
+                other: baz
+            ",
+            )
+            .unwrap();
+        assert_eq!(n.constant_parameters(), vec!["const", "nested:name"]);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_to_toml_string() {
+        let r = crate::node::make_reclass();
+        let n = r
+            .render_node_from_str(
+                "toml",
+                r"
+            parameters:
+              name: foo
+              nested:
+                replicas: 3
+                ratio: 0.5
+                enabled: true
+                list: [a, b]
+              after: bar
+            ",
+            )
+            .unwrap();
+        let t: toml::Table = toml::from_str(&n.to_toml_string().unwrap()).unwrap();
+        assert_eq!(t["name"].as_str(), Some("foo"));
+        assert_eq!(t["after"].as_str(), Some("bar"));
+        assert_eq!(t["nested"]["replicas"].as_integer(), Some(3));
+        assert_eq!(t["nested"]["ratio"].as_float(), Some(0.5));
+        assert_eq!(t["nested"]["enabled"].as_bool(), Some(true));
+        assert_eq!(
+            t["nested"]["list"],
+            toml::Value::Array(vec!["a".into(), "b".into()])
+        );
+        assert_eq!(t["_reclass_"]["name"]["full"].as_str(), Some("toml"));
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_to_toml_string_null() {
+        let r = crate::node::make_reclass();
+        let n = r
+            .render_node_from_str("toml", "parameters: {nested: {list: [a, null]}}")
+            .unwrap();
+        assert_eq!(
+            n.to_toml_string().unwrap_err().to_string(),
+            "Can't serialize parameter 'nested:list:1' as TOML: TOML doesn't support null values"
+        );
+    }
+
+    #[test]
+    fn test_to_json_schema() {
+        let r = crate::node::make_reclass();
+        let n = r
+            .render_node_from_str(
+                "schema",
+                r"
+            parameters:
+              nested:
+                name: foo
+                replicas: 3
+                ratio: 0.5
+                enabled: true
+                unset: null
+              list:
+                - a
+                - b
+              mixed:
+                - a
+                - 1
+            ",
+            )
+            .unwrap();
+        let schema = n.to_json_schema();
+
+        assert_eq!(schema["$schema"], "http://json-schema.org/draft-07/schema#");
+        assert_eq!(schema["type"], "object");
+        assert_eq!(
+            schema["required"],
+            serde_json::Value::from(vec!["_reclass_", "nested", "list", "mixed"])
+        );
+
+        let reclass = &schema["properties"]["_reclass_"];
+        assert_eq!(reclass["type"], "object");
+        assert_eq!(
+            reclass["properties"]["name"]["properties"]["parts"]["items"]["type"],
+            "string"
+        );
+
+        let nested = &schema["properties"]["nested"];
+        assert_eq!(nested["type"], "object");
+        assert_eq!(nested["properties"]["name"]["type"], "string");
+        assert_eq!(nested["properties"]["replicas"]["type"], "integer");
+        assert_eq!(nested["properties"]["ratio"]["type"], "number");
+        assert_eq!(nested["properties"]["enabled"]["type"], "boolean");
+        assert_eq!(nested["properties"]["unset"]["type"], "null");
+        // keys with null values are optional
+        assert_eq!(
+            nested["required"],
+            serde_json::Value::from(vec!["name", "replicas", "ratio", "enabled"])
+        );
+
+        let list = &schema["properties"]["list"];
+        assert_eq!(list["type"], "array");
+        assert_eq!(list["items"]["type"], "string");
+
+        let mixed = &schema["properties"]["mixed"];
+        assert_eq!(mixed["type"], "array");
+        assert_eq!(mixed["items"]["anyOf"][0]["type"], "string");
+        assert_eq!(mixed["items"]["anyOf"][1]["type"], "integer");
     }
 }
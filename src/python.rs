@@ -0,0 +1,418 @@
+// pyo3's `#[pymethods]` expansion generates wrapper functions next to the annotated impl block,
+// which trigger this lint for methods which return `PyResult`. The lint can therefore only be
+// allowed for the whole module which holds the impl block.
+#![allow(clippy::useless_conversion)]
+
+use chrono::Local;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyType};
+use rayon::ThreadPoolBuilder;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::config::{CompatFlag, Config};
+use crate::inventory::Inventory;
+use crate::node::{Node, NodeInfo};
+use crate::Reclass;
+
+#[pymethods]
+impl Reclass {
+    #[new]
+    #[pyo3(signature = (inventory_path=".", nodes_path=None, classes_path=None, ignore_class_notfound=None))]
+    pub fn new_py(
+        inventory_path: Option<&str>,
+        nodes_path: Option<&str>,
+        classes_path: Option<&str>,
+        ignore_class_notfound: Option<bool>,
+    ) -> PyResult<Self> {
+        let c = Config::new(
+            inventory_path,
+            nodes_path,
+            classes_path,
+            ignore_class_notfound,
+        )
+        .map_err(|e| PyValueError::new_err(format!("{e}")))?;
+        let r = Self::new_from_config(c).map_err(|e| PyValueError::new_err(format!("{e}")))?;
+        Ok(r)
+    }
+
+    /// Creates a `Reclass` instance for the provided `inventory_path` and loads config options
+    /// from the provided config file. The value of `config_file` is interpreted relative to
+    /// `inventory_path`.
+    ///
+    /// Returns a `Reclass` instance or raises a `ValueError`
+    #[classmethod]
+    #[pyo3(name = "from_config_file", signature = (inventory_path, config_file, verbose=false))]
+    fn py_from_config_file(
+        _cls: &Bound<'_, PyType>,
+        inventory_path: &str,
+        config_file: &str,
+        verbose: bool,
+    ) -> PyResult<Self> {
+        Self::from_config_file(inventory_path, config_file, verbose)
+            .map_err(|e| PyValueError::new_err(format!("{e}")))
+    }
+
+    /// Creates a `Reclass` instance from the provided `Config` instance.
+    ///
+    /// Returns a `Reclass` instance or raises a `ValueError`
+    #[classmethod]
+    fn from_config(_cls: &Bound<'_, PyType>, config: Config) -> PyResult<Self> {
+        let r = Self::new_from_config(config).map_err(|e| PyValueError::new_err(format!("{e}")))?;
+        Ok(r)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("{self:#?}")
+    }
+
+    /// Re-discovers all nodes and classes with the instance's current config.
+    ///
+    /// Raises a `ValueError` if discovery fails. In that case, the instance doesn't hold any
+    /// nodes or classes afterwards.
+    #[pyo3(name = "reload")]
+    fn py_reload(&mut self) -> PyResult<()> {
+        self.reload()
+            .map_err(|e| PyValueError::new_err(format!("{e}")))
+    }
+
+    /// Returns the rendered data for the node with the provided name if it exists.
+    pub fn nodeinfo(&self, nodename: &str) -> PyResult<NodeInfo> {
+        self.render_node(nodename)
+            .map_err(|e| PyValueError::new_err(format!("Error while rendering {nodename}: {e}")))
+    }
+
+    /// Returns the rendered data for a node with the provided name whose definition is given as a
+    /// YAML string.
+    pub fn nodeinfo_from_str(&self, nodename: &str, yaml: &str) -> PyResult<NodeInfo> {
+        self.render_node_from_str(nodename, yaml)
+            .map_err(|e| PyValueError::new_err(format!("Error while rendering {nodename}: {e}")))
+    }
+
+    /// Returns true if a node with the provided name was discovered in `nodes_path`.
+    #[must_use]
+    pub fn node_exists(&self, name: &str) -> bool {
+        self.nodes.contains_key(name)
+    }
+
+    /// Returns true if a class with the provided name was discovered in `classes_path`. Relative
+    /// class names are interpreted as relative to `classes_path`.
+    #[must_use]
+    pub fn class_exists(&self, name: &str) -> bool {
+        Node::default()
+            .abs_class_name(name)
+            .is_ok_and(|cls| self.class_info(&cls).is_some())
+    }
+
+    /// Returns the location of the node with the provided name, i.e. the directory relative to
+    /// `nodes_path` which holds the node file. Returns `None` if no such node was discovered.
+    #[must_use]
+    pub fn node_location(&self, name: &str) -> Option<PathBuf> {
+        self.nodes.get(name).map(|info| info.loc.clone())
+    }
+
+    /// Returns the location of the class with the provided name, i.e. the directory relative to
+    /// `classes_path` which anchors the class's relative includes. Relative class names are
+    /// interpreted as relative to `classes_path`. Returns `None` if no such class was discovered.
+    #[must_use]
+    pub fn class_location(&self, name: &str) -> Option<PathBuf> {
+        let cls = Node::default().abs_class_name(name).ok()?;
+        self.class_info(&cls).map(|info| info.loc.clone())
+    }
+
+    /// Returns the flattened reference paths of all references which are present in the
+    /// unrendered parameters of the node with the provided name.
+    #[pyo3(name = "node_references")]
+    fn py_node_references(&self, name: &str) -> PyResult<Vec<String>> {
+        self.node_references(name).map_err(|e| {
+            PyValueError::new_err(format!("Error while parsing references of {name}: {e}"))
+        })
+    }
+
+    /// Returns the rendered data for the full inventory.
+    pub fn inventory(&self) -> PyResult<Inventory> {
+        self.render_inventory()
+            .map_err(|e| PyValueError::new_err(format!("Error while rendering inventory: {e}")))
+    }
+
+    /// Configures the number of threads to use when rendering the full inventory. Calling the
+    /// method with `count=0` will configure the thread pool to have one thread per logical core of
+    /// the system.
+    ///
+    /// Note that this method should only be called once and will print a diagnostic message if
+    /// called again. Use `set_local_thread_count()` to configure the number of threads for a
+    /// single Reclass instance instead.
+    #[classmethod]
+    pub fn set_thread_count(_cls: &Bound<'_, PyType>, count: usize) {
+        if let Err(e) = ThreadPoolBuilder::new().num_threads(count).build_global() {
+            eprintln!("While initializing global thread pool: {e}");
+        }
+    }
+
+    /// Configures the number of threads to use when rendering the full inventory for the current
+    /// Reclass instance. The inventory is rendered in a thread pool which is local to the render
+    /// call. Calling the method with `count=0` will use one thread per logical core of the system,
+    /// and calling it with `count=None` will use the global thread pool again.
+    ///
+    /// In contrast to `set_thread_count()`, this method can be called repeatedly.
+    #[pyo3(signature = (count))]
+    pub fn set_local_thread_count(&mut self, count: Option<usize>) {
+        self.config.thread_count = count;
+    }
+
+    /// Sets the provided CompatFlag in the current Reclass instance's config object
+    pub fn set_compat_flag(&mut self, flag: CompatFlag) {
+        self.config.compatflags.insert(flag);
+    }
+
+    /// Unsets the provided CompatFlag in the current Reclass instance's config object
+    pub fn unset_compat_flag(&mut self, flag: &CompatFlag) {
+        self.config.compatflags.remove(flag);
+    }
+
+    /// Clears the compatflags set in the current Reclass instance's config object
+    pub fn clear_compat_flags(&mut self) {
+        self.config.compatflags.clear();
+    }
+
+    /// Returns a dict containing all discovered nodes with their paths relative to `nodes_path`.
+    ///
+    /// NOTE: We don't use the generated getter here, because we don't want to return the
+    /// EntityInfo.
+    #[getter]
+    pub fn nodes(&self) -> PyResult<HashMap<String, PathBuf>> {
+        let res = self
+            .nodes
+            .iter()
+            .map(|(k, v)| (k.clone(), v.path.clone()))
+            .collect::<HashMap<String, PathBuf>>();
+        Ok(res)
+    }
+
+    /// Returns the dict of all discovered classes and their paths relative to `classes_path`.
+    ///
+    /// NOTE: We don't use the generated getter here, because we don't want to return the
+    /// EntityInfo.
+    #[getter]
+    pub fn classes(&self) -> PyResult<HashMap<String, PathBuf>> {
+        let res = self
+            .classes
+            .iter()
+            .map(|(k, v)| (k.clone(), v.path.clone()))
+            .collect::<HashMap<String, PathBuf>>();
+        Ok(res)
+    }
+
+    /// Returns a list of `(name, kind, path)` tuples for all discovered nodes and classes. `kind`
+    /// is either `node` or `class`, and `path` is relative to `nodes_path` or `classes_path`
+    /// respectively.
+    #[pyo3(name = "entities")]
+    fn py_entities(&self) -> Vec<(String, String, PathBuf)> {
+        self.entities()
+            .into_iter()
+            .map(|(name, kind, path)| (name, kind.to_string(), path))
+            .collect()
+    }
+
+    /// Update the current Reclass instance's config object with the provided
+    /// `ignore_class_notfound_regexp` patterns
+    pub fn set_ignore_class_notfound_regexp(&mut self, patterns: Vec<String>) -> PyResult<()> {
+        self.config
+            .set_ignore_class_notfound_regexp(patterns)
+            .map_err(|e| {
+                PyValueError::new_err(format!(
+                    "Error while setting ignore_class_notfound_regexp: {e}"
+                ))
+            })
+    }
+}
+
+#[pymethods]
+impl Config {
+    fn __repr__(&self) -> String {
+        format!("{self:#?}")
+    }
+
+    /// Creates a Config object based on the provided `inventory_path` and the config options
+    /// passed in the `config` Python dict. If `verbose` is set to `true`, reclass-rs will print
+    /// diagnostic messages for unknown config options.
+    ///
+    /// Returns a `Config` object or raises a `ValueError`.
+    #[classmethod]
+    #[pyo3(signature = (inventory_path, config, verbose=false))]
+    fn from_dict(
+        _cls: &Bound<'_, PyType>,
+        inventory_path: &str,
+        config: &Bound<'_, PyDict>,
+        verbose: bool,
+    ) -> PyResult<Self> {
+        let mut cfg = Config::new(Some(inventory_path), None, None, None).map_err(|e| {
+            PyValueError::new_err(format!(
+                "Failed to initialize reclass-rs config object: {e}"
+            ))
+        })?;
+
+        for (k, v) in config {
+            let kstr = k.extract::<&str>()?;
+            let val: crate::types::Value = TryInto::try_into(v)?;
+            cfg.set_option(Path::new(inventory_path), kstr, &val.into(), verbose)
+                .map_err(|e| {
+                    PyValueError::new_err(format!("Error while setting option {kstr}: {e}"))
+                })?;
+        }
+        cfg.compile_ignore_class_notfound_patterns().map_err(|e| {
+            PyValueError::new_err(format!("Error while compiling config patterns: {e}"))
+        })?;
+
+        Ok(cfg)
+    }
+
+    /// Returns a dict summarizing the effective configuration. See `Config::effective()` for
+    /// details.
+    #[pyo3(name = "effective")]
+    fn py_effective<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.effective()
+            .map_err(|e| PyValueError::new_err(format!("{e}")))?
+            .as_py_dict(py)
+    }
+
+    /// Checks that the configured nodes and classes paths are non-overlapping and that both paths
+    /// exist. Raises a `ValueError` otherwise.
+    #[pyo3(name = "validate")]
+    fn py_validate(&self) -> PyResult<()> {
+        self.validate()
+            .map_err(|e| PyValueError::new_err(format!("Invalid config: {e}")))
+    }
+}
+
+#[pymethods]
+impl Inventory {
+    /// Returns the Inventory as a Python dict.
+    ///
+    /// The structure of the returned dict should match Python reclass the structure of the dict
+    /// returned by Python reclass's `inventory()` method.
+    fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
+        dict.set_item("classes", self.classes.clone().into_pyobject(py)?)?;
+        let nodes_dict = PyDict::new(py);
+        for (name, info) in &self.nodes {
+            nodes_dict.set_item(name, info.as_dict(py)?)?;
+        }
+        dict.set_item("nodes", nodes_dict)?;
+
+        let reclass_dict = PyDict::new(py);
+        let ts = Local::now();
+        reclass_dict.set_item("timestamp", ts.format("%c").to_string())?;
+        dict.set_item("__reclass__", reclass_dict)?;
+        Ok(dict)
+    }
+
+    /// Returns the sorted list of nodes which include class `class`. Returns an empty list if no
+    /// node includes the class.
+    ///
+    /// Note that classes with references in their name are tracked unrendered, e.g. as `${cls}`.
+    #[must_use]
+    pub fn nodes_with_class(&self, class: &str) -> Vec<String> {
+        self.classes.get(class).cloned().unwrap_or_default()
+    }
+
+    /// Returns the sorted list of nodes which include application `app`. Returns an empty list if
+    /// no node includes the application.
+    #[must_use]
+    pub fn nodes_with_application(&self, app: &str) -> Vec<String> {
+        self.applications.get(app).cloned().unwrap_or_default()
+    }
+}
+
+#[pymethods]
+impl NodeInfo {
+    fn __repr__(&self) -> String {
+        format!("{self:#?}")
+    }
+
+    /// Returns the NodeInfo `parameters` field as a PyDict
+    #[getter]
+    fn parameters<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.parameters.as_py_dict(py)
+    }
+
+    /// Returns the value of the rendered parameter at the provided `:`-separated path (e.g.
+    /// `cluster:name`), or `None` if the path doesn't exist.
+    ///
+    /// In contrast to indexing into `parameters`, only the requested value is converted into a
+    /// Python object.
+    pub(crate) fn parameter<'py>(
+        &self,
+        py: Python<'py>,
+        path: &str,
+    ) -> PyResult<Option<Bound<'py, PyAny>>> {
+        self.parameters
+            .get_path(path)
+            .map(|v| v.as_py_obj(py))
+            .transpose()
+    }
+
+    /// Returns the classes included by the node, sorted lexically. Field `classes` holds the
+    /// classes in merge order.
+    #[must_use]
+    pub fn sorted_classes(&self) -> Vec<String> {
+        let mut classes = self.classes.clone();
+        classes.sort();
+        classes
+    }
+
+    /// Returns the applications included by the node, sorted lexically. Field `applications`
+    /// holds the applications in merge order.
+    #[must_use]
+    pub fn sorted_applications(&self) -> Vec<String> {
+        let mut applications = self.applications.clone();
+        applications.sort();
+        applications
+    }
+
+    /// Returns the paths of all rendered parameters which are marked as constant
+    #[getter(constant_parameters)]
+    fn py_constant_parameters(&self) -> Vec<String> {
+        self.constant_parameters()
+    }
+
+    /// Returns the NodeInfo `exports` field as a PyDict
+    #[getter]
+    fn exports<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.exports.as_py_dict(py)
+    }
+
+    /// Returns the NodeInfo data as a PyDict
+    ///
+    /// This method generates a PyDict which should be structured identically to Python Reclass's
+    /// `nodeinfo` return value.
+    pub(crate) fn as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("__reclass__", self.reclass_as_dict(py)?)?;
+        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
+        dict.set_item("applications", self.applications.clone().into_pyobject(py)?)?;
+        dict.set_item(
+            "environment",
+            self.reclass.environment.clone().into_pyobject(py)?,
+        )?;
+        dict.set_item("exports", self.exports(py)?)?;
+        dict.set_item("parameters", self.parameters(py)?)?;
+        Ok(dict)
+    }
+
+    /// Returns the NodeInfo `meta` field as a PyDict
+    fn reclass_as_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let dict = PyDict::new(py);
+        dict.set_item("node", self.reclass.node.clone().into_pyobject(py)?)?;
+        dict.set_item("name", self.reclass.name.clone().into_pyobject(py)?)?;
+        dict.set_item("uri", self.reclass.uri.clone().into_pyobject(py)?)?;
+        dict.set_item(
+            "environment",
+            self.reclass.environment.clone().into_pyobject(py)?,
+        )?;
+        dict.set_item("timestamp", self.render_timestamp())?;
+        Ok(dict)
+    }
+}
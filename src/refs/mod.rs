@@ -1,9 +1,11 @@
 mod parser;
 
+use crate::list::{List, UniqueList};
 use crate::types::{Mapping, Value};
 use anyhow::{anyhow, Result};
 use nom::error::{convert_error, VerboseError};
 use std::collections::HashSet;
+use std::fmt::Write;
 
 #[derive(Debug, PartialEq, Eq)]
 /// Represents a parsed Reclass reference
@@ -17,6 +19,39 @@ pub enum Token {
     Combined(Vec<Token>),
 }
 
+/// Options which control reference resolution and value merging while rendering.
+///
+/// The options for a render are constructed once from the `Config` and carried along in the
+/// `ResolveState` which is threaded through `Token::resolve()` and `Value::interpolate()`.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderOpts {
+    /// Maximum allowed recursion depth for `Token::resolve()`
+    pub max_depth: usize,
+    /// Whether sequence merges only append elements which aren't present yet
+    pub unique_sequences: bool,
+    /// Whether shell-style default values (`${foo:-default}`) are recognized in references
+    pub shell_style_defaults: bool,
+    /// Whether reference path segment `*` collects the values of a Mapping or the elements of a
+    /// Sequence into a Sequence
+    pub wildcard_refs: bool,
+    /// Whether merging a value over a value of an incompatible type replaces the value instead of
+    /// returning an error
+    pub type_changing_merge: bool,
+}
+
+impl Default for RenderOpts {
+    fn default() -> Self {
+        Self {
+            max_depth: RESOLVE_MAX_DEPTH,
+            unique_sequences: false,
+            shell_style_defaults: false,
+            wildcard_refs: false,
+            type_changing_merge: false,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct ResolveState {
     /// Reference paths which we've seen during reference resolution
@@ -26,9 +61,24 @@ pub struct ResolveState {
     depth: usize,
     /// Parameters key which we're currently processing.
     current_keys: Vec<String>,
+    /// Options for the current render.
+    opts: RenderOpts,
 }
 
 impl ResolveState {
+    /// Creates an initial resolution state for a render with the provided options.
+    pub(crate) fn with_opts(opts: RenderOpts) -> Self {
+        Self {
+            opts,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the options for the current render.
+    pub(crate) fn opts(&self) -> &RenderOpts {
+        &self.opts
+    }
+
     /// Pushes the provided index into the last element of current_keys as `[idx]`.
     pub(crate) fn push_list_index(&mut self, idx: usize) {
         let mut kcount = self.current_keys.len();
@@ -36,7 +86,7 @@ impl ResolveState {
             self.current_keys.push(String::new());
             kcount = 1;
         }
-        self.current_keys[kcount - 1].push_str(&format!("[{idx}]"));
+        let _ = write!(self.current_keys[kcount - 1], "[{idx}]");
     }
 
     /// Pushes mapping key into the `current_keys` list. If possible, the provided value is
@@ -82,8 +132,9 @@ impl ResolveState {
         let current_key = self.current_key();
         let paths = self.seen_paths_list();
         anyhow!(
-            "Token resolution exceeded recursion depth of {RESOLVE_MAX_DEPTH} for \
-            parameter '{current_key}'. We've seen the following reference paths: [{paths}].",
+            "Token resolution exceeded recursion depth of {} for parameter '{current_key}'. \
+            We've seen the following reference paths: [{paths}].",
+            self.opts.max_depth
         )
     }
 
@@ -96,6 +147,23 @@ impl ResolveState {
         )
     }
 
+    /// Returns the default value for a reference whose lookup failed due to a missing key, or a
+    /// suitable error if the reference doesn't have a default value.
+    fn missing_key_default(&self, path: &str, key: &str, default: Option<&str>) -> Result<Value> {
+        match default {
+            Some("") => Ok(Value::Null),
+            Some(d) => Ok(Value::Literal(d.to_string())),
+            None => Err(self.render_missing_key_error(path, key)),
+        }
+    }
+
+    /// Adds the parameter key which is currently being processed to the provided reference parse
+    /// error.
+    pub(crate) fn render_parse_error(&self, e: &anyhow::Error) -> anyhow::Error {
+        let current_key = self.current_key();
+        anyhow!("{e} in parameter '{current_key}'")
+    }
+
     /// Renders a lookup error with the given message
     fn render_lookup_error(&self, path: &str, key: &str, msg: &str) -> anyhow::Error {
         let current_key = self.current_key();
@@ -106,10 +174,11 @@ impl ResolveState {
     }
 }
 
-/// Maximum allowed recursion depth for Token::resolve(). We're fairly conservative with the value,
-/// since it's rather unlikely that a well-formed inventory will have any references that are
-/// nested deeper than 64.
-const RESOLVE_MAX_DEPTH: usize = 64;
+/// Default maximum allowed recursion depth for Token::resolve(). We're fairly conservative with
+/// the value, since it's rather unlikely that a well-formed inventory will have any references
+/// that are nested deeper than 64. The limit can be adjusted with config option
+/// `resolve_max_depth`.
+pub(crate) const RESOLVE_MAX_DEPTH: usize = 64;
 
 impl Token {
     /// Parses an arbitrary string into a `Token`. Returns None, if the string doesn't contain any
@@ -139,6 +208,37 @@ impl Token {
         matches!(self, Self::Literal(_))
     }
 
+    /// Returns the reference paths of all references contained in the Token, without resolving
+    /// any references. Each path is returned once, in the order in which it first appears.
+    ///
+    /// Only the statically known segments of a reference path are flattened. Nested references
+    /// are kept in their original `${...}` form as a marker for the dynamic part of the path, and
+    /// their own reference paths are reported separately, e.g. the reference paths for
+    /// `${foo:${bar}}` are `foo:${bar}` and `bar`.
+    pub fn references(&self) -> Vec<String> {
+        let mut refs = UniqueList::default();
+        self.collect_references(&mut refs);
+        refs.into()
+    }
+
+    /// Recursively collects the reference paths of the Token into `refs`.
+    fn collect_references(&self, refs: &mut UniqueList) {
+        match self {
+            Self::Literal(_) => {}
+            Self::Ref(parts) => {
+                refs.append_if_new(parts.iter().map(ToString::to_string).collect());
+                for p in parts {
+                    p.collect_references(refs);
+                }
+            }
+            Self::Combined(tokens) => {
+                for t in tokens {
+                    t.collect_references(refs);
+                }
+            }
+        }
+    }
+
     /// Renders the token into an arbitrary Value or a string. Reference values are looked up in
     /// the Mapping provided through parameter `params`.
     ///
@@ -159,7 +259,7 @@ impl Token {
     fn resolve(&self, params: &Mapping, state: &mut ResolveState) -> Result<Value> {
         match self {
             // Literal tokens can be directly turned into `Value::Literal`
-            Self::Literal(s) => Ok(Value::Literal(s.to_string())),
+            Self::Literal(s) => Ok(Value::Literal(s.clone())),
             Self::Combined(tokens) => {
                 let res = interpolate_token_slice(tokens, params, state)?;
                 // The result of `interpolate_token_slice()` for a `Token::Combined()` can't result
@@ -174,13 +274,13 @@ impl Token {
                 // We track the number of calls to `Token::resolve()` for Token::Ref that the
                 // current `state` has seen in state.depth.
                 state.depth += 1;
-                if state.depth > RESOLVE_MAX_DEPTH {
-                    // If we've called `Token::resolve()` more than RESOLVE_MAX_DEPTH (64) times
+                if state.depth > state.opts.max_depth {
+                    // If we've called `Token::resolve()` more than `max_depth` (default 64) times
                     // recursively, it's likely that there's still an edge case where we don't
                     // detect a reference loop with the current reference path tracking
-                    // implementation. We abort at a recursion depth of 64, since it's quite
-                    // unlikely that there's a legitimate case where we have a recursion depth of
-                    // 64 when resolving references for a well formed inventory.
+                    // implementation. By default, we abort at a recursion depth of 64, since it's
+                    // quite unlikely that there's a legitimate case where we have a recursion
+                    // depth of 64 when resolving references for a well formed inventory.
                     return Err(state.render_recursion_depth_error());
                 }
                 // Construct flattened ref path by resolving any potential nested references in the
@@ -194,16 +294,23 @@ impl Token {
                 }
                 state.seen_paths.insert(path.clone());
 
+                // Split off shell-style default value, if enabled. The default value is returned
+                // if any segment of the reference path doesn't exist.
+                let (refpath, default) = match path.split_once(":-") {
+                    Some((p, d)) if state.opts.shell_style_defaults => (p, Some(d)),
+                    _ => (path.as_str(), None),
+                };
+
                 // generate iterator containing flattened reference path segments
-                let mut refpath_iter = path.split(':');
+                let mut refpath_iter = refpath.split(':');
                 // we handle the first element separately, so we can establish a local mutable
                 // variable which we can update during the walk of the parameters Mapping.
                 let k0 = refpath_iter.next().unwrap();
                 // v is the value which we update to point to the next value as we recursively
                 // descend into the params Mapping
-                let mut v = params
-                    .get(&k0.into())
-                    .ok_or_else(|| state.render_missing_key_error(&path, k0))?;
+                let Some(mut v) = params.get(&k0.into()) else {
+                    return state.missing_key_default(&path, k0, default);
+                };
 
                 // newv is used to hold temporary Values generated by interpolating v
                 let mut newv;
@@ -224,21 +331,51 @@ impl Token {
                     // Do lookup in interpolated value, return error if interpolated value doesn't
                     // support lookups.
                     match newv {
+                        // wildcard segment: collect the values of the Mapping, or the elements of
+                        // the Sequence, into a new Sequence. Further path segments are looked up
+                        // in the resulting Sequence.
+                        Value::Mapping(_) | Value::Sequence(_)
+                            if key == "*" && state.opts.wildcard_refs =>
+                        {
+                            if let Value::Mapping(m) = newv {
+                                newv = Value::Sequence(m.as_map().values().cloned().collect());
+                            }
+                            v = &newv;
+                        }
                         // trivial case: v is a Mapping, we can just lookup the next value based
                         // on `key`.
                         Value::Mapping(_) => {
-                            v = newv
-                                .get(&key.into())
-                                .ok_or_else(|| state.render_missing_key_error(&path, key))?;
+                            let Some(nv) = newv.get(&key.into()) else {
+                                return state.missing_key_default(&path, key, default);
+                            };
+                            v = nv;
                         }
-                        // Sequence lookups aren't supported by Python Reclass. We may implement
-                        // them in the future.
+                        // Sequence lookups aren't supported by Python Reclass. We support looking up
+                        // sequence elements by their (zero-based) index.
                         Value::Sequence(_) => {
-                            return Err(state.render_lookup_error(
-                                &path,
-                                key,
-                                "Sequence lookups aren't supported for Reclass references!",
-                            ));
+                            let seq = newv.as_sequence().unwrap();
+                            let idx = key.parse::<usize>().map_err(|_| {
+                                state.render_lookup_error(
+                                    &path,
+                                    key,
+                                    &format!(
+                                        "Can't continue lookup, {} is a Value::Sequence and \
+                                        '{key}' isn't a valid sequence index",
+                                        traversed.join(":")
+                                    ),
+                                )
+                            })?;
+                            v = seq.get(idx).ok_or_else(|| {
+                                state.render_lookup_error(
+                                    &path,
+                                    key,
+                                    &format!(
+                                        "Index {idx} out of bounds for sequence {} of length {}",
+                                        traversed.join(":"),
+                                        seq.len()
+                                    ),
+                                )
+                            })?;
                         }
                         Value::String(_) | Value::ValueList(_) => unreachable!(
                             "We should have rendered Value::String and Value::ValueList into some other variant"
@@ -280,7 +417,7 @@ impl std::fmt::Display for Token {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         fn flatten(ts: &[Token]) -> String {
             ts.iter().fold(String::new(), |mut st, t| {
-                st.push_str(&format!("{t}"));
+                st.push_str(&t.to_string());
                 st
             })
         }
@@ -348,7 +485,7 @@ fn interpolate_string_or_valuelist(
                 i.push(v);
             }
             // Finally we flatten the resulting ValueList into a single Value.
-            Value::ValueList(i).flattened()
+            Value::ValueList(i).flattened_with(state.opts())
         }
         // Do nothing for other types
         _ => Ok(v.clone()),
@@ -385,7 +522,7 @@ impl std::fmt::Display for ParseError<'_> {
 ///
 /// Users should use `Token::parse()` which converts the internal `ParseError` into a format
 /// suitable to be handled with `anyhow::Result`.
-fn parse_ref(input: &str) -> Result<Token, ParseError> {
+fn parse_ref(input: &str) -> Result<Token, ParseError<'_>> {
     use self::parser::parse_ref;
     let (uncons, token) = parse_ref(input).map_err(|e| match e {
         nom::Err::Error(e) | nom::Err::Failure(e) => ParseError {
@@ -406,7 +543,7 @@ fn parse_ref(input: &str) -> Result<Token, ParseError> {
             "Trailing data '{}' occurred when parsing '{}', this shouldn't happen! Parsed result: {}",
             uncons, input, token
         );
-    };
+    }
     Ok(token)
 }
 
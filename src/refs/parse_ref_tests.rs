@@ -4,14 +4,14 @@ use super::*;
 fn test_parse_no_ref() {
     let input = "foo-bar-baz";
     let res = parse_ref(input).unwrap();
-    assert_eq!(res, Token::literal_from_str("foo-bar-baz"))
+    assert_eq!(res, Token::literal_from_str("foo-bar-baz"));
 }
 
 #[test]
 fn test_parse_escaped_ref() {
     let input = r"foo-bar-\${baz}";
     let res = parse_ref(input).unwrap();
-    assert_eq!(res, Token::literal_from_str("foo-bar-${baz}"))
+    assert_eq!(res, Token::literal_from_str("foo-bar-${baz}"));
 }
 
 #[test]
@@ -24,7 +24,7 @@ fn test_parse_ref() {
             Token::Literal("foo-".to_owned()),
             Token::Ref(vec![Token::Literal("bar:baz".to_owned())])
         ])
-    )
+    );
 }
 
 #[test]
@@ -60,7 +60,7 @@ fn test_parse_ref_error_1() {
     let res = parse_ref(input);
     assert!(res.is_err());
     let e = res.unwrap_err();
-    println!("{}", e);
+    println!("{e}");
 }
 
 #[test]
@@ -69,7 +69,7 @@ fn test_parse_ref_error_2() {
     let res = parse_ref(input);
     assert!(res.is_err());
     let e = res.unwrap_err();
-    println!("{}", e);
+    println!("{e}");
 }
 
 #[test]
@@ -78,13 +78,13 @@ fn test_parse_ref_error_3() {
     let res = parse_ref(input);
     assert!(res.is_err());
     let e = res.unwrap_err();
-    println!("{}", e);
+    println!("{e}");
 }
 
 #[test]
 fn test_parse_ref_format() {
     let input = r"foo-${foo:${bar}}-${baz}-\${bar}-\\${qux}";
-    let res = parse_ref(&input).unwrap();
+    let res = parse_ref(input).unwrap();
     assert_eq!(
         res,
         Token::Combined(vec![
@@ -99,5 +99,5 @@ fn test_parse_ref_format() {
             Token::Ref(vec![Token::literal_from_str("qux")]),
         ])
     );
-    assert_eq!(format!("{}", res), input);
+    assert_eq!(format!("{res}"), input);
 }
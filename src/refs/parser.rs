@@ -217,23 +217,20 @@ mod test_parser_funcs {
     #[test]
     fn test_simple_ref() {
         assert_eq!(
-            parse_ref(&"${foo}".to_string()),
+            parse_ref("${foo}"),
             Ok(("", Token::Ref(vec![Token::literal_from_str("foo")])))
         );
     }
 
     #[test]
     fn test_parse_literal_dollar() {
-        assert_eq!(
-            parse_ref(&"$".to_string()),
-            Ok(("", Token::literal_from_str("$")))
-        );
+        assert_eq!(parse_ref("$"), Ok(("", Token::literal_from_str("$"))));
     }
 
     #[test]
     fn test_parse_escape_in_literal() {
         assert_eq!(
-            parse_ref(&"foo\\bar".to_string()),
+            parse_ref("foo\\bar"),
             Ok(("", Token::literal_from_str("foo\\bar")))
         );
     }
@@ -241,7 +238,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_literal_dollar_begin() {
         assert_eq!(
-            parse_ref(&"$foo".to_string()),
+            parse_ref("$foo"),
             Ok(("", Token::literal_from_str("$foo"),))
         );
     }
@@ -249,30 +246,27 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_literal_double_dollar() {
         assert_eq!(
-            parse_ref(&"foo$$foo".to_string()),
+            parse_ref("foo$$foo"),
             Ok(("", Token::literal_from_str("foo$$foo")))
         );
     }
 
     #[test]
     fn test_parse_literal_dollar_end() {
-        assert_eq!(
-            parse_ref(&"foo$".to_string()),
-            Ok(("", Token::literal_from_str("foo$")))
-        );
+        assert_eq!(parse_ref("foo$"), Ok(("", Token::literal_from_str("foo$"))));
     }
 
     #[test]
     fn test_parse_literal_double_dollar_end() {
         assert_eq!(
-            parse_ref(&"foo$$".to_string()),
+            parse_ref("foo$$"),
             Ok(("", Token::literal_from_str("foo$$")))
         );
     }
 
     #[test]
     fn test_parse_leading_dollar_ref() {
-        let refstr = r#"$${foo}"#.to_string();
+        let refstr = r"$${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -282,13 +276,13 @@ mod test_parser_funcs {
                     Token::Ref(vec![Token::literal_from_str("foo")])
                 ])
             ))
-        )
+        );
     }
 
     #[test]
     fn test_parse_full_string_ref() {
         assert_eq!(
-            parse_ref(&"${foo:bar:baz}".to_string()),
+            parse_ref("${foo:bar:baz}"),
             Ok((
                 "",
                 Token::Ref(vec![Token::literal_from_str("foo:bar:baz"),])
@@ -299,7 +293,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_ref_at_start() {
         assert_eq!(
-            parse_ref(&"${foo}bar".to_string()),
+            parse_ref("${foo}bar"),
             Ok((
                 "",
                 Token::Combined(vec![
@@ -313,7 +307,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_ref_at_end() {
         assert_eq!(
-            parse_ref(&"foo${bar}".to_string()),
+            parse_ref("foo${bar}"),
             Ok((
                 "",
                 Token::Combined(vec![
@@ -327,7 +321,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_ref_followed_by_ref() {
         assert_eq!(
-            parse_ref(&"${foo}${bar}".to_string()),
+            parse_ref("${foo}${bar}"),
             Ok((
                 "",
                 Token::Combined(vec![
@@ -341,7 +335,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_interspersed_refs() {
         assert_eq!(
-            parse_ref(&"a-${foo}-${bar}-b".to_string()),
+            parse_ref("a-${foo}-${bar}-b"),
             Ok((
                 "",
                 Token::Combined(vec![
@@ -358,7 +352,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_nested_refs() {
         assert_eq!(
-            parse_ref(&"${foo:${bar}}".to_string()),
+            parse_ref("${foo:${bar}}"),
             Ok((
                 "",
                 Token::Ref(vec![
@@ -372,7 +366,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_nested_refs_complex_1() {
         assert_eq!(
-            parse_ref(&"${foo:${bar}:baz}".to_string()),
+            parse_ref("${foo:${bar}:baz}"),
             Ok((
                 "",
                 Token::Ref(vec![
@@ -387,7 +381,7 @@ mod test_parser_funcs {
     #[test]
     fn test_parse_nested_refs_complex_2() {
         assert_eq!(
-            parse_ref(&"${foo:${bar:${baz}}}".to_string()),
+            parse_ref("${foo:${bar:${baz}}}"),
             Ok((
                 "",
                 Token::Ref(vec![
@@ -403,25 +397,25 @@ mod test_parser_funcs {
 
     #[test]
     fn test_parse_escaped_ref() {
-        let refstr = r#"\${foo}"#.to_string();
+        let refstr = r"\${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok(("", Token::literal_from_str("${foo}")))
-        )
+        );
     }
 
     #[test]
     fn test_parse_escaped_ref_embedded() {
-        let refstr = r#"pass \${foo}"#.to_string();
+        let refstr = r"pass \${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok(("", Token::literal_from_str("pass ${foo}")))
-        )
+        );
     }
 
     #[test]
     fn test_parse_double_escaped_ref() {
-        let refstr = r#"\\${foo}"#.to_string();
+        let refstr = r"\\${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -431,21 +425,21 @@ mod test_parser_funcs {
                     Token::Ref(vec![Token::literal_from_str("foo")])
                 ])
             ))
-        )
+        );
     }
 
     #[test]
     fn test_parse_escaped_ref_close() {
-        let refstr = r#"${foo\}}"#.to_string();
+        let refstr = r"${foo\}}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok(("", Token::Ref(vec![Token::literal_from_str("foo}")])))
-        )
+        );
     }
 
     #[test]
     fn test_parse_escaped_ref_close_embedded() {
-        let refstr = r#"foo$-${foo\}}-\${bar}"#.to_string();
+        let refstr = r"foo$-${foo\}}-\${bar}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -456,12 +450,12 @@ mod test_parser_funcs {
                     Token::literal_from_str("-${bar}"),
                 ])
             ))
-        )
+        );
     }
 
     #[test]
     fn test_parse_escaped_escape_close_in_refpath() {
-        let refstr = r#"${foo:${bar\}:${baz}}}"#.to_string();
+        let refstr = r"${foo:${bar\}:${baz}}}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -474,12 +468,12 @@ mod test_parser_funcs {
                     ])
                 ])
             ))
-        )
+        );
     }
 
     #[test]
     fn test_parse_embedded_nested_ref() {
-        let refstr = r#"${foo:bar${bar}}"#.to_string();
+        let refstr = r"${foo:bar${bar}}".to_string();
 
         assert_eq!(
             parse_ref(&refstr),
@@ -495,7 +489,7 @@ mod test_parser_funcs {
 
     #[test]
     fn test_parse_embedded_nested_ref_escaped() {
-        let refstr = r#"${foo:bar\\${bar}}"#.to_string();
+        let refstr = r"${foo:bar\\${bar}}".to_string();
 
         assert_eq!(
             parse_ref(&refstr),
@@ -511,25 +505,25 @@ mod test_parser_funcs {
 
     #[test]
     fn test_parse_incomplete_ref_error() {
-        let refstr = r#"${foo:${bar}"#.to_string();
+        let refstr = r"${foo:${bar}".to_string();
 
         let res = parse_ref(&refstr);
-        println!("{:#?}", res);
+        println!("{res:#?}");
         assert!(res.is_err());
     }
 
     #[test]
     fn test_parse_incomplete_ref_error_2() {
-        let refstr = r#"${bar}${bar"#.to_string();
+        let refstr = r"${bar}${bar".to_string();
 
         let res = parse_ref(&refstr);
-        println!("{:#?}", res);
+        println!("{res:#?}");
         assert!(res.is_err());
     }
 
     #[test]
     fn test_parse_incomplete_ref_escaped() {
-        let refstr = r#"\${foo:${bar}"#.to_string();
+        let refstr = r"\${foo:${bar}".to_string();
 
         assert_eq!(
             parse_ref(&refstr),
@@ -545,17 +539,17 @@ mod test_parser_funcs {
 
     #[test]
     fn test_parse_incomplete_ref_double_escaped_error() {
-        let refstr = r#"\\${foo:${bar}"#.to_string();
-        println!("{}", refstr);
+        let refstr = r"\\${foo:${bar}".to_string();
+        println!("{refstr}");
 
         let res = parse_ref(&refstr);
-        println!("{:#?}", res);
+        println!("{res:#?}");
         assert!(res.is_err());
     }
 
     #[test]
     fn test_parse_unmatched_closing_brace() {
-        let refstr = r#"foo}bar"#.to_string();
+        let refstr = r"foo}bar".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok(("", Token::literal_from_str("foo}bar")))
@@ -573,7 +567,7 @@ mod test_parser_funcs {
         // Therefore the string `\\\${foo}` is parsed as a freestanding `\` followed by a
         // double-escaped reference resulting in `\\` followed by the contents of `${foo}` once
         // interpolated.
-        let refstr = r#"\\\${foo}"#.to_string();
+        let refstr = r"\\\${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -583,7 +577,7 @@ mod test_parser_funcs {
                     Token::Ref(vec![Token::literal_from_str("foo")])
                 ])
             ))
-        )
+        );
     }
 
     #[test]
@@ -597,7 +591,7 @@ mod test_parser_funcs {
         // Therefore the string `\\\\${foo}` is parsed as two freestanding `\` followed by a
         // double-escaped reference resulting in `\\\` followed by the contents of `${foo}` once
         // interpolated.
-        let refstr = r#"\\\\${foo}"#.to_string();
+        let refstr = r"\\\\${foo}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok((
@@ -607,7 +601,7 @@ mod test_parser_funcs {
                     Token::Ref(vec![Token::literal_from_str("foo")])
                 ])
             ))
-        )
+        );
     }
 
     #[test]
@@ -621,52 +615,52 @@ mod test_parser_funcs {
         // Therefore the string `${foo\\\}` is parsed as a reference to `foo\\`. The first `\` in
         // the reference is parsed as a freestanding `\` and the following `\\` is parsed as a
         // double-escaped reference closing symbol.
-        let refstr = r#"${foo\\\}"#.to_string();
+        let refstr = r"${foo\\\}".to_string();
         assert_eq!(
             parse_ref(&refstr),
             Ok(("", Token::Ref(vec![Token::literal_from_str(r"foo\\")])))
-        )
+        );
     }
 
     #[test]
     fn test_parse_inventory_query_escape() {
         // To ensure compatibility with Python reclass's reference parser, we parse `\$[` as `$[`
         // even though we don't support inventory queries yet.
-        let refstr = r#"\$['foo']['bar']"#;
+        let refstr = r"\$['foo']['bar']";
         assert_eq!(
-            parse_ref(&refstr),
+            parse_ref(refstr),
             Ok(("", Token::literal_from_str(r"$['foo']['bar']")))
-        )
+        );
     }
 
     #[test]
     fn test_parse_inventory_query_escaped_embedded() {
         // To ensure compatibility with Python reclass's reference parser, we parse `\$[` as `$[`
         // even though we don't support inventory queries yet.
-        let refstr = r#"foo: \$['foo']['bar']"#;
+        let refstr = r"foo: \$['foo']['bar']";
         assert_eq!(
-            parse_ref(&refstr),
+            parse_ref(refstr),
             Ok(("", Token::literal_from_str(r"foo: $['foo']['bar']")))
-        )
+        );
     }
 
     #[test]
     fn test_parse_inventory_query() {
         // Non-escaped inventory queries are also parsed as literals.
-        let refstr = r#"$[foo:bar]"#;
+        let refstr = r"$[foo:bar]";
         assert_eq!(
-            parse_ref(&refstr),
+            parse_ref(refstr),
             Ok(("", Token::literal_from_str(r"$[foo:bar]")))
-        )
+        );
     }
 
     #[test]
     fn test_parse_inventory_query_double_escape() {
         // Double-escaped inventory query is parsed as `\` followed by escaped inventory query.
-        let refstr = r#"\\$[foo:bar]"#;
+        let refstr = r"\\$[foo:bar]";
         assert_eq!(
-            parse_ref(&refstr),
+            parse_ref(refstr),
             Ok(("", Token::literal_from_str(r"\$[foo:bar]")))
-        )
+        );
     }
 }
@@ -63,10 +63,10 @@ fn test_resolve_combined_3() {
         Token::literal_from_str("foo"),
         Token::Ref(vec![Token::literal_from_str("foo")]),
     ]);
-    let params = r#"
+    let params = r"
     foo: \${bar}
     bar: baz
-    "#;
+    ";
     let params = Mapping::from_str(params).unwrap();
 
     let mut state = ResolveState::default();
@@ -109,7 +109,7 @@ fn test_token_parse_embedded_ref() {
 #[test]
 fn test_resolve() {
     let p = Mapping::from_str("foo: foo").unwrap();
-    let reftoken = parse_ref(&"${foo}").unwrap();
+    let reftoken = parse_ref("${foo}").unwrap();
 
     let mut state = ResolveState::default();
     assert_eq!(
@@ -121,7 +121,7 @@ fn test_resolve() {
 #[test]
 fn test_resolve_subkey() {
     let p = Mapping::from_str("foo: {foo: foo}").unwrap();
-    let reftoken = parse_ref(&"${foo:foo}").unwrap();
+    let reftoken = parse_ref("${foo:foo}").unwrap();
 
     let mut state = ResolveState::default();
     let v = reftoken.resolve(&p, &mut state).unwrap();
@@ -131,7 +131,7 @@ fn test_resolve_subkey() {
 #[test]
 fn test_resolve_nested() {
     let p = Mapping::from_str("{foo: foo, bar: {foo: foo}}").unwrap();
-    let reftoken = parse_ref(&"${bar:${foo}}").unwrap();
+    let reftoken = parse_ref("${bar:${foo}}").unwrap();
 
     let mut state = ResolveState::default();
     let v = reftoken.resolve(&p, &mut state).unwrap();
@@ -140,15 +140,15 @@ fn test_resolve_nested() {
 
 #[test]
 fn test_resolve_nested_subkey() {
-    let params = r#"
+    let params = r"
     foo:
         bar: foo
     bar:
-        foo: foo"#;
+        foo: foo";
     let p = Mapping::from_str(params).unwrap();
 
     // ${bar:${foo:bar}} == ${bar:foo} == foo
-    let reftoken = parse_ref(&"${bar:${foo:bar}}").unwrap();
+    let reftoken = parse_ref("${bar:${foo:bar}}").unwrap();
     let mut state = ResolveState::default();
     let v = reftoken.resolve(&p, &mut state).unwrap();
     assert_eq!(v, Value::Literal("foo".to_string()));
@@ -156,14 +156,14 @@ fn test_resolve_nested_subkey() {
 
 #[test]
 fn test_resolve_kapitan_secret_ref() {
-    let params = r#"
+    let params = r"
     baz:
         baz: baz
-    "#;
+    ";
 
     let p = Mapping::from_str(params).unwrap();
 
-    let reftoken = parse_ref(&"?{vaultkv:foo/bar/${baz:baz}/qux}").unwrap();
+    let reftoken = parse_ref("?{vaultkv:foo/bar/${baz:baz}/qux}").unwrap();
     dbg!(&reftoken);
     let mut state = ResolveState::default();
     let v = reftoken.resolve(&p, &mut state).unwrap();
@@ -172,11 +172,11 @@ fn test_resolve_kapitan_secret_ref() {
 
 #[test]
 fn test_resolve_escaped_ref() {
-    let params = r#"
+    let params = r"
     foo:
       label: '\${PROJECT_LABEL}'
     PROJECT_LABEL: {}
-    "#;
+    ";
     let p = Mapping::from_str(params).unwrap();
 
     let reftoken = parse_ref("\\${PROJECT_LABEL}").unwrap();
@@ -187,11 +187,11 @@ fn test_resolve_escaped_ref() {
 
 #[test]
 fn test_resolve_mapping_value() {
-    let p = r#"
+    let p = r"
     foo:
       bar: bar
       baz: baz
-    "#;
+    ";
     let p = Mapping::from_str(p).unwrap();
     let reftoken = parse_ref("${foo}").unwrap();
     let mut state = ResolveState::default();
@@ -204,11 +204,11 @@ fn test_resolve_mapping_value() {
 
 #[test]
 fn test_resolve_mapping_embedded() {
-    let p = r#"
+    let p = r"
     foo:
       bar: bar
       baz: baz
-    "#;
+    ";
     let p = Mapping::from_str(p).unwrap();
     let reftoken = parse_ref("foo: ${foo}").unwrap();
     let mut state = ResolveState::default();
@@ -224,9 +224,9 @@ fn test_resolve_mapping_embedded() {
 #[test]
 #[should_panic(expected = "Detected reference loop with reference paths [\"foo\"].")]
 fn test_resolve_recursive_error() {
-    let p = r#"
+    let p = r"
     foo: ${foo}
-    "#;
+    ";
     let p = Mapping::from_str(p).unwrap();
     let reftoken = parse_ref("${foo}").unwrap();
 
@@ -237,10 +237,10 @@ fn test_resolve_recursive_error() {
 #[test]
 #[should_panic(expected = "Detected reference loop with reference paths [\"bar\", \"foo\"].")]
 fn test_resolve_recursive_error_2() {
-    let p = r#"
+    let p = r"
     foo: ${bar}
     bar: ${foo}
-    "#;
+    ";
     let p = Mapping::from_str(p).unwrap();
     let reftoken = parse_ref("${foo}").unwrap();
 
@@ -251,14 +251,164 @@ fn test_resolve_recursive_error_2() {
 #[test]
 #[should_panic(expected = "Detected reference loop with reference paths [\"baz\", \"foo\"].")]
 fn test_resolve_nested_recursive_error() {
-    let p = r#"
+    let p = r"
     foo: ${baz}
     baz:
       qux: ${foo}
-    "#;
+    ";
     let p = Mapping::from_str(p).unwrap();
     let reftoken = parse_ref("${foo}").unwrap();
 
     let mut state = ResolveState::default();
     let _v = reftoken.resolve(&p, &mut state).unwrap();
 }
+
+#[test]
+fn test_resolve_sequence_index() {
+    let p = Mapping::from_str("{foo: [a, b, {bar: c}]}").unwrap();
+
+    let reftoken = parse_ref("${foo:1}").unwrap();
+    let mut state = ResolveState::default();
+    let v = reftoken.resolve(&p, &mut state).unwrap();
+    assert_eq!(v, Value::Literal("b".into()));
+
+    let reftoken = parse_ref("${foo:2:bar}").unwrap();
+    let mut state = ResolveState::default();
+    let v = reftoken.resolve(&p, &mut state).unwrap();
+    assert_eq!(v, Value::Literal("c".into()));
+}
+
+#[test]
+#[should_panic(
+    expected = "While looking up key '3' in reference '${foo:3}' for parameter '': \
+    Index 3 out of bounds for sequence foo of length 3"
+)]
+fn test_resolve_sequence_index_out_of_bounds() {
+    let p = Mapping::from_str("{foo: [a, b, c]}").unwrap();
+    let reftoken = parse_ref("${foo:3}").unwrap();
+    let mut state = ResolveState::default();
+    let _v = reftoken.resolve(&p, &mut state).unwrap();
+}
+
+#[test]
+#[should_panic(
+    expected = "While looking up key 'bar' in reference '${foo:bar}' for parameter '': \
+    Can't continue lookup, foo is a Value::Sequence and 'bar' isn't a valid sequence index"
+)]
+fn test_resolve_sequence_index_invalid() {
+    let p = Mapping::from_str("{foo: [a, b, c]}").unwrap();
+    let reftoken = parse_ref("${foo:bar}").unwrap();
+    let mut state = ResolveState::default();
+    let _v = reftoken.resolve(&p, &mut state).unwrap();
+}
+
+#[test]
+fn test_resolve_nested_sequence_index() {
+    let params = r"
+    list:
+      - bar
+      - foo
+    map:
+      foo: foo-value
+      bar: bar-value
+    ";
+    let p = Mapping::from_str(params).unwrap();
+
+    // ${map:${list:0}} == ${map:bar} == bar-value
+    let reftoken = parse_ref("${map:${list:0}}").unwrap();
+    let mut state = ResolveState::default();
+    let v = reftoken.resolve(&p, &mut state).unwrap();
+    assert_eq!(v, Value::Literal("bar-value".into()));
+
+    // ${map:${list:1}} == ${map:foo} == foo-value
+    let reftoken = parse_ref("${map:${list:1}}").unwrap();
+    let mut state = ResolveState::default();
+    let v = reftoken.resolve(&p, &mut state).unwrap();
+    assert_eq!(v, Value::Literal("foo-value".into()));
+}
+
+#[test]
+fn test_resolve_shell_style_defaults() {
+    let p = Mapping::from_str("{present: value, nested: {foo: bar}}").unwrap();
+    let opts = RenderOpts {
+        shell_style_defaults: true,
+        ..Default::default()
+    };
+    let resolve = |r: &str, opts: RenderOpts| {
+        let mut state = ResolveState::with_opts(opts);
+        parse_ref(r).unwrap().resolve(&p, &mut state)
+    };
+
+    assert_eq!(
+        resolve("${missing:-hello}", opts).unwrap(),
+        Value::Literal("hello".into())
+    );
+    assert_eq!(
+        resolve("${present:-hello}", opts).unwrap(),
+        Value::Literal("value".into())
+    );
+    assert_eq!(
+        resolve("${nested:missing:-hello}", opts).unwrap(),
+        Value::Literal("hello".into())
+    );
+    assert_eq!(
+        resolve("${nested:foo:-hello}", opts).unwrap(),
+        Value::Literal("bar".into())
+    );
+    assert_eq!(resolve("${missing:-}", opts).unwrap(), Value::Null);
+    // Default values are only used for missing keys
+    assert!(resolve("${present:foo:-hello}", opts).is_err());
+
+    // Default values aren't recognized by default
+    let err = resolve("${missing:-hello}", RenderOpts::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lookup error for reference '${missing:-hello}' in parameter '': key 'missing' not found"
+    );
+}
+
+#[test]
+fn test_resolve_wildcard_refs() {
+    let p = r"
+    foo:
+      a: 1
+      b: ${bar}
+      c: [x, y]
+    bar: bar
+    list: [1, 2]
+    ";
+    let p = Mapping::from_str(p).unwrap();
+    let opts = RenderOpts {
+        wildcard_refs: true,
+        ..Default::default()
+    };
+    let render = |r: &str, opts: RenderOpts| {
+        let mut state = ResolveState::with_opts(opts);
+        parse_ref(r).unwrap().render(&p, &mut state)
+    };
+
+    assert_eq!(
+        render("${foo:*}", opts).unwrap(),
+        Value::Sequence(vec![
+            1.into(),
+            Value::Literal("bar".into()),
+            Value::Sequence(vec![Value::Literal("x".into()), Value::Literal("y".into())]),
+        ])
+    );
+    assert_eq!(
+        render("${foo:*:1}", opts).unwrap(),
+        Value::Literal("bar".into())
+    );
+    assert_eq!(
+        render("${list:*}", opts).unwrap(),
+        Value::Sequence(vec![1.into(), 2.into()])
+    );
+    assert!(render("${bar:*}", opts).is_err());
+
+    // Wildcard segments aren't recognized by default
+    let err = render("${foo:*}", RenderOpts::default()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "lookup error for reference '${foo:*}' in parameter '': key '*' not found"
+    );
+}
@@ -2,20 +2,14 @@ use super::*;
 
 #[test]
 fn test_is_ref() {
-    assert_eq!(Token::Literal("foo".into()).is_ref(), false);
-    assert_eq!(
-        Token::Ref(vec![Token::Literal("foo".into())]).is_ref(),
-        true
-    );
+    assert!(!Token::Literal("foo".into()).is_ref());
+    assert!(Token::Ref(vec![Token::Literal("foo".into())]).is_ref());
 }
 
 #[test]
 fn test_is_literal() {
-    assert_eq!(Token::Literal("foo".into()).is_literal(), true);
-    assert_eq!(
-        Token::Ref(vec![Token::Literal("foo".into())]).is_literal(),
-        false
-    );
+    assert!(Token::Literal("foo".into()).is_literal());
+    assert!(!Token::Ref(vec![Token::Literal("foo".into())]).is_literal());
 }
 
 #[test]
@@ -86,3 +80,32 @@ fn test_format_double_escaped() {
         r"${foo}\\${bar}".to_owned(),
     );
 }
+
+#[test]
+fn test_references_simple() {
+    let t = Token::parse("${foo:bar}").unwrap().unwrap();
+    assert_eq!(t.references(), vec!["foo:bar".to_owned()]);
+}
+
+#[test]
+fn test_references_combined() {
+    let t = Token::parse("a-${foo}-${bar:baz}-${foo}").unwrap().unwrap();
+    assert_eq!(t.references(), vec!["foo".to_owned(), "bar:baz".to_owned()]);
+}
+
+#[test]
+fn test_references_nested() {
+    let t = Token::Ref(vec![
+        Token::literal_from_str("foo:"),
+        Token::Ref(vec![Token::literal_from_str("bar")]),
+    ]);
+    assert_eq!(
+        t.references(),
+        vec!["foo:${bar}".to_owned(), "bar".to_owned()]
+    );
+}
+
+#[test]
+fn test_references_literal() {
+    assert!(Token::literal_from_str("foo").references().is_empty());
+}
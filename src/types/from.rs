@@ -1,3 +1,4 @@
+use super::value::TEMPLATE_TAG;
 use super::{Mapping, Value};
 use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
@@ -22,7 +23,10 @@ impl From<serde_yaml::Value> for Value {
     ///
     /// `serde_yaml::Value::String` is always converted into `Value::String`.
     ///
-    /// `serde_yaml::Tagged` values are not supported yet.
+    /// `serde_yaml::Tagged` values with tag `!template` are converted into `Value::Template`.
+    /// Non-string template values are stringified, e.g. `!template 42` becomes template `42` and
+    /// a sequence or mapping becomes its YAML representation. Other tagged values are not
+    /// supported yet.
     fn from(v: serde_yaml::Value) -> Self {
         match v {
             serde_yaml::Value::Null => Self::Null,
@@ -37,7 +41,19 @@ impl From<serde_yaml::Value> for Value {
                 Self::Sequence(seq)
             }
             serde_yaml::Value::Mapping(m) => Self::Mapping(Mapping::from(m)),
-            serde_yaml::Value::Tagged(_) => {
+            serde_yaml::Value::Tagged(t) => {
+                if t.tag == TEMPLATE_TAG {
+                    return Self::Template(match t.value {
+                        serde_yaml::Value::String(s) => s,
+                        serde_yaml::Value::Null => String::new(),
+                        serde_yaml::Value::Bool(b) => b.to_string(),
+                        serde_yaml::Value::Number(n) => n.to_string(),
+                        v => serde_yaml::to_string(&v)
+                            .unwrap_or_default()
+                            .trim_end()
+                            .to_string(),
+                    });
+                }
                 todo!("Tagged YAML values are not supported yet");
             }
         }
@@ -49,6 +65,9 @@ impl From<Value> for serde_yaml::Value {
     ///
     /// `Value::String` and `Value::Literal` are both converted to `serde_yaml::Value::String`.
     ///
+    /// `Value::Template` is converted to a `serde_yaml::Value::Tagged` string with tag
+    /// `!template`.
+    ///
     /// `Value::ValueList` is converted to `serde_yaml::Value::Sequence`.
     fn from(v: Value) -> Self {
         match v {
@@ -56,6 +75,10 @@ impl From<Value> for serde_yaml::Value {
             Value::Bool(b) => Self::Bool(b),
             Value::Number(n) => Self::Number(n),
             Value::Literal(s) | Value::String(s) => Self::String(s),
+            Value::Template(s) => Self::Tagged(Box::new(serde_yaml::value::TaggedValue {
+                tag: serde_yaml::value::Tag::new(TEMPLATE_TAG),
+                value: Self::String(s),
+            })),
             Value::Sequence(s) | Value::ValueList(s) => {
                 let mut seq: Vec<serde_yaml::Value> = Vec::with_capacity(s.len());
                 for v in s {
@@ -10,7 +10,7 @@ use std::hash::{Hash, Hasher};
 
 use super::value::Value;
 use super::KeyPrefix;
-use crate::refs::ResolveState;
+use crate::refs::{RenderOpts, ResolveState};
 
 /// Represents a YAML mapping in a form suitable to manage Reclass parameters.
 ///
@@ -27,8 +27,12 @@ use crate::refs::ResolveState;
 /// as constant. Keys are marked constant by prefixing them with the constant prefix marker
 /// `KeyPrefix::Constant`.
 ///
-/// Finally, Keys can be marked as overriding. This will cause `insert()` to drop any existing
-/// value for the key instead of tracking the old values as a `Value::ValueList`.
+/// Keys can be marked as overriding. This will cause `insert()` to drop any existing value for
+/// the key instead of tracking the old values as a `Value::ValueList`.
+///
+/// Finally, keys can be marked as appending. This will cause `insert()` to append a Sequence
+/// value to an existing Sequence value for the key in-place instead of tracking the old values as
+/// a `Value::ValueList`.
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
 pub struct Mapping {
     /// Holds the mapping data.
@@ -41,6 +45,11 @@ pub struct Mapping {
     /// the next call to [`Mapping::merge()`] where the contents of this map are merged into
     /// another map, i.e. a call to `merge()` where this map is `other`.
     override_keys: HashSet<Value>,
+    /// Holds the set of keys in the mapping which were marked as append, but for which no
+    /// previous Sequence was extended during insertion. Similar to `override_keys`, we process
+    /// appends for such keys during the next call to [`Mapping::merge()`] where this map is
+    /// `other`.
+    append_keys: HashSet<Value>,
 }
 
 impl std::fmt::Display for Mapping {
@@ -72,6 +81,7 @@ impl Mapping {
             map: IndexMap::with_capacity(capacity),
             const_keys: HashSet::default(),
             override_keys: HashSet::default(),
+            append_keys: HashSet::default(),
         }
     }
 
@@ -91,6 +101,7 @@ impl Mapping {
         self.map.shrink_to_fit();
         self.const_keys.shrink_to_fit();
         self.override_keys.shrink_to_fit();
+        self.append_keys.shrink_to_fit();
     }
 
     /// Removes all data from the mapping.
@@ -99,6 +110,7 @@ impl Mapping {
         self.map.clear();
         self.const_keys.clear();
         self.override_keys.clear();
+        self.append_keys.clear();
     }
 
     /// Inserts key-value pair in the mapping.
@@ -121,12 +133,17 @@ impl Mapping {
     /// If the key is marked as overriding, the existing value is replaced with the new value, and
     /// the old Value is returned.
     ///
+    /// If the key is marked as appending (with prefix `KeyPrefix::Append`) and both the existing
+    /// and the new value are Sequences, the elements of the new value are appended to the existing
+    /// Sequence in-place. Otherwise, the new value is added to a `Value::ValueList` as described
+    /// above.
+    ///
     /// Note that keys can't be marked constant and overriding. If a key has both markers, the
     /// marker which is the first character of the key will be processed, and the other marker will
     /// be treated as part of the actual key.
     #[inline]
     pub fn insert(&mut self, k: Value, v: Value) -> Result<Option<Value>> {
-        self.insert_impl(k, v, false, false)
+        self.insert_impl(k, v, false, false, false)
     }
 
     /// Inserts key-value pair in the mapping.
@@ -134,8 +151,8 @@ impl Mapping {
     /// See [`Mapping::insert()`] for the full semantics of insertion.
     ///
     /// In contrast to `Mapping::insert()` this method allows callers to force `k` to become
-    /// constant or be marked as overriding through the `force_const` and `force_override` flags
-    /// respectively.
+    /// constant or be marked as overriding or appending through the `force_const`,
+    /// `force_override` and `force_append` flags respectively.
     #[inline]
     fn insert_impl(
         &mut self,
@@ -143,6 +160,7 @@ impl Mapping {
         v: Value,
         force_const: bool,
         force_override: bool,
+        force_append: bool,
     ) -> Result<Option<Value>> {
         let (k, p) = k.strip_prefix();
         if !self.map.contains_key(&k) {
@@ -157,14 +175,22 @@ impl Mapping {
                     // override in this map.
                     self.override_keys.insert(k.clone());
                 }
+                Some(KeyPrefix::Append) => {
+                    // remember that `k` was marked as appending if we don't have a Sequence to
+                    // append to in this map.
+                    self.append_keys.insert(k.clone());
+                }
                 None => {}
-            };
+            }
             if force_const {
                 self.const_keys.insert(k.clone());
             }
             if force_override {
                 self.override_keys.insert(k.clone());
             }
+            if force_append {
+                self.append_keys.insert(k.clone());
+            }
             Ok(self.map.insert(k, v))
         } else if self.const_keys.contains(&k) {
             // k is marked constant and already set in the map, return error
@@ -181,6 +207,17 @@ impl Mapping {
                 // NOTE(sg): If we immediately process the override here, we don't need to update
                 // `override_keys`.
                 res = self.map.insert(k.clone(), v);
+            } else if (force_append || matches!(p, Some(KeyPrefix::Append)))
+                && v.is_sequence()
+                && self.map.get(&k).unwrap().is_sequence()
+            {
+                // Append the elements of the new Sequence to the existing Sequence for `k`.
+                // If we immediately process the append here, we don't need to update
+                // `append_keys`.
+                if let (Some(Value::Sequence(s)), Value::Sequence(new)) = (self.map.get_mut(&k), v)
+                {
+                    s.extend(new);
+                }
             } else {
                 // Append the new value to the ValueList for k
 
@@ -230,7 +267,7 @@ impl Mapping {
     /// insertion. Iterator element type is `(&'a Value, &'a Value)`.
     #[inline]
     #[must_use]
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             iter: self.map.iter(),
         }
@@ -257,6 +294,28 @@ impl Mapping {
         self.map.get(k)
     }
 
+    /// Returns a reference to the value at the provided `:`-separated path, e.g. `cluster:name`.
+    ///
+    /// Path segments which are used to index into a Sequence or ValueList are interpreted as
+    /// zero-based indices. Returns None if any segment of the path doesn't exist.
+    #[must_use]
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        let mut segments = path.split(':');
+        // `split()` always yields at least one element, an empty path results in a lookup of key
+        // `""`.
+        let mut v = self.get(&segments.next()?.into())?;
+        for key in segments {
+            let k = if v.is_sequence() || v.is_value_list() {
+                key.parse::<usize>()
+                    .map_or_else(|_| key.into(), Value::from)
+            } else {
+                key.into()
+            };
+            v = v.get(&k)?;
+        }
+        Some(v)
+    }
+
     /// Returns a mutable reference to the value for key `k` if the key is present in the mapping.
     /// Returns an error if called for a key which is marked constant.
     #[inline]
@@ -270,7 +329,7 @@ impl Mapping {
     /// Returns the given key's entry in the map for insertion and/or in-place updates.
     /// Returns an error if called for a key which is marked constant.
     #[inline]
-    pub fn entry(&mut self, k: Value) -> Result<indexmap::map::Entry<Value, Value>> {
+    pub fn entry(&mut self, k: Value) -> Result<indexmap::map::Entry<'_, Value, Value>> {
         if self.const_keys.contains(&k) {
             return Err(anyhow!("Key {k} is marked constant"));
         }
@@ -356,31 +415,222 @@ impl Mapping {
 
     /// Converts the `Mapping` into a `PyDict`.
     pub fn as_py_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        self.as_py_dict_impl(py, false)
+    }
+
+    /// Converts the `Mapping` into a `PyDict`. See [`Value::as_py_obj_debug()`] for the meaning
+    /// of `debug`.
+    pub(super) fn as_py_dict_impl<'py>(
+        &self,
+        py: Python<'py>,
+        debug: bool,
+    ) -> PyResult<Bound<'py, PyDict>> {
         let dict = PyDict::new(py);
 
         for (k, v) in self {
             let pyk = k.as_py_obj(py)?;
-            let pyv = v.as_py_obj(py)?;
+            let pyv = v.as_py_obj_impl(py, debug)?;
             dict.set_item(pyk, pyv)?;
         }
 
         Ok(dict.into_pyobject(py)?)
     }
 
+    /// Computes the structured difference between this mapping and `other`.
+    ///
+    /// Nested mappings which are present in both mappings are compared recursively. Keys in the
+    /// resulting `MappingDiff` are `:`-separated paths, e.g. `cluster:name`. Values whose type
+    /// changes (e.g. from a scalar to a mapping) are reported as changed without recursing into
+    /// them.
+    #[must_use]
+    pub fn diff(&self, other: &Self) -> MappingDiff {
+        let mut diff = MappingDiff::default();
+        self.diff_impl(other, &mut vec![], &mut diff);
+        diff
+    }
+
+    fn diff_impl(&self, other: &Self, path: &mut Vec<String>, diff: &mut MappingDiff) {
+        fn segment(k: &Value) -> String {
+            k.as_str()
+                .map_or_else(|| k.to_string(), ToString::to_string)
+        }
+        for (k, v) in self {
+            path.push(segment(k));
+            match (v, other.get(k)) {
+                (_, None) => diff.removed.push((path.join(":"), v.clone())),
+                (Value::Mapping(m), Some(Value::Mapping(o))) => m.diff_impl(o, path, diff),
+                (_, Some(o)) => {
+                    if v != o {
+                        diff.changed.push((path.join(":"), v.clone(), o.clone()));
+                    }
+                }
+            }
+            path.pop();
+        }
+        for (k, v) in other {
+            if !self.contains_key(k) {
+                path.push(segment(k));
+                diff.added.push((path.join(":"), v.clone()));
+                path.pop();
+            }
+        }
+    }
+
+    /// Returns the `:`-separated paths of all keys in this Mapping (including nested Mappings)
+    /// which are marked as overriding, but which don't exist in Mapping `base`.
+    ///
+    /// `base` is expected to be a merged, but not yet rendered Mapping. Keys for which `base`
+    /// holds unresolved references along the path are never reported, since we can't know whether
+    /// those references resolve to a value which would be overridden.
+    pub(crate) fn dangling_overrides(&self, base: &Self) -> Vec<String> {
+        let mut res = vec![];
+        self.dangling_overrides_impl(
+            &|k| Some(base.get(k).into_iter().collect()),
+            &mut vec![],
+            &mut res,
+        );
+        res
+    }
+
+    /// `lookup` returns all values for the provided key in the base layers at the current path,
+    /// or `None` if the base layers contain unresolved references.
+    fn dangling_overrides_impl<'a>(
+        &self,
+        lookup: &dyn Fn(&Value) -> Option<Vec<&'a Value>>,
+        path: &mut Vec<String>,
+        res: &mut Vec<String>,
+    ) {
+        for (k, v) in self {
+            let Some(values) = lookup(k) else {
+                continue;
+            };
+            path.push(
+                k.as_str()
+                    .map_or_else(|| k.to_string(), ToString::to_string),
+            );
+            if self.is_override(k) && values.is_empty() {
+                res.push(path.join(":"));
+            } else if let Value::Mapping(m) = v {
+                m.dangling_overrides_impl(&|k| layer_values(&values, k), path, res);
+            }
+            path.pop();
+        }
+    }
+
+    /// Returns a content hash of the Mapping which is suitable as a cache key. The hash doesn't
+    /// depend on the order of the Mapping's keys.
+    ///
+    /// See [`Value::content_hash()`] for details.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        // Hash the kv pairs in a way that is not sensitive to their order, cf. `impl Hash for
+        // Mapping`.
+        let mut xor = 0;
+        for (k, v) in self {
+            let mut hasher = DefaultHasher::new();
+            k.hash_content(&mut hasher);
+            v.hash_content(&mut hasher);
+            xor ^= hasher.finish();
+        }
+        xor
+    }
+
     /// Checks if the provided key is marked as constant.
     #[inline]
     #[must_use]
-    fn is_const(&self, k: &Value) -> bool {
+    pub fn is_const(&self, k: &Value) -> bool {
         self.const_keys.contains(k)
     }
 
     /// Checks if the provided key is marked as overriding.
+    ///
+    /// Note that override markers are consumed when the Mapping is merged into another Mapping.
     #[inline]
     #[must_use]
-    fn is_override(&self, k: &Value) -> bool {
+    pub fn is_override(&self, k: &Value) -> bool {
         self.override_keys.contains(k)
     }
 
+    /// Checks if the provided key is marked as appending.
+    ///
+    /// Note that append markers are consumed when the Mapping is merged into another Mapping.
+    #[inline]
+    #[must_use]
+    pub fn is_append(&self, k: &Value) -> bool {
+        self.append_keys.contains(k)
+    }
+
+    /// Returns the keys of the Mapping which are marked as constant, in insertion order.
+    #[must_use]
+    pub fn constant_keys(&self) -> Vec<&Value> {
+        self.map.keys().filter(|k| self.is_const(k)).collect()
+    }
+
+    /// Converts the Mapping into a `serde_yaml::Mapping`, re-adding the constant (`=`), override
+    /// (`~`) and append (`+`) prefixes to keys which are marked as constant, overriding or
+    /// appending.
+    ///
+    /// In contrast to the `From<Mapping>` implementation for `serde_yaml::Mapping`, the result can
+    /// be parsed back into an equivalent `Mapping`. Nested Mappings, including Mappings in
+    /// Sequences, are converted recursively. Only string keys can carry a prefix, key markers
+    /// for other key types are dropped.
+    #[must_use]
+    pub fn to_yaml_with_prefixes(&self) -> serde_yaml::Mapping {
+        let mut new = serde_yaml::Mapping::with_capacity(self.map.len());
+        for (k, v) in self {
+            let prefix = if self.is_const(k) {
+                Some(KeyPrefix::Constant)
+            } else if self.is_override(k) {
+                Some(KeyPrefix::Override)
+            } else if self.is_append(k) {
+                Some(KeyPrefix::Append)
+            } else {
+                None
+            };
+            let k = match (k, prefix) {
+                (Value::String(s) | Value::Literal(s), Some(p)) => {
+                    serde_yaml::Value::String(format!("{p}{s}"))
+                }
+                _ => serde_yaml::Value::from(k.clone()),
+            };
+            new.insert(k, yaml_with_prefixes(v));
+        }
+        new
+    }
+
+    /// Returns the `:`-separated paths of all keys in this Mapping (including nested Mappings)
+    /// which are marked as constant.
+    pub(crate) fn constant_paths(&self) -> Vec<String> {
+        let mut res = vec![];
+        self.constant_paths_impl(&mut vec![], &mut res);
+        res
+    }
+
+    fn constant_paths_impl(&self, path: &mut Vec<String>, res: &mut Vec<String>) {
+        for (k, v) in self {
+            path.push(
+                k.as_str()
+                    .map_or_else(|| k.to_string(), ToString::to_string),
+            );
+            if self.is_const(k) {
+                res.push(path.join(":"));
+            }
+            if let Value::Mapping(m) = v {
+                m.constant_paths_impl(path, res);
+            }
+            path.pop();
+        }
+    }
+
+    /// Marks the provided key as overriding, if it's present in the map. The override is
+    /// processed when this map is merged into another map.
+    #[inline]
+    pub(crate) fn mark_override(&mut self, k: &Value) {
+        if self.map.contains_key(k) {
+            self.override_keys.insert(k.clone());
+        }
+    }
+
     /// Merges Mapping `other` into this mapping.
     ///
     /// The function parses each key present in `other`
@@ -388,15 +638,242 @@ impl Mapping {
     /// This function will update the current map's constant key set with any keys that are marked
     /// as constant in the `other` map.
     pub fn merge(&mut self, other: &Self) -> Result<()> {
+        self.merge_with_limit(other, None)
+    }
+
+    /// Merges Mapping `other` into this mapping.
+    ///
+    /// See [`Mapping::merge()`] for the full semantics of merging.
+    ///
+    /// If `max_value_list_len` is `Some`, the method returns an error if merging `other` results
+    /// in a `Value::ValueList` which has more elements than the provided limit.
+    pub(crate) fn merge_with_limit(
+        &mut self,
+        other: &Self,
+        max_value_list_len: Option<usize>,
+    ) -> Result<()> {
         for (k, v) in other {
-            // ValueList merging is implemented in insert_impl
-            self.insert_impl(
+            self.merge_key(other, k, v, max_value_list_len)?;
+        }
+        Ok(())
+    }
+
+    /// Merges Mapping `other` into this mapping and returns a report of how each key of `other`
+    /// changed this mapping.
+    ///
+    /// See [`Mapping::merge()`] for the full semantics of merging. The report holds one
+    /// [`KeyChange`] per key in `other`, in the order in which the keys were merged. Like
+    /// [`Mapping::merge()`], this method doesn't limit the length of merged ValueLists.
+    pub fn merge_with_report(&mut self, other: &Self) -> Result<Vec<KeyChange>> {
+        let mut report = Vec::with_capacity(other.len());
+        for (k, v) in other {
+            let kind = self.merge_key(other, k, v, None)?;
+            report.push(KeyChange {
+                key: k.clone(),
+                kind,
+            });
+        }
+        Ok(report)
+    }
+
+    /// Merges key `k` with value `v` of Mapping `other` into this mapping and returns how the key
+    /// changed this mapping.
+    ///
+    /// If `max_value_list_len` is `Some`, the method returns an error if the merge results in a
+    /// `Value::ValueList` which has more elements than the provided limit.
+    fn merge_key(
+        &mut self,
+        other: &Self,
+        k: &Value,
+        v: &Value,
+        max_value_list_len: Option<usize>,
+    ) -> Result<KeyChangeKind> {
+        // Keys in `other` are already stripped of any prefixes.
+        let existed = self.map.contains_key(k);
+        // ValueList merging is implemented in insert_impl
+        let old = self.insert_impl(
+            k.clone(),
+            v.clone(),
+            other.is_const(k),
+            other.is_override(k),
+            other.is_append(k),
+        )?;
+        if let Some(limit) = max_value_list_len {
+            if let Some(Value::ValueList(l)) = self.map.get(k) {
+                if l.len() > limit {
+                    return Err(anyhow!(
+                        "ValueList for key {k} exceeds the configured maximum length of \
+                        {limit} after merging"
+                    ));
+                }
+            }
+        }
+        Ok(if !existed {
+            KeyChangeKind::Inserted
+        } else if other.is_override(k) {
+            KeyChangeKind::Overridden(Box::new(old.unwrap_or(Value::Null)))
+        } else {
+            KeyChangeKind::Appended
+        })
+    }
+
+    /// Merges Mapping `other` into this mapping, treating keys of the form `path:N` in `other` as
+    /// overrides for element `N` of the Sequence at `:`-separated `path` in this mapping.
+    ///
+    /// Keys of the form `path:N` can appear at any level of `other`, in which case `path` is
+    /// relative to the nested Mapping which holds the key. All other keys are merged with
+    /// [`Mapping::merge_with_limit()`]. Element overrides are applied after that. If the Sequence
+    /// at `path` is a ValueList of Sequences, the layers are concatenated before applying the
+    /// override, since indices refer to the merged Sequence. If `unique_sequences` is true, the
+    /// layers are merged by only appending elements which aren't present yet after applying the
+    /// override, matching config option `unique_sequence_merge`.
+    ///
+    /// Returns an error if `path` doesn't refer to an existing Sequence, or if `N` is out of
+    /// bounds for that Sequence.
+    pub(crate) fn merge_with_index_keys(
+        &mut self,
+        other: &Self,
+        max_value_list_len: Option<usize>,
+        unique_sequences: bool,
+    ) -> Result<()> {
+        let mut overrides = vec![];
+        let rest = other.split_index_keys("", &mut overrides)?;
+        self.merge_with_limit(&rest, max_value_list_len)?;
+        for (path, idx, v) in overrides {
+            self.set_sequence_element(&path, idx, v, unique_sequences)
+                .map_err(|e| anyhow!("Can't override sequence element '{path}:{idx}': {e}"))?;
+        }
+        Ok(())
+    }
+
+    /// Returns a copy of this Mapping without any keys of the form `path:N`, including such keys
+    /// in nested Mappings. The removed keys are added to `overrides` as `(path, N, value)` with
+    /// `path` prefixed with `prefix`.
+    fn split_index_keys(
+        &self,
+        prefix: &str,
+        overrides: &mut Vec<(String, usize, Value)>,
+    ) -> Result<Self> {
+        let mut res = Self::new();
+        for (k, v) in self {
+            if let Some((path, idx)) = k.as_str().and_then(parse_index_key) {
+                overrides.push((format!("{prefix}{path}"), idx, v.clone()));
+                continue;
+            }
+            let v = match (v, k.as_str()) {
+                (Value::Mapping(m), Some(key)) => {
+                    Value::Mapping(m.split_index_keys(&format!("{prefix}{key}:"), overrides)?)
+                }
+                _ => v.clone(),
+            };
+            res.insert_impl(
                 k.clone(),
-                v.clone(),
-                other.is_const(k),
-                other.is_override(k),
+                v,
+                self.is_const(k),
+                self.is_override(k),
+                self.is_append(k),
             )?;
         }
+        Ok(res)
+    }
+    /// Eagerly deep-merges Mapping `other` into this mapping.
+    ///
+    /// For keys which hold a Mapping in both mappings, the nested mappings are merged
+    /// recursively. All other values in `other` overwrite the values in this mapping.
+    ///
+    /// In contrast to [`Mapping::merge()`], this method never builds `Value::ValueList` layers
+    /// and ignores constant and override keys. It's intended for manipulating already rendered
+    /// (flattened) Mappings.
+    pub fn deep_merge(&mut self, other: &Self) {
+        for (k, v) in other {
+            match (self.map.get_mut(k), v) {
+                (Some(Value::Mapping(m)), Value::Mapping(o)) => m.deep_merge(o),
+                _ => {
+                    self.map.insert(k.clone(), v.clone());
+                }
+            }
+        }
+    }
+
+    /// Replaces element `idx` of the Sequence at `:`-separated `path` with `v`.
+    ///
+    /// Intermediate ValueLists whose layers are all Mappings are merged into a single Mapping, so
+    /// that `path` can be followed. See [`Mapping::merge_with_index_keys()`] for the meaning of
+    /// `unique_sequences`.
+    fn set_sequence_element(
+        &mut self,
+        path: &str,
+        idx: usize,
+        v: Value,
+        unique_sequences: bool,
+    ) -> Result<()> {
+        let mut segments = path.split(':').collect::<Vec<_>>();
+        // `split()` always yields at least one element
+        let last = segments.pop().unwrap_or_default();
+        let mut m = self;
+        for seg in segments {
+            let next = m
+                .get_mut(&seg.into())?
+                .ok_or_else(|| anyhow!("key '{seg}' doesn't exist"))?;
+            if let Value::ValueList(layers) = next {
+                if layers.iter().all(Value::is_mapping) {
+                    let mut merged = Mapping::new();
+                    for l in layers.drain(..) {
+                        if let Value::Mapping(l) = l {
+                            merged.merge(&l)?;
+                        }
+                    }
+                    *next = Value::Mapping(merged);
+                }
+            }
+            m = match next {
+                Value::Mapping(n) => n,
+                o => return Err(anyhow!("'{seg}' is a {}, not a Mapping", o.variant())),
+            };
+        }
+        let target = m
+            .get_mut(&last.into())?
+            .ok_or_else(|| anyhow!("key '{last}' doesn't exist"))?;
+        // Lengths of the concatenated sequence layers, if `target` is a ValueList of Sequences
+        let mut layer_lens = vec![];
+        if let Value::ValueList(layers) = target {
+            if layers.iter().all(Value::is_sequence) {
+                let merged = layers
+                    .drain(..)
+                    .flat_map(|l| match l {
+                        Value::Sequence(s) => {
+                            layer_lens.push(s.len());
+                            s
+                        }
+                        _ => unreachable!("all layers are sequences"),
+                    })
+                    .collect();
+                *target = Value::Sequence(merged);
+            }
+        }
+        let Value::Sequence(s) = target else {
+            return Err(anyhow!(
+                "'{last}' is a {}, not a Sequence",
+                target.variant()
+            ));
+        };
+        let len = s.len();
+        let e = s
+            .get_mut(idx)
+            .ok_or_else(|| anyhow!("index {idx} out of bounds for sequence of length {len}"))?;
+        *e = v;
+        if unique_sequences && layer_lens.len() > 1 {
+            // Merge the layers again, skipping elements of later layers which are already present
+            let mut elems = std::mem::take(s).into_iter();
+            s.extend(elems.by_ref().take(layer_lens[0]));
+            for n in &layer_lens[1..] {
+                for e in elems.by_ref().take(*n) {
+                    if !s.contains(&e) {
+                        s.push(e);
+                    }
+                }
+            }
+        }
         Ok(())
     }
 
@@ -405,15 +882,18 @@ impl Mapping {
     ///
     /// Used in `Value::flattened()` to preserve const and override key information when flattening
     /// Mapping values.
-    pub(super) fn flattened(&self) -> Result<Self> {
+    ///
+    /// See [`Value::flattened_with()`] for the meaning of `opts`.
+    pub(super) fn flattened(&self, opts: &RenderOpts) -> Result<Self> {
         let mut res = Self::new();
         for (k, v) in self {
             // Propagate key properties to the resulting mapping by using `insert_impl()`.
             res.insert_impl(
                 k.clone(),
-                v.flattened()?,
+                v.flattened_with(opts)?,
                 self.is_const(k),
                 self.is_override(k),
+                self.is_append(k),
             )?;
         }
         Ok(res)
@@ -425,6 +905,10 @@ impl Mapping {
     /// The method looks up reference values in parameter `root`. After interpolation of each
     /// Mapping key-value pair, the resulting value is flattened before it's inserted in the new
     /// Mapping. Mapping keys are inserted into the new mapping unchanged.
+    ///
+    /// Key-value pairs are interpolated in the Mapping's insertion order. This ensures that the
+    /// first error which is encountered, and therefore the error which is returned, is the same
+    /// for repeated renders of the same inputs.
     pub(super) fn interpolate(&self, root: &Self, state: &mut ResolveState) -> Result<Self> {
         let mut res = Self::new();
         for (k, v) in self {
@@ -438,7 +922,13 @@ impl Mapping {
             let mut v = v.interpolate(root, &mut st)?;
             v.flatten()?;
             // Propagate key properties to the resulting mapping by using `insert_impl()`.
-            res.insert_impl(k.clone(), v, self.is_const(k), self.is_override(k))?;
+            res.insert_impl(
+                k.clone(),
+                v,
+                self.is_const(k),
+                self.is_override(k),
+                self.is_append(k),
+            )?;
         }
         Ok(res)
     }
@@ -460,7 +950,8 @@ impl From<serde_yaml::Mapping> for Mapping {
 impl From<Mapping> for serde_yaml::Mapping {
     /// Converts a `Mapping` into a `serde_yaml::Mapping`.
     ///
-    /// Note that information about constant keys is lost here.
+    /// Note that information about constant keys is lost here. Use
+    /// [`Mapping::to_yaml_with_prefixes()`] to preserve it.
     fn from(m: Mapping) -> Self {
         let mut new = Self::with_capacity(m.map.len());
         for (k, v) in m.map {
@@ -526,6 +1017,129 @@ impl FromIterator<(Value, Value)> for Mapping {
     }
 }
 
+/// Converts `v` into a `serde_yaml::Value`, using [`Mapping::to_yaml_with_prefixes()`] for any
+/// Mappings.
+fn yaml_with_prefixes(v: &Value) -> serde_yaml::Value {
+    match v {
+        Value::Mapping(m) => serde_yaml::Value::Mapping(m.to_yaml_with_prefixes()),
+        Value::Sequence(s) | Value::ValueList(s) => {
+            serde_yaml::Value::Sequence(s.iter().map(yaml_with_prefixes).collect())
+        }
+        _ => serde_yaml::Value::from(v.clone()),
+    }
+}
+
+/// Returns the values for key `k` in all Mapping layers of `bases`, or `None` if any of the layers
+/// is an unresolved reference.
+fn layer_values<'a>(bases: &[&'a Value], k: &Value) -> Option<Vec<&'a Value>> {
+    let mut values = vec![];
+    for base in bases {
+        match base {
+            Value::Mapping(m) => values.extend(m.get(k)),
+            Value::ValueList(l) => values.extend(layer_values(&l.iter().collect::<Vec<_>>(), k)?),
+            Value::String(_) => return None,
+            _ => {}
+        }
+    }
+    Some(values)
+}
+
+/// Splits a key of the form `path:N` into `path` and index `N`.
+fn parse_index_key(k: &str) -> Option<(&str, usize)> {
+    let (path, idx) = k.rsplit_once(':')?;
+    if path.is_empty() {
+        return None;
+    }
+    Some((path, idx.parse().ok()?))
+}
+
+/// How a single key was changed by [`Mapping::merge_with_report()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyChangeKind {
+    /// The key wasn't present in the mapping and was inserted
+    Inserted,
+    /// The value was appended to the existing value, either as a new layer of a
+    /// `Value::ValueList` or in-place for keys marked as appending
+    Appended,
+    /// The key was marked as overriding and replaced the contained old value
+    Overridden(Box<Value>),
+}
+
+/// Record of a single key merged by [`Mapping::merge_with_report()`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyChange {
+    /// The merged key
+    pub key: Value,
+    /// How the key was merged
+    pub kind: KeyChangeKind,
+}
+
+/// Structured difference between two Mappings, as computed by [`Mapping::diff()`].
+///
+/// Keys are stored as `:`-separated paths into the compared mappings.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MappingDiff {
+    /// Keys which are only present in the other mapping, together with their values
+    pub added: Vec<(String, Value)>,
+    /// Keys which are only present in the original mapping, together with their values
+    pub removed: Vec<(String, Value)>,
+    /// Keys which are present in both mappings with different values, together with the old and
+    /// new value
+    pub changed: Vec<(String, Value, Value)>,
+}
+
+impl MappingDiff {
+    /// Returns true if the compared mappings are identical.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Converts the `MappingDiff` into a Python dict with keys `added`, `removed` and `changed`.
+    pub fn as_py_obj<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        Value::from(self.clone()).as_py_obj(py)
+    }
+}
+
+impl From<MappingDiff> for Value {
+    /// Converts a `MappingDiff` into a `Value::Mapping` with keys `added`, `removed` and
+    /// `changed`.
+    ///
+    /// Keys `added` and `removed` map each path to its value. Key `changed` maps each path to a
+    /// Mapping with keys `old` and `new`.
+    fn from(d: MappingDiff) -> Self {
+        let added = d
+            .added
+            .into_iter()
+            .map(|(k, v)| (k.into(), v))
+            .collect::<Mapping>();
+        let removed = d
+            .removed
+            .into_iter()
+            .map(|(k, v)| (k.into(), v))
+            .collect::<Mapping>();
+        let changed = d
+            .changed
+            .into_iter()
+            .map(|(k, old, new)| {
+                let change = [("old".into(), old), ("new".into(), new)]
+                    .into_iter()
+                    .collect::<Mapping>();
+                (k.into(), change.into())
+            })
+            .collect::<Mapping>();
+        Value::Mapping(
+            [
+                ("added".into(), added.into()),
+                ("removed".into(), removed.into()),
+                ("changed".into(), changed.into()),
+            ]
+            .into_iter()
+            .collect(),
+        )
+    }
+}
+
 /// Iterator over `Mapping`.
 pub struct Iter<'a> {
     iter: indexmap::map::Iter<'a, Value, Value>,
@@ -596,22 +1210,22 @@ mod mapping_tests {
         let mut m = Mapping::new();
         m.insert_raw("a".into(), 1.into());
         m.insert_raw("b".into(), "foo".into());
-        m.insert_raw("c".into(), 3.14.into());
+        m.insert_raw("c".into(), 2.5.into());
         m.insert_raw("d".into(), Value::Bool(true));
         m
     }
 
     #[test]
     fn test_from_str() {
-        let input = r#"
+        let input = r"
         a: 1
         b: foo
-        c: 3.14
+        c: 2.5
         d: true
         e: [1,2,3]
         f:
           foo: bar
-        "#;
+        ";
         let m = Mapping::from_str(input).unwrap();
         let mut expected = create_map();
         expected.insert_raw("e".into(), vec![1, 2, 3].into());
@@ -630,17 +1244,17 @@ mod mapping_tests {
         assert_eq!(items.len(), 4);
         assert_eq!(items[0], (&"a".into(), &1.into()));
         assert_eq!(items[1], (&"b".into(), &"foo".into()));
-        assert_eq!(items[2], (&"c".into(), &3.14.into()));
+        assert_eq!(items[2], (&"c".into(), &2.5.into()));
         assert_eq!(items[3], (&"d".into(), &Value::Bool(true)));
     }
 
     #[test]
     fn test_contains_key() {
         let mut m = create_map();
-        m.insert_raw(3.14.into(), "3.14".into());
+        m.insert_raw(2.5.into(), "2.5".into());
 
         assert!(m.contains_key(&"a".into()));
-        assert!(m.contains_key(&3.14.into()));
+        assert!(m.contains_key(&2.5.into()));
         assert!(!m.contains_key(&"e".into()));
         assert!(!m.contains_key(&5.into()));
     }
@@ -648,10 +1262,10 @@ mod mapping_tests {
     #[test]
     fn test_get() {
         let mut m = create_map();
-        m.insert_raw(3.14.into(), "3.14".into());
+        m.insert_raw(2.5.into(), "2.5".into());
 
         assert_eq!(m.get(&"a".into()), Some(&1.into()));
-        assert_eq!(m.get(&3.14.into()), Some(&"3.14".into()));
+        assert_eq!(m.get(&2.5.into()), Some(&"2.5".into()));
         assert_eq!(m.get(&"e".into()), None);
     }
 
@@ -743,10 +1357,10 @@ mod mapping_tests {
             let m = m.as_py_dict(py).unwrap();
             assert_eq!(m.len(), 6);
             assert_eq!(format!("{:?}", m.keys()), "['a', 'b', 'c', 'd', 'e', 'f']");
-            let a = m.get_item(&"a").unwrap().unwrap();
+            let a = m.get_item("a").unwrap().unwrap();
             assert!(a.is_instance_of::<pyo3::types::PyInt>());
             assert!(a.downcast_exact::<pyo3::types::PyInt>().unwrap().eq(&1));
-            let f = m.get_item(&"f").unwrap().unwrap();
+            let f = m.get_item("f").unwrap().unwrap();
             assert!(f.is_instance_of::<PyDict>());
             let f = f.downcast_exact::<PyDict>().unwrap();
             assert_eq!(f.len(), 1);
@@ -769,7 +1383,7 @@ mod mapping_tests {
 
         let mut items = vec![];
         for it in &m {
-            items.push(it)
+            items.push(it);
         }
 
         assert_eq!(
@@ -777,7 +1391,7 @@ mod mapping_tests {
             vec![
                 (&"a".into(), &1.into()),
                 (&"b".into(), &"foo".into()),
-                (&"c".into(), &3.14.into()),
+                (&"c".into(), &2.5.into()),
                 (&"d".into(), &Value::Bool(true)),
             ]
         );
@@ -785,11 +1399,11 @@ mod mapping_tests {
 
     #[test]
     fn test_from_str_const_keys() {
-        let input = r#"
+        let input = r"
         foo: foo
         =bar: bar
         baz: baz
-        "#;
+        ";
         let m = Mapping::from_str(input).unwrap();
         let mut expected = Mapping::new();
         expected.insert_raw("foo".into(), "foo".into());
@@ -801,6 +1415,34 @@ mod mapping_tests {
         assert_eq!(m, expected);
     }
 
+    #[test]
+    fn test_constant_keys() {
+        let m = Mapping::from_str("{=foo: foo, bar: bar, ~baz: baz, =qux: {=a: a, b: b}}").unwrap();
+        assert_eq!(m.constant_keys(), vec![&"foo".into(), &"qux".into()]);
+        assert!(m.is_const(&"foo".into()));
+        assert!(!m.is_const(&"bar".into()));
+        assert!(!m.is_const(&"baz".into()));
+        assert!(m.is_override(&"baz".into()));
+        assert!(!m.is_override(&"foo".into()));
+        assert_eq!(m.constant_paths(), vec!["foo", "qux", "qux:a"]);
+    }
+
+    #[test]
+    fn test_to_yaml_with_prefixes() {
+        let m = Mapping::from_str("{=a: 1, b: {~c: 2, +f: [4]}, d: [{=e: 3}]}").unwrap();
+        let y = m.to_yaml_with_prefixes();
+        let expected: serde_yaml::Mapping =
+            serde_yaml::from_str("{=a: 1, b: {~c: 2, +f: [4]}, d: [{=e: 3}]}").unwrap();
+        assert_eq!(y, expected);
+
+        let roundtrip = Mapping::from(y);
+        assert_eq!(roundtrip, m);
+        assert_eq!(roundtrip.constant_keys(), vec![&"a".into()]);
+        let b = roundtrip.get(&"b".into()).unwrap().as_mapping().unwrap();
+        assert!(b.is_override(&"c".into()));
+        assert!(b.is_append(&"f".into()));
+    }
+
     #[test]
     fn test_insert_const_key() {
         let mut m = Mapping::new();
@@ -921,13 +1563,13 @@ mod mapping_tests {
 
     #[test]
     fn test_from_serde_yaml_const_keys() {
-        let input = r#"
+        let input = r"
         foo: foo
         bar:
           =qux: qux
           ~foo: foo
         =baz: baz
-        "#;
+        ";
         let rawm: serde_yaml::Mapping = serde_yaml::from_str(input).unwrap();
 
         let m = Mapping::from(rawm);
@@ -949,21 +1591,21 @@ mod mapping_tests {
 
     #[test]
     fn test_mapping_merge_simple() {
-        let base = r#"
+        let base = r"
         foo: foo
-        "#;
+        ";
         let mut base = Mapping::from_str(base).unwrap();
-        let m = r#"
+        let m = r"
         bar: bar
-        "#;
+        ";
         let m = Mapping::from_str(m).unwrap();
 
         base.merge(&m).unwrap();
 
-        let expected = r#"
+        let expected = r"
         foo: foo
         bar: bar
-        "#;
+        ";
         let e = Mapping::from_str(expected).unwrap();
         assert_eq!(base, e);
     }
@@ -985,19 +1627,19 @@ mod mapping_tests {
 
     #[test]
     fn test_mapping_merge_nested() {
-        let base = r#"
+        let base = r"
         foo:
           foo: foo
         bar:
           bar: bar
-        "#;
+        ";
         let mut base = Mapping::from_str(base).unwrap();
-        let m = r#"
+        let m = r"
         foo:
           baz: baz
         bar:
           qux: qux
-        "#;
+        ";
         let m = Mapping::from_str(m).unwrap();
 
         base.merge(&m).unwrap();
@@ -1021,6 +1663,266 @@ mod mapping_tests {
         assert_eq!(base, expected);
     }
 
+    #[test]
+    fn test_diff() {
+        let a = Mapping::from_str(
+            r"
+        foo: foo
+        bar: bar
+        nested:
+          a: 1
+          b: 2
+          c: 3
+        scalar: foo
+        ",
+        )
+        .unwrap();
+        let b = Mapping::from_str(
+            r"
+        foo: foo
+        baz: baz
+        nested:
+          a: 1
+          b: 3
+        scalar:
+          foo: bar
+        ",
+        )
+        .unwrap();
+
+        let d = a.diff(&b);
+        assert!(!d.is_empty());
+        assert_eq!(d.added, vec![("baz".to_string(), "baz".into())]);
+        assert_eq!(
+            d.removed,
+            vec![
+                ("bar".to_string(), "bar".into()),
+                ("nested:c".to_string(), 3.into())
+            ]
+        );
+        assert_eq!(
+            d.changed,
+            vec![
+                ("nested:b".to_string(), 2.into(), 3.into()),
+                (
+                    "scalar".to_string(),
+                    "foo".into(),
+                    Mapping::from_str("foo: bar").unwrap().into()
+                )
+            ]
+        );
+
+        assert!(a.diff(&a).is_empty());
+    }
+
+    #[test]
+    fn test_diff_as_value() {
+        let a = Mapping::from_str("{foo: foo, bar: bar}").unwrap();
+        let b = Mapping::from_str("{foo: baz, qux: qux}").unwrap();
+        let v = Value::from(a.diff(&b));
+        let expected = Mapping::from_str(
+            r"
+        added:
+          qux: qux
+        removed:
+          bar: bar
+        changed:
+          foo:
+            old: foo
+            new: baz
+        ",
+        )
+        .unwrap();
+        assert_eq!(v, Value::Mapping(expected));
+    }
+
+    #[test]
+    fn test_get_path() {
+        let m = Mapping::from_str(
+            r"
+        cluster:
+          name: c-test
+          nodes:
+            - name: a
+            - name: b
+        foo: bar
+        ",
+        )
+        .unwrap();
+        assert_eq!(m.get_path("foo"), Some(&"bar".into()));
+        assert_eq!(m.get_path("cluster:name"), Some(&"c-test".into()));
+        assert_eq!(m.get_path("cluster:nodes:1:name"), Some(&"b".into()));
+        assert_eq!(m.get_path("cluster:nodes:2:name"), None);
+        assert_eq!(m.get_path("cluster:nodes:foo"), None);
+        assert_eq!(m.get_path("cluster:missing"), None);
+        assert_eq!(m.get_path("foo:bar"), None);
+        assert_eq!(m.get_path(""), None);
+    }
+
+    #[test]
+    fn test_mapping_deep_merge() {
+        let mut base = Mapping::from_str(
+            r"
+            foo:
+              bar: bar
+              nested:
+                a: a
+                b: b
+            baz: [1, 2]
+            qux: qux
+            ",
+        )
+        .unwrap();
+        let m = Mapping::from_str(
+            r"
+            foo:
+              nested:
+                b: bb
+                c: c
+              new: new
+            baz: [3]
+            ",
+        )
+        .unwrap();
+
+        base.deep_merge(&m);
+
+        let expected = Mapping::from_str(
+            r"
+            foo:
+              bar: bar
+              nested:
+                a: a
+                b: bb
+                c: c
+              new: new
+            baz: [3]
+            qux: qux
+            ",
+        )
+        .unwrap();
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn test_mapping_deep_merge_overwrite() {
+        let mut base = Mapping::from_str("{foo: {bar: bar}, baz: baz, =qux: qux}").unwrap();
+        let m = Mapping::from_str("{foo: foo, baz: {bar: bar}, qux: quux}").unwrap();
+
+        base.deep_merge(&m);
+
+        assert_eq!(base.get(&"foo".into()), Some(&"foo".into()));
+        assert_eq!(
+            base.get(&"baz".into()),
+            Some(&Mapping::from_str("bar: bar").unwrap().into())
+        );
+        // constant keys are overwritten too
+        assert_eq!(base.get(&"qux".into()), Some(&"quux".into()));
+        assert!(!base.get(&"baz".into()).unwrap().is_value_list());
+    }
+
+    #[test]
+    fn test_mapping_merge_with_limit() {
+        let mut m = Mapping::new();
+        let other = Mapping::from_str("{foo: bar, baz: qux}").unwrap();
+        // The first merge inserts the values directly, each following merge appends one layer to
+        // the ValueLists for keys `foo` and `baz`.
+        for _ in 0..5 {
+            m.merge_with_limit(&other, Some(5)).unwrap();
+        }
+        assert_eq!(
+            m.get(&"foo".into()).unwrap().as_value_list().unwrap().len(),
+            5
+        );
+        let err = m.merge_with_limit(&other, Some(5)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            r#"ValueList for key "foo" exceeds the configured maximum length of 5 after merging"#
+        );
+
+        // merging without limit never fails
+        m.merge(&other).unwrap();
+    }
+
+    #[test]
+    fn test_mapping_merge_with_index_keys() {
+        let mut m = Mapping::from_str("{foo: [a, b, c], bar: {baz: [a]}}").unwrap();
+        m.merge(&Mapping::from_str("foo: [d]").unwrap()).unwrap();
+        let other = Mapping::from_str(r#"{"foo:3": x, "bar:baz:0": y, qux: "1:2"}"#).unwrap();
+        m.merge_with_index_keys(&other, None, false).unwrap();
+        // Index overrides are applied to the merged sequence layers
+        assert_eq!(
+            m,
+            Mapping::from_str(r#"{foo: [a, b, c, x], bar: {baz: [y]}, qux: "1:2"}"#).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mapping_merge_with_index_keys_nested() {
+        let mut m = Mapping::from_str("{foo: {bar: [a, b]}, baz: {qux: [a]}}").unwrap();
+        m.merge(&Mapping::from_str("foo: {bar: [c], x: 1}").unwrap())
+            .unwrap();
+        // Index keys in nested mappings are relative to the nested mapping, and paths can be
+        // followed through layered mappings.
+        let other =
+            Mapping::from_str(r#"{foo: {"bar:2": y, x: 2}, baz: {"qux:0": z}, "foo:bar:0": w}"#)
+                .unwrap();
+        m.merge_with_index_keys(&other, None, false).unwrap();
+        let mut v = Value::Mapping(m);
+        v.render_with_self().unwrap();
+        let expected: serde_yaml::Value =
+            serde_yaml::from_str("{foo: {bar: [w, b, y], x: 2}, baz: {qux: [z]}}").unwrap();
+        assert_eq!(serde_yaml::Value::from(v), expected);
+    }
+
+    #[test]
+    fn test_mapping_merge_with_index_keys_unique() {
+        let mut m = Mapping::from_str("foo: [a, b]").unwrap();
+        m.merge(&Mapping::from_str("foo: [c, a]").unwrap()).unwrap();
+        let other = Mapping::from_str(r#""foo:0": c"#).unwrap();
+        m.merge_with_index_keys(&other, None, true).unwrap();
+        // The override is applied to the concatenated layers, and elements of later layers which
+        // are already present are dropped afterwards.
+        assert_eq!(m, Mapping::from_str("foo: [c, b, a]").unwrap());
+    }
+
+    #[test]
+    fn test_mapping_merge_with_index_keys_errors() {
+        let mut m = Mapping::from_str("{foo: [a, b, c], bar: baz}").unwrap();
+        for (k, msg) in [
+            (
+                "foo:3",
+                "Can't override sequence element 'foo:3': index 3 out of bounds for sequence of \
+                length 3",
+            ),
+            (
+                "bar:0",
+                "Can't override sequence element 'bar:0': 'bar' is a Value::String, not a Sequence",
+            ),
+            (
+                "baz:0",
+                "Can't override sequence element 'baz:0': key 'baz' doesn't exist",
+            ),
+        ] {
+            let mut other = Mapping::new();
+            other.insert(k.into(), "x".into()).unwrap();
+            let err = m.merge_with_index_keys(&other, None, false).unwrap_err();
+            assert_eq!(err.to_string(), msg);
+        }
+    }
+
+    #[test]
+    fn test_dangling_overrides() {
+        let mut base = Mapping::from_str("{foo: {bar: 1}, ref: '${foo}'}").unwrap();
+        base.merge(&Mapping::from_str("foo: {baz: 2}").unwrap())
+            .unwrap();
+        let m = Mapping::from_str("{bar: {~baz: 1}, ~qux: 1, ref: {~x: 1}, foo: {~baz: 3, ~y: 4}}")
+            .unwrap();
+        // `foo:baz` exists in the second layer of `foo`, `ref` is an unresolved reference, and
+        // nested keys of new keys can't override anything
+        assert_eq!(m.dangling_overrides(&base), vec!["bar:baz", "qux", "foo:y"]);
+    }
+
     #[test]
     fn test_mapping_merge_const() {
         let mut base = Mapping::from_str("foo: foo").unwrap();
@@ -1089,4 +1991,82 @@ mod mapping_tests {
         base.merge(&m1).unwrap();
         assert_eq!(base, Mapping::from_str("=foo: bar").unwrap());
     }
+
+    #[test]
+    fn test_mapping_merge_append() {
+        let mut base = Mapping::from_str("{foo: [a, b], bar: [a, b]}").unwrap();
+        let m = Mapping::from_str("{+foo: [c], bar: [c]}").unwrap();
+        assert!(m.is_append(&"foo".into()));
+        assert!(!m.is_append(&"bar".into()));
+
+        base.merge(&m).unwrap();
+
+        // The `+`-prefixed key is appended to the existing Sequence in-place
+        assert_eq!(
+            base.get(&"foo".into()),
+            Some(&Value::Sequence(vec!["a".into(), "b".into(), "c".into()]))
+        );
+        // The non-prefixed key is tracked as a ValueList
+        assert_eq!(
+            base.get(&"bar".into()),
+            Some(&Value::ValueList(vec![
+                Value::Sequence(vec!["a".into(), "b".into()]),
+                Value::Sequence(vec!["c".into()]),
+            ]))
+        );
+        // The append marker is consumed by the merge
+        assert!(!base.is_append(&"foo".into()));
+    }
+
+    #[test]
+    fn test_mapping_merge_append_non_sequence() {
+        // Append markers for non-Sequence values are merged like regular keys
+        let mut base = Mapping::from_str("{foo: bar}").unwrap();
+        let m = Mapping::from_str("{+foo: [baz]}").unwrap();
+
+        base.merge(&m).unwrap();
+
+        assert_eq!(
+            base.get(&"foo".into()),
+            Some(&Value::ValueList(vec![
+                "bar".into(),
+                Value::Sequence(vec!["baz".into()]),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_mapping_merge_with_report() {
+        let mut base = Mapping::from_str("{foo: [a], bar: bar, baz: baz}").unwrap();
+        let m = Mapping::from_str("{foo: [b], ~bar: qux, qux: qux}").unwrap();
+
+        let report = base.merge_with_report(&m).unwrap();
+
+        assert_eq!(
+            report,
+            vec![
+                KeyChange {
+                    key: "foo".into(),
+                    kind: KeyChangeKind::Appended
+                },
+                KeyChange {
+                    key: "bar".into(),
+                    kind: KeyChangeKind::Overridden(Box::new("bar".into()))
+                },
+                KeyChange {
+                    key: "qux".into(),
+                    kind: KeyChangeKind::Inserted
+                },
+            ]
+        );
+        assert_eq!(base.get(&"bar".into()), Some(&"qux".into()));
+        assert_eq!(base.get(&"qux".into()), Some(&"qux".into()));
+        assert_eq!(
+            base.get(&"foo".into()),
+            Some(&Value::ValueList(vec![
+                Value::Sequence(vec!["a".into()]),
+                Value::Sequence(vec!["b".into()]),
+            ]))
+        );
+    }
 }
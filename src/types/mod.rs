@@ -2,8 +2,9 @@ mod from;
 pub(crate) mod mapping;
 mod value;
 
-pub use mapping::Mapping;
-pub use value::Value;
+pub use mapping::{KeyChange, KeyChangeKind, Mapping, MappingDiff};
+pub(crate) use value::TEMPLATE_TAG;
+pub use value::{MergeResult, Value};
 
 /// A YAML sequence in which the elements are `reclass_rs::value::Value`
 pub type Sequence = Vec<Value>;
@@ -20,6 +21,11 @@ enum KeyPrefix {
     /// Keys prefixed with the override marker are taken as the new base value, discarding any
     /// previous content of the key.
     Override, // '~',
+    /// Represents a key whose Sequence value should be appended to the existing Sequence
+    ///
+    /// Keys prefixed with the append marker extend an existing Sequence value in-place instead
+    /// of being tracked as a new layer in a `Value::ValueList`.
+    Append, // '+',
 }
 
 impl KeyPrefix {
@@ -27,6 +33,7 @@ impl KeyPrefix {
         match c {
             '=' => Some(Self::Constant),
             '~' => Some(Self::Override),
+            '+' => Some(Self::Append),
             _ => None,
         }
     }
@@ -37,6 +44,7 @@ impl std::fmt::Display for KeyPrefix {
         match self {
             Self::Constant => write!(f, "="),
             Self::Override => write!(f, "~"),
+            Self::Append => write!(f, "+"),
         }
     }
 }
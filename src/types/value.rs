@@ -1,14 +1,19 @@
 // Inspired by `serde_yaml::Value`
 
 use anyhow::{anyhow, Result};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use serde_yaml::Number;
+use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 use std::mem;
 
 use super::KeyPrefix;
 use super::{Mapping, Sequence};
-use crate::refs::{ResolveState, Token};
+use crate::refs::{RenderOpts, ResolveState, Token};
+
+/// YAML tag which marks a string as a deferred template, cf. `Value::Template`.
+pub(crate) const TEMPLATE_TAG: &str = "!template";
 
 /// Represents a YAML value in a form suitable for processing Reclass parameters.
 #[derive(Clone, Debug, PartialEq)]
@@ -21,6 +26,10 @@ pub enum Value {
     String(String),
     /// Represents a string literal value which can't contain reclass references.
     Literal(String),
+    /// Represents a deferred template string (YAML tag `!template`) which reclass-rs passes
+    /// through verbatim. Template values are never interpolated by reclass-rs and are tagged in
+    /// the output, so that downstream consumers know that they need to process them.
+    Template(String),
     /// Represents a YAML numerical value.
     Number(Number),
     /// Represents a YAML mapping.
@@ -69,6 +78,7 @@ impl std::fmt::Display for Value {
             Self::Bool(b) => write!(f, "{b}"),
             Self::Number(n) => write!(f, "{n}"),
             Self::String(s) | Self::Literal(s) => write!(f, "\"{s}\""),
+            Self::Template(s) => write!(f, "!template \"{s}\""),
             Self::Sequence(seq) | Self::ValueList(seq) => {
                 write!(f, "[")?;
                 for (i, v) in seq.iter().enumerate() {
@@ -93,7 +103,7 @@ impl Hash for Value {
             Self::Null => {}
             Self::Bool(v) => v.hash(state),
             Self::Number(v) => v.hash(state),
-            Self::Literal(v) | Self::String(v) => v.hash(state),
+            Self::Literal(v) | Self::String(v) | Self::Template(v) => v.hash(state),
             Self::Mapping(v) => v.hash(state),
             Self::Sequence(v) | Self::ValueList(v) => v.hash(state),
         }
@@ -135,6 +145,14 @@ impl From<Value> for serde_json::Value {
                 serde_json::Value::Number(jn)
             }
             Value::Literal(s) | Value::String(s) => Self::String(s),
+            // JSON doesn't support tags, we represent template values as a single-key object
+            // which is keyed by the tag. This matches how serde_yaml serializes tagged values to
+            // JSON.
+            Value::Template(s) => {
+                let mut m = serde_json::Map::with_capacity(1);
+                m.insert(TEMPLATE_TAG.to_string(), Self::String(s));
+                Self::Object(m)
+            }
             Value::Sequence(s) => {
                 let mut seq: Vec<Self> = Vec::with_capacity(s.len());
                 for v in s {
@@ -266,6 +284,23 @@ impl Value {
         matches!(self, Self::Literal(_))
     }
 
+    /// Checks if the `Value` is a Template.
+    #[inline]
+    #[must_use]
+    pub fn is_template(&self) -> bool {
+        matches!(self, Self::Template(_))
+    }
+
+    /// If the `Value` is a Template, return the associated template `str`. Returns None otherwise.
+    #[inline]
+    #[must_use]
+    pub fn as_template(&self) -> Option<&str> {
+        match self {
+            Self::Template(s) => Some(s),
+            _ => None,
+        }
+    }
+
     /// If the `Value` is a String or Literal, return the associated `str`. Returns None otherwise.
     #[inline]
     #[must_use]
@@ -410,6 +445,79 @@ impl Value {
         }
     }
 
+    /// Returns a mutable reference to the value at the provided `:`-separated path, e.g.
+    /// `cluster:name`, if the path exists in the `Value`. Returns None otherwise.
+    ///
+    /// Path segments which are used to index into a Sequence or ValueList are interpreted as
+    /// zero-based indices. See [`Value::get_mut()`] for details on how each segment is looked up.
+    ///
+    /// Returns an error when the path traverses a constant key in a Mapping.
+    pub fn get_path_mut(&mut self, path: &str) -> Result<Option<&mut Value>> {
+        let mut v = self;
+        for key in path.split(':') {
+            let k = if v.is_sequence() || v.is_value_list() {
+                key.parse::<usize>()
+                    .map_or_else(|_| key.into(), Value::from)
+            } else {
+                key.into()
+            };
+            match v.get_mut(&k)? {
+                Some(n) => v = n,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(v))
+    }
+
+    /// Returns a content hash of the Value which is suitable as a cache key.
+    ///
+    /// In contrast to the `Hash` implementation, the content hash treats `Value::String` and
+    /// `Value::Literal`, as well as `Value::Sequence` and `Value::ValueList` as equivalent, so
+    /// that semantically equal Values produce the same hash. Similar to the `Hash` implementation,
+    /// the content hash of a Mapping doesn't depend on the order of its keys.
+    ///
+    /// The hash isn't cryptographically secure and is only guaranteed to be stable within a
+    /// single build of reclass-rs. Don't use it for security purposes or persist it.
+    #[must_use]
+    pub fn content_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash_content(&mut hasher);
+        hasher.finish()
+    }
+
+    pub(super) fn hash_content<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Self::Null => 0u8.hash(state),
+            Self::Bool(b) => {
+                1u8.hash(state);
+                b.hash(state);
+            }
+            Self::Number(n) => {
+                2u8.hash(state);
+                n.hash(state);
+            }
+            Self::String(s) | Self::Literal(s) => {
+                3u8.hash(state);
+                s.hash(state);
+            }
+            Self::Template(s) => {
+                4u8.hash(state);
+                s.hash(state);
+            }
+            Self::Sequence(s) | Self::ValueList(s) => {
+                5u8.hash(state);
+                s.len().hash(state);
+                for v in s {
+                    v.hash_content(state);
+                }
+            }
+            Self::Mapping(m) => {
+                6u8.hash(state);
+                m.content_hash().hash(state);
+            }
+        }
+    }
+
     /// Provides a nice string for each enum variant for debugging and pretty-printing.
     pub(crate) fn variant(&self) -> &str {
         match self {
@@ -420,15 +528,53 @@ impl Value {
             Self::Sequence(_) => "Value::Sequence",
             Self::String(_) => "Value::String",
             Self::Literal(_) => "Value::Literal",
+            Self::Template(_) => "Value::Template",
             Self::ValueList(_) => "Value::ValueList",
         }
     }
 
     /// Converts the `Value` into a `PyObject`.
-    #[allow(clippy::missing_panics_doc)]
     pub fn as_py_obj<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.as_py_obj_impl(py, false)
+    }
+
+    /// Converts the `Value` into a `PyObject` without requiring that the Value is fully rendered.
+    /// This is intended for inspecting intermediate parameters, e.g. before ValueLists have been
+    /// flattened.
+    ///
+    /// If `flatten` is true, the Value is flattened before it's converted. Otherwise, ValueLists
+    /// are emitted as a Python list of their layers.
+    pub fn as_py_obj_debug<'py>(
+        &self,
+        py: Python<'py>,
+        flatten: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        if flatten {
+            let v = self
+                .flattened()
+                .map_err(|e| PyValueError::new_err(format!("{e}")))?;
+            v.as_py_obj_impl(py, true)
+        } else {
+            self.as_py_obj_impl(py, true)
+        }
+    }
+
+    /// Converts the `Value` into a `PyObject`. ValueLists are only emitted (as a list of their
+    /// layers) if `debug` is true.
+    #[allow(clippy::missing_panics_doc)]
+    pub(super) fn as_py_obj_impl<'py>(
+        &self,
+        py: Python<'py>,
+        debug: bool,
+    ) -> PyResult<Bound<'py, PyAny>> {
         let obj = match self {
             Value::Literal(s) | Value::String(s) => s.into_pyobject(py)?.into_any(),
+            // Template values are emitted as a single-key dict which is keyed by the tag
+            Value::Template(s) => {
+                let dict = pyo3::types::PyDict::new(py);
+                dict.set_item(TEMPLATE_TAG, s)?;
+                dict.into_any()
+            }
             Value::Bool(b) => pyo3::types::PyBool::new(py, *b).to_owned().into_any(),
             Value::Number(n) => {
                 if n.is_i64() {
@@ -446,13 +592,20 @@ impl Value {
             Value::Sequence(s) => {
                 let mut pyseq = vec![];
                 for v in s {
-                    pyseq.push(v.as_py_obj(py)?);
+                    pyseq.push(v.as_py_obj_impl(py, debug)?);
                 }
                 pyseq.into_pyobject(py)?.into_any()
             }
-            Value::Mapping(m) => m.as_py_dict(py)?.into_any(),
+            Value::Mapping(m) => m.as_py_dict_impl(py, debug)?.into_any(),
             Value::Null => Option::<()>::None.into_pyobject(py)?.into_any(),
-            // ValueList should never get emitted to Python
+            Value::ValueList(l) if debug => {
+                let mut layers = vec![];
+                for v in l {
+                    layers.push(v.as_py_obj_impl(py, debug)?);
+                }
+                layers.into_pyobject(py)?.into_any()
+            }
+            // ValueList should never get emitted to Python outside of debugging
             Value::ValueList(_) => unreachable!(),
         };
         Ok(obj)
@@ -492,7 +645,8 @@ impl Value {
     #[inline]
     pub(crate) fn raw_string(&self) -> Result<String> {
         match self {
-            Value::Literal(s) => Ok(s.clone()),
+            // Templates which are embedded in other strings are rendered verbatim
+            Value::Literal(s) | Value::Template(s) => Ok(s.clone()),
             // We serialize Null as `None` to be compatible with Python's str()
             Value::Null => Ok("None".to_string()),
             // We need custom formatting for bool instead of `format!("{b}")`, so that this
@@ -534,7 +688,7 @@ impl Value {
                 // String interpolation parses any Reclass references in the String and resolves
                 // them. The result of `Token::render()` can be an arbitrary Value, except for
                 // `Value::String()`, since `render()` will recursively call `interpolate()`.
-                if let Some(token) = Token::parse(s)? {
+                if let Some(token) = Token::parse(s).map_err(|e| state.render_parse_error(&e))? {
                     token.render(root, state)?
                 } else {
                     // If Token::parse() returns None, we can be sure that there's no references
@@ -575,7 +729,8 @@ impl Value {
                     // done with a layer, any references that we saw there have been successfully
                     // resolved, and don't matter for the next layer we're interpolating).
                     let mut st = state.clone();
-                    r.merge(v.interpolate(root, &mut st)?)?;
+                    let opts = *st.opts();
+                    r.merge(v.interpolate(root, &mut st)?, &opts)?;
                 }
                 // Depending on the structure of the ValueList, we may end up with a final
                 // interpolated Value which contains more ValueLists due to mapping merges. Such
@@ -601,7 +756,13 @@ impl Value {
     ///
     /// Note that this method will call [`Value::flatten()`] after merging two Mappings to ensure
     /// that the resulting Value doesn't contain any `ValueList` elements.
-    fn merge(&mut self, other: Self) -> Result<()> {
+    ///
+    /// If `opts.unique_sequences` is true, merging two Sequences only appends the elements of
+    /// `other` which aren't already present in self.
+    ///
+    /// If `opts.type_changing_merge` is true, merging a Value over a Value of an incompatible type
+    /// (e.g. a string over a mapping) replaces self with `other` instead of returning an error.
+    fn merge(&mut self, other: Self, opts: &RenderOpts) -> Result<()> {
         if other.is_null() {
             // Any value can be replaced by null,
             let _prev = std::mem::replace(self, other);
@@ -610,11 +771,16 @@ impl Value {
 
         // If `other` is a ValueList, flatten it before trying to merge
         let other = if other.is_value_list() {
-            other.flattened()?
+            other.flattened_with(opts)?
         } else {
             other
         };
 
+        if opts.type_changing_merge && !self.is_mergeable_with(&other) {
+            let _prev = std::mem::replace(self, other);
+            return Ok(());
+        }
+
         // we assume that self is already interpolated
         match self {
             // anything can be merged over null
@@ -628,10 +794,20 @@ impl Value {
             },
             Self::Sequence(s) => match other {
                 // merge sequence and sequence
-                Self::Sequence(mut other) => s.append(&mut other),
+                Self::Sequence(mut other) => {
+                    if opts.unique_sequences {
+                        for v in other {
+                            if !s.contains(&v) {
+                                s.push(v);
+                            }
+                        }
+                    } else {
+                        s.append(&mut other);
+                    }
+                }
                 _ => return Err(anyhow!("Can't merge {} over sequence", other.variant())),
             },
-            Self::Literal(_) | Self::Bool(_) | Self::Number(_) => {
+            Self::Literal(_) | Self::Template(_) | Self::Bool(_) | Self::Number(_) => {
                 if other.is_mapping() || other.is_sequence() {
                     // We can't merge simple non-null types over mappings or sequences
                     return Err(anyhow!(
@@ -652,10 +828,101 @@ impl Value {
                 // to ensure that they don't construct nested ValueLists.
                 unreachable!("Encountered ValueList as merge target, this shouldn't happen!");
             }
-        };
+        }
         Ok(())
     }
 
+    /// Returns true if Value `other` can be merged over self without changing the type of self.
+    ///
+    /// Mappings and Sequences can only be merged with Values of the same type, while simple values
+    /// can be merged over each other. Null is compatible with any Value.
+    fn is_mergeable_with(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Null, _) | (_, Self::Null) => true,
+            (Self::Mapping(_), o) => o.is_mapping(),
+            (Self::Sequence(_), o) => o.is_sequence(),
+            (_, o) => !o.is_mapping() && !o.is_sequence(),
+        }
+    }
+
+    /// Merges divergent Values `ours` and `theirs` which were both derived from Value `base`.
+    ///
+    /// Changes which were only made on one side are taken over into the merged Value. Mappings
+    /// are merged key by key, so both sides can change different keys of the same Mapping without
+    /// producing a conflict. All other Values are treated as leaves.
+    ///
+    /// If both sides changed the same leaf to different Values, the conflict is recorded in the
+    /// returned [`MergeResult`] and the Value from `ours` is used in the merged Value. Keys which
+    /// were removed on one side are represented as `Value::Null` in the recorded conflicts.
+    ///
+    /// This method expects rendered Values, and returns an error when encountering a
+    /// `Value::ValueList` or an unparsed `Value::String` which needs to be compared.
+    pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Result<MergeResult> {
+        let mut conflicts = vec![];
+        let value = Self::merge3_impl(
+            Some(base),
+            Some(ours),
+            Some(theirs),
+            &mut vec![],
+            &mut conflicts,
+        )?
+        .unwrap_or_default();
+        Ok(MergeResult { value, conflicts })
+    }
+
+    fn merge3_impl(
+        base: Option<&Value>,
+        ours: Option<&Value>,
+        theirs: Option<&Value>,
+        path: &mut Vec<String>,
+        conflicts: &mut Vec<(String, Value, Value)>,
+    ) -> Result<Option<Value>> {
+        for v in [base, ours, theirs].into_iter().flatten() {
+            if v.is_value_list() || v.is_string() {
+                return Err(anyhow!(
+                    "Can't three-way merge {} at '{}', did you mean to render the values first?",
+                    v.variant(),
+                    path.join(":")
+                ));
+            }
+        }
+        if ours == theirs || base == theirs {
+            return Ok(ours.cloned());
+        }
+        if base == ours {
+            return Ok(theirs.cloned());
+        }
+        if let (Some(Self::Mapping(o)), Some(Self::Mapping(t))) = (ours, theirs) {
+            let base = base.and_then(Value::as_mapping);
+            let mut merged = Mapping::new();
+            let keys = o.as_map().keys();
+            for k in keys.chain(t.as_map().keys().filter(|k| !o.contains_key(k))) {
+                path.push(
+                    k.as_str()
+                        .map_or_else(|| k.to_string(), ToString::to_string),
+                );
+                let v = Self::merge3_impl(
+                    base.and_then(|b| b.get(k)),
+                    o.get(k),
+                    t.get(k),
+                    path,
+                    conflicts,
+                )?;
+                path.pop();
+                if let Some(v) = v {
+                    merged.insert(k.clone(), v)?;
+                }
+            }
+            return Ok(Some(Self::Mapping(merged)));
+        }
+        conflicts.push((
+            path.join(":"),
+            ours.cloned().unwrap_or_default(),
+            theirs.cloned().unwrap_or_default(),
+        ));
+        Ok(ours.cloned())
+    }
+
     /// Flattens the Value and returns the resulting Value.
     ///
     /// This method recursively flattens any `ValueList`s which are present in the value or its
@@ -667,6 +934,14 @@ impl Value {
     /// Note that we don't recommend calling `flattened()` on arbitrary Values. Users should always
     /// prefer calling [`Value::rendered()`] or one of the in-place variations of that method.
     pub(crate) fn flattened(&self) -> Result<Self> {
+        self.flattened_with(&RenderOpts::default())
+    }
+
+    /// Flattens the Value and returns the resulting Value.
+    ///
+    /// The merge options in `opts` are applied when merging the layers of `ValueList`s, see
+    /// [`Value::merge()`]. See [`Value::flattened()`] for details.
+    pub(crate) fn flattened_with(&self, opts: &RenderOpts) -> Result<Self> {
         match self {
             // Flatten ValueList by iterating over its elements and merging each element into a
             // base Value.
@@ -674,22 +949,24 @@ impl Value {
                 // NOTE(sg): Empty ValueLists get flattened to Value::Null
                 let mut base = Value::Null;
                 for v in l {
-                    base.merge(v.clone())?;
+                    base.merge(v.clone(), opts)?;
                 }
                 Ok(base)
             }
             // Flatten Mapping by flattening each value and inserting it into a new Mapping.
-            Self::Mapping(m) => Ok(Self::Mapping(m.flattened()?)),
+            Self::Mapping(m) => Ok(Self::Mapping(m.flattened(opts)?)),
             // Flatten Sequence by flattening each element and inserting it into a new Sequence
             Self::Sequence(s) => {
                 let mut n = Vec::with_capacity(s.len());
                 for v in s {
-                    n.push(v.flattened()?);
+                    n.push(v.flattened_with(opts)?);
                 }
                 Ok(Self::Sequence(n))
             }
             // Simple values are flattened as themselves
-            Self::Null | Self::Bool(_) | Self::Literal(_) | Self::Number(_) => Ok(self.clone()),
+            Self::Null | Self::Bool(_) | Self::Literal(_) | Self::Template(_) | Self::Number(_) => {
+                Ok(self.clone())
+            }
             // Flattening an unparsed string is an error
             Self::String(_) => Err(anyhow!(
                 "Can't flatten unparsed String, did you mean to call `rendered()`?"
@@ -714,12 +991,18 @@ impl Value {
     /// reference keys in `root`. After all references have been interpolated, the method flattens
     /// any remaining ValueLists and returns the final "flattened" value.
     pub fn rendered(&self, root: &Mapping) -> Result<Self> {
-        let mut state = ResolveState::default();
-        let mut v = self
+        self.rendered_with_state(root, ResolveState::default())
+    }
+
+    /// Renders the Value starting from the provided initial resolution state.
+    ///
+    /// See [`Value::rendered()`] for details.
+    pub(crate) fn rendered_with_state(&self, root: &Mapping, state: ResolveState) -> Result<Self> {
+        let mut state = state;
+        let v = self
             .interpolate(root, &mut state)
             .map_err(|e| anyhow!("While resolving references: {e}"))?;
-        v.flatten()?;
-        Ok(v)
+        v.flattened_with(state.opts())
     }
 
     /// Renders the Value in-place.
@@ -730,23 +1013,61 @@ impl Value {
         Ok(())
     }
 
+    /// Renders the Value in-place, looking up Reclass references in the provided `root` Mapping.
+    ///
+    /// Use this method to render a value which was extracted from a larger parameter structure,
+    /// e.g. a fragment of a node's parameters, against the full parameter structure. Use
+    /// [`Value::render_with_self()`] to render a Mapping which is its own lookup root, e.g. a
+    /// node's full parameters.
+    ///
+    /// See [`Value::rendered()`] for details on how Reclass references are rendered.
+    pub fn render_against(&mut self, root: &Mapping) -> Result<()> {
+        self.render(root)
+    }
+
     /// Renders the Value in-place if it's a Mapping, using itself as the parameter lookup source.
     /// Returns an error when called for a Value variant other than `Value::Mapping`.
     ///
     /// See [`Value::rendered()`] for details on how Reclass references are rendered.
     pub fn render_with_self(&mut self) -> Result<()> {
+        self.render_with_self_state(ResolveState::default())
+    }
+
+    /// Renders the Value in-place with itself as the parameter lookup source, starting from the
+    /// provided initial resolution state.
+    ///
+    /// See [`Value::render_with_self()`] for details.
+    pub(crate) fn render_with_self_state(&mut self, state: ResolveState) -> Result<()> {
         let m = self.as_mapping().ok_or_else(|| {
             anyhow!(
                 "Can't render {} with itself as the parameter source",
                 self.variant()
             )
         })?;
-        let n = self.rendered(m)?;
+        let n = self.rendered_with_state(m, state)?;
         let _prev = std::mem::replace(self, n);
         Ok(())
     }
 }
 
+/// Result of a three-way merge, as computed by [`Value::merge3()`].
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MergeResult {
+    /// The merged Value
+    pub value: Value,
+    /// Leaves which were changed differently on both sides, as `:`-separated path together with
+    /// our and their value
+    pub conflicts: Vec<(String, Value, Value)>,
+}
+
+impl MergeResult {
+    /// Returns true if the merge didn't produce any conflicts.
+    #[must_use]
+    pub fn is_clean(&self) -> bool {
+        self.conflicts.is_empty()
+    }
+}
+
 #[cfg(test)]
 mod value_tests;
 
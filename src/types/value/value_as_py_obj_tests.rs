@@ -1,4 +1,5 @@
 use super::*;
+use std::str::FromStr;
 #[test]
 fn test_as_py_obj_null() {
     pyo3::prepare_freethreaded_python();
@@ -36,13 +37,10 @@ fn test_as_py_obj_int() {
 fn test_as_py_obj_float() {
     pyo3::prepare_freethreaded_python();
     Python::with_gil(|py| {
-        let n: Value = 3.14.into();
+        let n: Value = 2.5.into();
         let n = n.as_py_obj(py).unwrap();
         assert!(n.is_instance_of::<pyo3::types::PyFloat>());
-        assert!(n
-            .downcast_exact::<pyo3::types::PyFloat>()
-            .unwrap()
-            .eq(&3.14));
+        assert!(n.downcast_exact::<pyo3::types::PyFloat>().unwrap().eq(&2.5));
     });
 }
 
@@ -56,7 +54,7 @@ fn test_as_py_obj_sequence() {
         assert!(s
             .downcast_exact::<pyo3::types::PyList>()
             .unwrap()
-            .eq(&vec![1, 2, 3])
+            .eq(vec![1, 2, 3])
             .unwrap());
     });
 }
@@ -78,3 +76,30 @@ fn test_as_py_obj_string() {
         );
     });
 }
+
+#[test]
+fn test_as_py_obj_debug_value_list() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let m = Mapping::from_str("{foo: bar}").unwrap();
+        let v = Value::ValueList(vec![
+            vec![1, 2].into(),
+            Value::Mapping(m.clone()),
+            Value::ValueList(vec![3.into(), 4.into()]),
+        ]);
+        let l = v.as_py_obj_debug(py, false).unwrap();
+        let l = l.downcast_exact::<pyo3::types::PyList>().unwrap();
+        assert_eq!(l.len(), 3);
+        assert!(l.get_item(0).unwrap().eq(vec![1, 2]).unwrap());
+        assert!(l
+            .get_item(1)
+            .unwrap()
+            .eq(m.as_py_dict(py).unwrap())
+            .unwrap());
+        assert!(l.get_item(2).unwrap().eq(vec![3, 4]).unwrap());
+
+        let v = Value::ValueList(vec![vec![1].into(), vec![2].into()]);
+        let l = v.as_py_obj_debug(py, true).unwrap();
+        assert!(l.eq(vec![1, 2]).unwrap());
+    });
+}
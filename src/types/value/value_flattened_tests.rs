@@ -26,8 +26,8 @@ test_flattened_simple! {
     Bool,true,Value::Bool(true),
     Number,5.into(),Value::Number(5.into()),
     Literal,"foo".into(),Value::Literal("foo".into()),
-    Sequence,vec![Value::Bool(true), 3.14.into()],Value::Sequence(vec![Value::Bool(true), 3.14.into()]),
-    Mapping,Mapping::from_str("{foo: true, bar: 3.14}").unwrap(),Value::Mapping(Mapping::from_str("{foo: true, bar: 3.14}").unwrap())
+    Sequence,vec![Value::Bool(true), 2.5.into()],Value::Sequence(vec![Value::Bool(true), 2.5.into()]),
+    Mapping,Mapping::from_str("{foo: true, bar: 2.5}").unwrap(),Value::Mapping(Mapping::from_str("{foo: true, bar: 2.5}").unwrap())
 }
 
 #[test]
@@ -69,7 +69,7 @@ fn test_flattened_simple_value_list() {
 #[test]
 fn test_flattened_mixed_value_list() {
     let v = Value::ValueList(vec![
-        Value::Number(3.14.into()),
+        Value::Number(2.5.into()),
         Value::Null,
         Value::Literal("bar".into()),
     ]);
@@ -309,3 +309,76 @@ fn test_flatten_value_list() {
             .unwrap();
     assert_eq!(m, expected);
 }
+
+#[test]
+fn test_flattened_with_unique_sequences() {
+    let v = Value::ValueList(vec![
+        Value::Sequence(vec!["foo".into(), "bar".into()]),
+        Value::Sequence(vec!["baz".into(), "foo".into()]),
+        Value::Sequence(vec!["qux".into(), "bar".into()]),
+    ]);
+    let unique = RenderOpts {
+        unique_sequences: true,
+        ..Default::default()
+    };
+    let f = v.flattened_with(&unique).unwrap();
+    assert_eq!(
+        f,
+        Value::Sequence(vec!["foo".into(), "bar".into(), "baz".into(), "qux".into()])
+    );
+}
+
+#[test]
+fn test_flattened_with_unique_sequences_scalar_value_list() {
+    // ValueLists of scalars are flattened to the last value regardless of unique sequence merges
+    let v = Value::ValueList(vec![
+        Value::Literal("foo".into()),
+        Value::Literal("foo".into()),
+        Value::Literal("bar".into()),
+    ]);
+    let unique = RenderOpts {
+        unique_sequences: true,
+        ..Default::default()
+    };
+    assert_eq!(
+        v.flattened_with(&unique).unwrap(),
+        Value::Literal("bar".into())
+    );
+}
+
+#[test]
+fn test_flattened_scalar_over_mapping_error() {
+    let v = Value::ValueList(vec![
+        Value::Mapping(Mapping::from_str("{foo: bar}").unwrap()),
+        Value::Literal("foo".into()),
+    ]);
+    let err = v.flattened().unwrap_err();
+    assert_eq!(err.to_string(), "Can't merge Value::Literal over mapping");
+}
+
+#[test]
+fn test_flattened_with_type_changing_merge() {
+    let opts = RenderOpts {
+        type_changing_merge: true,
+        ..Default::default()
+    };
+    let v = Value::ValueList(vec![
+        Value::Mapping(Mapping::from_str("{foo: bar}").unwrap()),
+        Value::Literal("foo".into()),
+    ]);
+    assert_eq!(
+        v.flattened_with(&opts).unwrap(),
+        Value::Literal("foo".into())
+    );
+
+    let v = Value::ValueList(vec![
+        Value::Literal("foo".into()),
+        Value::Sequence(vec!["bar".into()]),
+        Value::Mapping(Mapping::from_str("{foo: bar}").unwrap()),
+        Value::Mapping(Mapping::from_str("{baz: qux}").unwrap()),
+    ]);
+    let f = v.flattened_with(&opts).unwrap();
+    let m: serde_yaml::Mapping = f.as_mapping().unwrap().clone().into();
+    let expected = serde_yaml::from_str("{foo: bar, baz: qux}").unwrap();
+    assert_eq!(m, expected);
+}
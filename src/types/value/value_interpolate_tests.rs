@@ -1,4 +1,5 @@
 use super::*;
+use crate::refs::RenderOpts;
 
 use std::str::FromStr;
 
@@ -16,7 +17,7 @@ fn sequence_literal(v: Vec<Value>) -> Value {
         .unwrap()
 }
 
-fn mapping_literal(m: Mapping) -> Value {
+fn mapping_literal(m: &Mapping) -> Value {
     Value::Mapping(m.render(&Mapping::new()).unwrap())
 }
 
@@ -81,16 +82,18 @@ fn test_extend_mapping() {
     o.insert(Value::String("m".into()), Value::Mapping(n))
         .unwrap();
 
-    let mut r = Mapping::new();
-    r.insert(Value::String("a".into()), Value::Bool(true))
+    let mut expected = Mapping::new();
+    expected
+        .insert(Value::String("a".into()), Value::Bool(true))
         .unwrap();
-    r.insert(Value::String("b".into()), Value::Bool(true))
+    expected
+        .insert(Value::String("b".into()), Value::Bool(true))
         .unwrap();
 
     p.merge(&o).unwrap();
     p = p.render(&p).unwrap();
 
-    assert_eq!(p.get(&"m".into()).unwrap(), &Value::Mapping(r));
+    assert_eq!(p.get(&"m".into()).unwrap(), &Value::Mapping(expected));
 }
 
 #[test]
@@ -241,136 +244,136 @@ fn test_nested_ref() {
 #[test]
 fn test_merge_over_ref() {
     let mut p = Mapping::new();
-    let base = r#"
+    let base = r"
     foodict:
       bar: bar
       baz: baz
       qux: qux
-    foo: ${foodict}"#;
+    foo: ${foodict}";
     let base = Mapping::from_str(base).unwrap();
     p.merge(&base).unwrap();
 
-    let overlay = r#"
+    let overlay = r"
     foo:
-      bar: barer"#;
+      bar: barer";
     let overlay = Mapping::from_str(overlay).unwrap();
     p.merge(&overlay).unwrap();
 
     p = p.render(&p).unwrap();
     dbg!(&p);
 
-    let merged_foo = r#"
+    let merged_foo = r"
     bar: barer
     baz: baz
-    qux: qux"#;
+    qux: qux";
     let merged_foo = Mapping::from_str(merged_foo).unwrap();
     dbg!(&merged_foo);
 
-    assert_eq!(p.get(&"foo".into()).unwrap(), &mapping_literal(merged_foo));
+    assert_eq!(p.get(&"foo".into()).unwrap(), &mapping_literal(&merged_foo));
 }
 
 #[test]
 fn test_merge_over_ref_nested() {
     let mut p = Mapping::new();
-    let base = r#"
+    let base = r"
     foodict:
       bar: bar
       baz: baz
       qux: qux
     some:
-      foo: ${foodict}"#;
+      foo: ${foodict}";
     let base = Mapping::from_str(base).unwrap();
     p.merge(&base).unwrap();
 
-    let overlay = r#"
+    let overlay = r"
     some:
       foo:
-        bar: barer"#;
+        bar: barer";
     let overlay = Mapping::from_str(overlay).unwrap();
 
     p.merge(&overlay).unwrap();
     p = p.render(&p).unwrap();
 
-    let merged_some = r#"
+    let merged_some = r"
     foo:
       bar: barer
       baz: baz
-      qux: qux"#;
+      qux: qux";
     let merged_some = Mapping::from_str(merged_some).unwrap();
 
     assert_eq!(
         p.get(&"some".into()).unwrap(),
-        &mapping_literal(merged_some)
+        &mapping_literal(&merged_some)
     );
 }
 
 #[test]
 fn test_merge_over_null() {
     let mut p = Mapping::new();
-    let base = r#"
+    let base = r"
     foodict:
       bar: bar
       baz: baz
       qux: qux
     some:
-      foo: null"#;
+      foo: null";
     let base = Mapping::from_str(base).unwrap();
     p.merge(&base).unwrap();
 
-    let overlay = r#"
+    let overlay = r"
     some:
       foo:
-        bar: barer"#;
+        bar: barer";
     let overlay = Mapping::from_str(overlay).unwrap();
 
     p.merge(&overlay).unwrap();
     p = p.render(&p).unwrap();
 
-    let merged_some = r#"
+    let merged_some = r"
     foo:
-      bar: barer"#;
+      bar: barer";
     let merged_some = Mapping::from_str(merged_some).unwrap();
 
     assert_eq!(
         p.get(&"some".into()).unwrap(),
-        &mapping_literal(merged_some)
+        &mapping_literal(&merged_some)
     );
 }
 
 #[test]
 fn test_merge_null() {
     let mut p = Mapping::new();
-    let base = r#"
+    let base = r"
     some:
       foo:
         bar: bar
         baz: baz
-        qux: qux"#;
+        qux: qux";
     let base = Mapping::from_str(base).unwrap();
     p.merge(&base).unwrap();
 
-    let overlay = r#"
+    let overlay = r"
     some:
-      foo: null"#;
+      foo: null";
     let overlay = Mapping::from_str(overlay).unwrap();
 
     p.merge(&overlay).unwrap();
     p = p.render(&p).unwrap();
 
-    let merged_some = r#"
-    foo: null"#;
+    let merged_some = r"
+    foo: null";
     let merged_some = Mapping::from_str(merged_some).unwrap();
 
     assert_eq!(
         p.get(&"some".into()).unwrap(),
-        &mapping_literal(merged_some)
+        &mapping_literal(&merged_some)
     );
 }
 
 #[test]
 fn test_merge_interpolate_embedded_nested_ref() {
     let mut p = Mapping::new();
-    let base = r#"
+    let base = r"
     foo:
       bar:
         baz: baz
@@ -380,16 +383,16 @@ fn test_merge_interpolate_embedded_nested_ref() {
         release-1.21: foo-1.22
         release-1.22: foo-1.22
         release-1.23: foo-1.22
-    "#;
+    ";
     let base = Mapping::from_str(base).unwrap();
     p.merge(&base).unwrap();
 
-    let config1 = r#"
+    let config1 = r"
     version: release-1.21
     foo:
       bar:
         baz: baz-${bar:foo:${version}}
-    "#;
+    ";
     let config1 = Mapping::from_str(config1).unwrap();
     p.merge(&config1).unwrap();
 
@@ -415,11 +418,11 @@ fn test_merge_interpolate_embedded_nested_ref() {
 
 #[test]
 fn test_interpolate_duplicate_ref_no_loop() {
-    let base = r#"
+    let base = r"
     foo:
       bar: ${baz}-${baz}
     baz: baz
-    "#;
+    ";
     let base = Mapping::from_str(base).unwrap();
 
     let p = base.render(&base).unwrap();
@@ -431,13 +434,13 @@ fn test_interpolate_duplicate_ref_no_loop() {
 
 #[test]
 fn test_interpolate_sequence_duplicate_ref_no_loop() {
-    let base = r#"
+    let base = r"
     foo:
       bar:
       - ${baz}
       - ${baz}
     baz: baz
-    "#;
+    ";
     let base = Mapping::from_str(base).unwrap();
 
     let p = base.render(&base).unwrap();
@@ -449,7 +452,7 @@ fn test_interpolate_sequence_duplicate_ref_no_loop() {
 
 #[test]
 fn test_interpolate_nested_mapping_no_loop() {
-    let base = r#"
+    let base = r"
     foo:
       bar:
         baz: ${foo:baz:bar}
@@ -457,7 +460,7 @@ fn test_interpolate_nested_mapping_no_loop() {
       baz:
         bar: qux
         qux: ${foo:bar:qux}
-    "#;
+    ";
     let base = Mapping::from_str(base).unwrap();
 
     let p = base.render(&base).unwrap();
@@ -473,18 +476,18 @@ fn test_interpolate_nested_mapping_no_loop() {
     expected = "While resolving references: Detected reference loop with reference paths [\"bar\", \"foo\"]."
 )]
 fn test_merge_interpolate_loop() {
-    let base = r#"
+    let base = r"
     foo:
       bar: ${bar}
     bar:
       baz: baz
       qux: qux
-    "#;
+    ";
     let base = Mapping::from_str(base).unwrap();
-    let config1 = r#"
+    let config1 = r"
     bar:
       baz: ${foo}
-    "#;
+    ";
     let config1 = Mapping::from_str(config1).unwrap();
 
     let mut p = Mapping::new();
@@ -499,7 +502,7 @@ fn test_merge_interpolate_loop() {
 #[should_panic(expected = "While resolving references: \
     Detected reference loop with reference paths [\"baz\", \"foo\"].")]
 fn test_interpolate_sequence_loop() {
-    let base = r#"
+    let base = r"
     foo:
       bar:
       - ${bar}
@@ -507,8 +510,8 @@ fn test_interpolate_sequence_loop() {
     bar: ${qux}
     baz:
       bar: ${foo}
-    qux: 3.14
-    "#;
+    qux: 2.5
+    ";
     let base = Mapping::from_str(base).unwrap();
 
     let mut v = Value::from(base);
@@ -519,7 +522,7 @@ fn test_interpolate_sequence_loop() {
 #[should_panic(expected = "While resolving references: \
     Detected reference loop with reference paths [\"foo:bar:qux\", \"foo:baz:qux\", \"foo:qux:foo\"]")]
 fn test_interpolate_nested_mapping_loop() {
-    let m = r#"
+    let m = r"
     foo:
       bar:
         baz: ${foo:baz:bar}
@@ -529,7 +532,7 @@ fn test_interpolate_nested_mapping_loop() {
         qux: ${foo:bar:qux}
       qux:
         foo: ${foo:baz:qux}
-    "#;
+    ";
     let m = Mapping::from_str(m).unwrap();
 
     let mut v = Value::from(m);
@@ -556,3 +559,67 @@ fn test_interpolate_depth_exceeded() {
     let mut v = Value::from(map);
     v.render_with_self().unwrap();
 }
+
+#[test]
+fn test_interpolate_render_opts_max_depth() {
+    // Construct a chain of references `k0: ${k1}`, `k1: ${k2}`, ..., `k{depth}: end`.
+    let chain = |depth: usize| {
+        let mut map = Mapping::new();
+        for i in 0..depth {
+            map.insert(format!("k{i}").into(), format!("${{k{}}}", i + 1).into())
+                .unwrap();
+        }
+        map.insert(format!("k{depth}").into(), "end".into())
+            .unwrap();
+        Value::from(map)
+    };
+    let opts = RenderOpts {
+        max_depth: 4,
+        ..Default::default()
+    };
+
+    // A chain of 10 references renders fine with the default options
+    chain(10)
+        .render_with_self_state(ResolveState::with_opts(RenderOpts::default()))
+        .unwrap();
+
+    let err = chain(10)
+        .render_with_self_state(ResolveState::with_opts(opts))
+        .unwrap_err();
+    assert!(format!("{err:#}")
+        .contains("Token resolution exceeded recursion depth of 4 for parameter 'k"));
+
+    chain(2)
+        .render_with_self_state(ResolveState::with_opts(opts))
+        .unwrap();
+}
+
+#[test]
+fn test_render_against_root() {
+    let root = r"
+    cluster:
+      name: c-test
+      domain: ${cluster:name}.example.com
+    app:
+      url: https://app.${cluster:domain}
+      name: ${cluster:name}-app
+    ";
+    let root = Mapping::from_str(root).unwrap();
+
+    // Extract a fragment from the parameters, and render it against the full parameters. The
+    // fragment itself doesn't contain key `cluster`, so it can't be rendered with itself as the
+    // lookup root.
+    let mut v = root.get(&"app".into()).unwrap().clone();
+    assert!(v.clone().render_with_self().is_err());
+    v.render_against(&root).unwrap();
+
+    let expected = Mapping::from_str(
+        r"
+    url: https://app.c-test.example.com
+    name: c-test-app
+    ",
+    )
+    .unwrap();
+    let expected = Value::Mapping(expected).rendered(&Mapping::new()).unwrap();
+    assert_eq!(v, expected);
+}
@@ -44,7 +44,7 @@ macro_rules! test_number {
         )*
     }
 }
-test_number! { u64 5 i64 -3 f64 3.14 }
+test_number! { u64 5 i64 -3 f64 2.5 }
 
 #[test]
 fn test_is_string() {
@@ -182,14 +182,50 @@ fn test_get_mut_mapping_const_key() {
     assert!(m.get_mut(&"a".into()).is_err());
 }
 
+#[test]
+fn test_get_path_mut() {
+    let mut v = Value::from(Mapping::from_str("{a: {b: {c: foo}, s: [x, {y: z}]}}").unwrap());
+
+    let c = v.get_path_mut("a:b:c").unwrap().unwrap();
+    *c = "bar".into();
+    assert_eq!(
+        v.get(&"a".into())
+            .and_then(|a| a.get(&"b".into()))
+            .and_then(|b| b.get(&"c".into())),
+        Some(&"bar".into())
+    );
+
+    let y = v.get_path_mut("a:s:1:y").unwrap().unwrap();
+    *y = "qux".into();
+    assert_eq!(
+        v.get(&"a".into())
+            .and_then(|a| a.get(&"s".into()))
+            .and_then(|s| s.get(&1.into()))
+            .and_then(|e| e.get(&"y".into())),
+        Some(&"qux".into())
+    );
+
+    assert_eq!(v.get_path_mut("a:b:missing").unwrap(), None);
+    assert_eq!(v.get_path_mut("a:s:2").unwrap(), None);
+    assert_eq!(v.get_path_mut("a:b:c:d").unwrap(), None);
+}
+
+#[test]
+fn test_get_path_mut_const_key() {
+    let mut v = Value::from(Mapping::from_str("{a: {=b: {c: foo}}}").unwrap());
+    let err = v.get_path_mut("a:b:c").unwrap_err();
+    assert_eq!(err.to_string(), "Key \"b\" is marked constant");
+    assert!(v.get_path_mut("a").unwrap().is_some());
+}
+
 #[test]
 fn test_get_sequence() {
-    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 3.14.into()]);
+    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 2.5.into()]);
     let s = Value::from(s);
 
     // non-u64 and out of bounds accesses return None
     assert_eq!(s.get(&(-1).into()), None);
-    assert_eq!(s.get(&3.14.into()), None);
+    assert_eq!(s.get(&2.5.into()), None);
     assert_eq!(s.get(&3.into()), None);
 
     // non-number accesses return None
@@ -197,12 +233,12 @@ fn test_get_sequence() {
 
     assert_eq!(s.get(&0.into()), Some(&"a".into()));
     assert_eq!(s.get(&1.into()), Some(&2.into()));
-    assert_eq!(s.get(&2.into()), Some(&3.14.into()));
+    assert_eq!(s.get(&2.into()), Some(&2.5.into()));
 }
 
 #[test]
 fn test_get_mut_sequence() {
-    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 3.14.into()]);
+    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 2.5.into()]);
     let mut s = Value::from(s);
 
     assert_eq!(s.get(&0.into()), Some(&"a".into()));
@@ -214,12 +250,12 @@ fn test_get_mut_sequence() {
 
 #[test]
 fn test_get_valuelist() {
-    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 3.14.into()]);
+    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 2.5.into()]);
     let l = Value::ValueList(s);
 
     // non-u64 and out of bounds accesses return None
     assert_eq!(l.get(&(-1).into()), None);
-    assert_eq!(l.get(&3.14.into()), None);
+    assert_eq!(l.get(&2.5.into()), None);
     assert_eq!(l.get(&3.into()), None);
 
     // non-number accesses return None
@@ -227,12 +263,12 @@ fn test_get_valuelist() {
 
     assert_eq!(l.get(&0.into()), Some(&"a".into()));
     assert_eq!(l.get(&1.into()), Some(&2.into()));
-    assert_eq!(l.get(&2.into()), Some(&3.14.into()));
+    assert_eq!(l.get(&2.into()), Some(&2.5.into()));
 }
 
 #[test]
 fn test_get_mut_valuelist() {
-    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 3.14.into()]);
+    let s = Sequence::from_iter(vec!["a".into(), 2.into(), 2.5.into()]);
     let mut l = Value::ValueList(s);
 
     assert_eq!(l.get(&0.into()), Some(&"a".into()));
@@ -279,6 +315,7 @@ fn test_strip_prefix() {
     let k2 = Value::from("~foo");
     let k3 = Value::from("foo");
     let k4 = Value::from(3);
+    let k5 = Value::from("+foo");
     assert_eq!(
         k1.strip_prefix(),
         (Value::from("foo"), Some(KeyPrefix::Constant))
@@ -289,6 +326,10 @@ fn test_strip_prefix() {
     );
     assert_eq!(k3.strip_prefix(), (Value::from("foo"), None));
     assert_eq!(k4.strip_prefix(), (Value::from(3), None));
+    assert_eq!(
+        k5.strip_prefix(),
+        (Value::from("foo"), Some(KeyPrefix::Append))
+    );
 }
 
 #[test]
@@ -315,8 +356,8 @@ fn test_raw_string_number() {
         "-1".to_string()
     );
     assert_eq!(
-        Value::Number(3.14.into()).raw_string().unwrap(),
-        "3.14".to_string()
+        Value::Number(2.5.into()).raw_string().unwrap(),
+        "2.5".to_string()
     );
     assert_eq!(
         Value::Number(serde_yaml::Number::from(f64::INFINITY))
@@ -350,19 +391,203 @@ fn test_raw_string_mapping() {
 
 #[test]
 fn test_raw_string_sequence() {
-    let v = Value::Sequence(vec!["foo".into(), 3.14.into(), Value::Bool(true)]);
+    let v = Value::Sequence(vec!["foo".into(), 2.5.into(), Value::Bool(true)]);
     let vstr = v.raw_string().unwrap();
-    assert_eq!(vstr, r#"["foo",3.14,true]"#);
+    assert_eq!(vstr, r#"["foo",2.5,true]"#);
 }
 
 #[test]
 fn test_raw_string_mapping_nonstring_keys() {
     // raw_string() will turn boolean, number, and null values used as keys into strings when
     // serializing the Mapping as JSON.
-    let m = Mapping::from_str("{true: foo, 3.14: true, ~: 1.23}").unwrap();
+    let m = Mapping::from_str("{true: foo, 2.5: true, ~: 1.23}").unwrap();
     // turn string values into literals by calling interpolate
     let m = Value::Mapping(m).rendered(&Mapping::new()).unwrap();
     let mstr = m.raw_string().unwrap();
     // NOTE(sg): serde_json output is sorted by keys
-    assert_eq!(mstr, r#"{"3.14":true,"null":1.23,"true":"foo"}"#);
+    assert_eq!(mstr, r#"{"2.5":true,"null":1.23,"true":"foo"}"#);
+}
+
+#[test]
+fn test_template_from_yaml() {
+    let m = Mapping::from_str(r#"{foo: !template "${x}", x: bar}"#).unwrap();
+    assert_eq!(m.get(&"foo".into()), Some(&Value::Template("${x}".into())));
+    assert!(m.get(&"foo".into()).unwrap().is_template());
+    assert_eq!(m.get(&"foo".into()).unwrap().as_template(), Some("${x}"));
+}
+
+#[test]
+fn test_template_from_yaml_non_string() {
+    let m = Mapping::from_str(
+        "{a: !template 42, b: !template true, c: !template , d: !template [x, y]}",
+    )
+    .unwrap();
+    assert_eq!(m.get(&"a".into()), Some(&Value::Template("42".into())));
+    assert_eq!(m.get(&"b".into()), Some(&Value::Template("true".into())));
+    assert_eq!(m.get(&"c".into()), Some(&Value::Template(String::new())));
+    assert_eq!(
+        m.get(&"d".into()),
+        Some(&Value::Template("- x\n- y".into()))
+    );
+}
+
+#[test]
+fn test_template_not_interpolated() {
+    let m = Mapping::from_str(r#"{foo: !template "${x}", x: bar}"#).unwrap();
+    let m = Value::Mapping(m.clone()).rendered(&m).unwrap();
+    let m = m.as_mapping().unwrap();
+    assert_eq!(m.get(&"foo".into()), Some(&Value::Template("${x}".into())));
+    assert_eq!(m.get(&"x".into()), Some(&Value::Literal("bar".into())));
+}
+
+#[test]
+fn test_template_json_output() {
+    let v = Value::Template("${x}".into());
+    assert_eq!(v.raw_string().unwrap(), "${x}");
+    let j = serde_json::Value::from(v);
+    assert_eq!(j.to_string(), r#"{"!template":"${x}"}"#);
+}
+
+#[test]
+fn test_template_yaml_output() {
+    let v = Value::Template("${x}".into());
+    let y = serde_yaml::Value::from(v);
+    assert_eq!(serde_yaml::to_string(&y).unwrap(), "!template ${x}\n");
+    assert_eq!(Value::from(y), Value::Template("${x}".into()));
+}
+
+fn merge3_values(base: &str, ours: &str, theirs: &str) -> MergeResult {
+    let base = Value::from(serde_yaml::from_str::<serde_yaml::Value>(base).unwrap());
+    let ours = Value::from(serde_yaml::from_str::<serde_yaml::Value>(ours).unwrap());
+    let theirs = Value::from(serde_yaml::from_str::<serde_yaml::Value>(theirs).unwrap());
+    Value::merge3(
+        &base.rendered(&Mapping::new()).unwrap(),
+        &ours.rendered(&Mapping::new()).unwrap(),
+        &theirs.rendered(&Mapping::new()).unwrap(),
+    )
+    .unwrap()
+}
+
+#[test]
+fn test_merge3_non_conflicting() {
+    let base = r"
+    a: 1
+    b:
+      c: foo
+      d: [1, 2]
+    e: removed
+    ";
+    let ours = r"
+    a: 2
+    b:
+      c: foo
+      d: [1, 2]
+    e: removed
+    f: added
+    ";
+    let theirs = r"
+    a: 1
+    b:
+      c: bar
+      d: [1, 2, 3]
+    ";
+    let res = merge3_values(base, ours, theirs);
+    assert!(res.is_clean());
+    let expected = r"
+    a: 2
+    b:
+      c: bar
+      d: [1, 2, 3]
+    f: added
+    ";
+    let expected = Value::from(serde_yaml::from_str::<serde_yaml::Value>(expected).unwrap());
+    assert_eq!(res.value, expected.rendered(&Mapping::new()).unwrap());
+}
+
+#[test]
+fn test_merge3_same_change() {
+    let res = merge3_values("a: 1", "a: 2", "a: 2");
+    assert!(res.is_clean());
+    assert_eq!(res.value.get(&"a".into()), Some(&Value::from(2)));
+}
+
+#[test]
+fn test_merge3_conflict() {
+    let base = r"
+    a: 1
+    b:
+      c: foo
+      d: x
+    ";
+    let ours = r"
+    a: 2
+    b:
+      c: bar
+    ";
+    let theirs = r"
+    a: 3
+    b:
+      c: foo
+      d: y
+    ";
+    let res = merge3_values(base, ours, theirs);
+    assert!(!res.is_clean());
+    assert_eq!(
+        res.conflicts,
+        vec![
+            ("a".to_string(), Value::from(2), Value::from(3)),
+            ("b:d".to_string(), Value::Null, Value::Literal("y".into())),
+        ]
+    );
+    // Conflicts are resolved with our value, non-conflicting changes are merged
+    let expected = r"
+    a: 2
+    b:
+      c: bar
+    ";
+    let expected = Value::from(serde_yaml::from_str::<serde_yaml::Value>(expected).unwrap());
+    assert_eq!(res.value, expected.rendered(&Mapping::new()).unwrap());
+}
+
+#[test]
+fn test_merge3_unrendered_error() {
+    let err = Value::merge3(&Value::Null, &"${foo}".into(), &Value::Null).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "Can't three-way merge Value::String at '', did you mean to render the values first?"
+    );
+}
+
+#[test]
+fn test_content_hash_string_literal() {
+    let s = Value::String("x".into());
+    let l = Value::Literal("x".into());
+    assert_eq!(s.content_hash(), l.content_hash());
+    assert_ne!(s.content_hash(), Value::Template("x".into()).content_hash());
+    assert_ne!(l.content_hash(), Value::Literal("y".into()).content_hash());
+}
+
+#[test]
+fn test_content_hash_sequence_value_list() {
+    let s = Value::Sequence(vec![1.into(), "x".into()]);
+    let l = Value::ValueList(vec![1.into(), Value::Literal("x".into())]);
+    assert_eq!(s.content_hash(), l.content_hash());
+    let r = Value::Sequence(vec!["x".into(), 1.into()]);
+    assert_ne!(s.content_hash(), r.content_hash());
+}
+
+#[test]
+fn test_content_hash_mapping() {
+    let a = Mapping::from_str("{foo: [a, b], bar: {baz: 1}}").unwrap();
+    let b = Mapping::from_str("{bar: {baz: 1}, foo: [a, b]}").unwrap();
+    let mut c = Mapping::new();
+    c.insert("foo".into(), Value::Literal("a".into())).unwrap();
+    assert_eq!(
+        Value::Mapping(a.clone()).content_hash(),
+        Value::Mapping(b).content_hash()
+    );
+    assert_ne!(
+        Value::Mapping(a).content_hash(),
+        Value::Mapping(c).content_hash()
+    );
 }
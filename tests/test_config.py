@@ -42,3 +42,21 @@ def test_config_from_dict_non_default():
     assert pathlib.Path(c.classes_path) == expected_classes_path
 
     assert c.ignore_class_notfound_regexp == ["foo", "bar"]
+
+
+def test_config_effective():
+    config_options = {
+        "nodes_uri": "nodes",
+        "classes_uri": "classes",
+        "ignore_class_notfound": True,
+        "compose_node_name": True,
+    }
+    c = reclass_rs.Config.from_dict("./tests/inventory", config_options)
+    eff = c.effective()
+
+    assert pathlib.Path(eff["nodes_path"]) == pathlib.Path("./tests/inventory/nodes").absolute()
+    assert pathlib.Path(eff["classes_path"]) == pathlib.Path("./tests/inventory/classes").absolute()
+    assert eff["ignore_class_notfound"]
+    assert eff["compose_node_name"]
+    assert eff["ignore_class_notfound_patterns"] == [".*"]
+    assert eff["compatflags"] == []
@@ -35,3 +35,35 @@ def test_ignore_regexp_update_config_render_n2():
 
     n2 = r.nodeinfo("n2")
     assert n2 is not None
+
+
+def test_ignore_regexp_from_dict():
+    config_options = {
+        "nodes_uri": "nodes",
+        "classes_uri": "classes",
+        "ignore_class_notfound": True,
+        "ignore_class_notfound_regexp": ["service.*", "*missing*"],
+        "ignore_class_notfound_glob": True,
+    }
+    c = reclass_rs.Config.from_dict(
+        "./tests/inventory-class-notfound-regexp", config_options
+    )
+    r = reclass_rs.Reclass.from_config(c)
+
+    n1 = r.nodeinfo("n1")
+    assert n1 is not None
+
+    with pytest.raises(
+        ValueError, match="Error while rendering n2: Class foo not found"
+    ):
+        n2 = r.nodeinfo("n2")
+
+
+def test_ignore_regexp_from_dict_invalid():
+    config_options = {
+        "ignore_class_notfound_regexp": ["("],
+    }
+    with pytest.raises(ValueError, match="ignore_class_notfound regex patterns"):
+        reclass_rs.Config.from_dict(
+            "./tests/inventory-class-notfound-regexp", config_options
+        )
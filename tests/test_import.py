@@ -27,4 +27,4 @@ def test_import_raises():
     with pytest.raises(ValueError) as exc:
         r = reclass_rs.Reclass("./inventory", "foo", "bar")
 
-    assert "Error while discovering nodes" in str(exc.value)
+    assert "Nodes path './inventory/foo' doesn't exist" in str(exc.value)
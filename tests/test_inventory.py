@@ -1,3 +1,5 @@
+import pytest
+
 import reclass_rs
 
 expected_classes = {
@@ -57,7 +59,7 @@ expected_applications = {
     "app1": ["n1"],
     "app2": ["n1"],
     "a": ["n12", "n13"],
-    "b": ["n13"],
+    "b": ["n12", "n13"],
     "c": ["n12"],
     "d": ["n13"],
 }
@@ -76,6 +78,10 @@ def test_inventory():
         "${baz}",  # appears as `\\${baz}`
         "cluster.foo",  # appears as `cluster.${dist}`
         "config_symlink",  # doesn't appear
+        "cycle.a",  # doesn't appear
+        "cycle.b",  # doesn't appear
+        "duplicate-key",  # doesn't appear
+        "instance",  # doesn't appear
         "foo.bar",  # appears as `${tenant}.${cluster}`
     }
     # class includes which aren't shown in their resolved form:
@@ -114,6 +120,61 @@ def test_inventory_as_dict():
     assert set(inv["__reclass__"].keys()) == set(["timestamp"])
 
 
+def test_inventory_nodes_with_class_and_application():
+    r = reclass_rs.Reclass.from_config_file("./tests/inventory", "reclass-config.yml")
+    inv = r.inventory()
+
+    assert inv.nodes_with_class("cls9") == expected_classes["cls9"]
+    assert inv.nodes_with_application("a") == ["n12", "n13"]
+    assert inv.nodes_with_class("missing") == []
+    assert inv.nodes_with_application("missing") == []
+
+
+def test_inventory_local_thread_count():
+    r = reclass_rs.Reclass.from_config_file("./tests/inventory", "reclass-config.yml")
+    r.set_local_thread_count(1)
+    assert r.config.thread_count == 1
+    inv1 = r.inventory()
+    r.set_local_thread_count(4)
+    assert r.config.thread_count == 4
+    inv4 = r.inventory()
+    r.set_local_thread_count(None)
+    assert r.config.thread_count is None
+
+    assert inv1.classes == inv4.classes
+    assert inv1.applications == inv4.applications
+    assert set(inv1.nodes.keys()) == set(inv4.nodes.keys())
+
+
+def test_reclass_entities():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    entities = r.entities()
+
+    assert len(entities) == len(r.nodes) + len(r.classes)
+    assert {name for (name, kind, _) in entities if kind == "node"} == expected_nodes
+    assert {name for (name, kind, _) in entities if kind == "class"} == set(
+        r.classes.keys()
+    )
+    assert ("n1", "node", "n1.yml") in [(n, k, str(p)) for (n, k, p) in entities]
+
+
+def test_reclass_entity_location():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    # Locations are returned as strings, the inventory root is the empty string
+    assert r.node_location("n1") == ""
+    assert r.node_location("missing") is None
+    assert r.class_location("nested.cls1") == "nested"
+    assert r.class_location("missing") is None
+
+
+def test_reclass_node_references():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert r.node_references("n11") == ["bar:${foo}", "foo", "bar:foo_int"]
+    assert r.node_references("n1") == []
+    with pytest.raises(ValueError):
+        r.node_references("missing")
+
+
 def test_reclass_from_config():
     config_options = {
         "nodes_uri": "targets",
@@ -135,6 +196,10 @@ def test_reclass_from_config():
         "${baz}",  # appears as `\\${baz}`
         "cluster.foo",  # appears as `cluster.${dist}`
         "config_symlink",  # doesn't appear
+        "cycle.a",  # doesn't appear
+        "cycle.b",  # doesn't appear
+        "duplicate-key",  # doesn't appear
+        "instance",  # doesn't appear
         "foo.bar",  # appears as `${tenant}.${cluster}`
     }
     # class includes which aren't shown in their resolved form:
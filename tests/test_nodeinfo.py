@@ -25,6 +25,15 @@ def test_nodeinfo_n1():
     }
 
 
+def test_nodeinfo_parameter():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    n = r.nodeinfo("n1")
+    assert n.parameter("foo:bar") == "cls2"
+    assert n.parameter("bar") == {"foo": "foo"}
+    assert n.parameter("_reclass_:name:parts:0") == "n1"
+    assert n.parameter("missing") is None
+
+
 def test_nodeinfo_n1_no_invpath():
     r = reclass_rs.Reclass(
         nodes_path="./tests/inventory/nodes", classes_path="./tests/inventory/classes"
@@ -81,6 +90,7 @@ def test_nodeinfo_n3():
     n = r.nodeinfo("n3")
     assert n.applications == []
     assert n.classes == ["cls4", "cls5", "cls6", "cls3"]
+    assert n.class_load_order == ["cls4", "cls5", "cls6", "cls3"]
     assert n.parameters == {
         "_reclass_": {
             "environment": "base",
@@ -121,3 +131,76 @@ def test_nodeinfo_n4():
         "qux": "cls1",
         "foo": {"foo": "cls1", "bar": "cls1", "baz": "cls1"},
     }
+
+
+def test_nodeinfo_from_str():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    n = r.nodeinfo_from_str(
+        "generated", "classes:\n  - cls1\nparameters:\n  foo:\n    bar: generated\n"
+    )
+    assert n.__reclass__.uri == ""
+    assert n.classes == ["cls1"]
+    assert n.parameters == {
+        "_reclass_": {
+            "environment": "base",
+            "name": {
+                "full": "generated",
+                "parts": ["generated"],
+                "path": "generated",
+                "short": "generated",
+            },
+        },
+        "foo": {"foo": "cls1", "bar": "generated", "baz": "cls1"},
+    }
+
+
+def test_node_exists():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert r.node_exists("n1")
+    assert not r.node_exists("n1000")
+
+
+def test_class_exists():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert r.class_exists("nested.cls1")
+    assert r.class_exists(".cls1")
+    assert not r.class_exists("missing")
+
+
+def test_nodeinfo_warnings():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert r.nodeinfo("n1").warnings == []
+
+
+def test_nodeinfo_parameter_docs():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert not r.config.extract_parameter_docs
+    assert r.nodeinfo("n15").parameter_docs == {}
+
+
+def test_nodeinfo_constant_parameters():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert r.nodeinfo("n1").constant_parameters == []
+
+
+def test_nodeinfo_sorted_classes_and_applications():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    n = r.nodeinfo("n12")
+    assert n.applications == ["c", "a", "b"]
+    assert n.sorted_applications() == ["a", "b", "c"]
+    assert n.sorted_classes() == sorted(n.classes)
+
+
+def test_nodeinfo_parameter_sources():
+    r = reclass_rs.Reclass(inventory_path="./tests/inventory")
+    assert not r.config.track_parameter_sources
+    assert r.nodeinfo("n1").parameter_sources == {}
+
+    c = reclass_rs.Config.from_dict(
+        "./tests/inventory", {"track_parameter_sources": True}
+    )
+    r = reclass_rs.Reclass.from_config(c)
+    sources = r.nodeinfo("n1").parameter_sources
+    assert sources["foo:bar"] == "cls2"
+    assert sources["foo:baz"] == "cls1"
+    assert sources["foo:foo"] == "n1"